@@ -0,0 +1,58 @@
+//! Bytes allocated when a notification carrying a 1MB image is cloned, as it is into every handle.
+//!
+//! Run with `cargo bench --features images --bench image_notification_clone`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use notify_rust::{Image, Notification};
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ROUNDS: u32 = 1000;
+
+fn main() {
+    // 512 * 683 * 3 bytes, just over 1MB
+    let image = Image::from_rgb(512, 683, vec![127; 512 * 683 * 3]).unwrap();
+    let notification = Notification::new()
+        .summary("Album art")
+        .body("a notification with a large image hint")
+        .image_data(image)
+        .finalize();
+
+    // keep the clones around, so none of them can be optimized away
+    let mut clones = Vec::with_capacity(ROUNDS as usize);
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        clones.push(notification.clone());
+    }
+    let elapsed = started.elapsed();
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(clones);
+
+    println!(
+        "clone with a 1MB image: {} bytes allocated, {:?} per clone",
+        allocated / ROUNDS as usize,
+        elapsed / ROUNDS
+    );
+}
@@ -0,0 +1,74 @@
+//! Bytes allocated when a notification carrying a 1MB image is sent through zbus.
+//!
+//! Compares the send against serializing the same hints as [`zvariant::Value`]s, as they used to be sent.
+//! Needs a session bus, nothing has to answer the calls:
+//! `dbus-run-session cargo bench --features images --bench image_zbus_serialize`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use notify_rust::{build_hint_map, Image, Notification};
+use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ROUNDS: u32 = 100;
+
+/// Runs `round` [`ROUNDS`] times, returning the bytes allocated by each.
+fn measure(mut round: impl FnMut()) -> (usize, std::time::Duration) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        round();
+    }
+    let elapsed = started.elapsed();
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+    (allocated / ROUNDS as usize, elapsed / ROUNDS)
+}
+
+fn main() {
+    // 512 * 683 * 3 bytes, just over 1MB
+    let image = Image::from_rgb(512, 683, vec![127; 512 * 683 * 3]).unwrap();
+    let notification = Notification::new()
+        .summary("Album art")
+        .body("a notification with a large image hint")
+        .image_data(image)
+        .finalize();
+
+    // connect before measuring
+    zbus::block_on(notification.show_nowait_async()).unwrap();
+    let (sent, sent_time) = measure(|| {
+        zbus::block_on(notification.show_nowait_async()).unwrap();
+    });
+    let (values, values_time) = measure(|| {
+        let hints = build_hint_map(&notification);
+        to_bytes(Context::new_dbus(LE, 0), &hints).unwrap();
+    });
+    println!(
+        "send with a 1MB image: {} bytes allocated, {:?} per send",
+        sent, sent_time
+    );
+    println!(
+        "the same hints as zvariant::Value: {} bytes allocated, {:?} per serialization",
+        values, values_time
+    );
+}
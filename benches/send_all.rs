@@ -0,0 +1,58 @@
+//! A burst of 20 notifications, shown one after another against sent as one batch.
+//!
+//! Needs a session bus without a notification server, an embedded one answers the calls:
+//! `dbus-run-session cargo bench --bench send_all`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use notify_rust::Notification;
+
+const BURST: usize = 20;
+const ROUNDS: u32 = 20;
+
+async fn one_by_one(notifications: &[Notification]) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        for notification in notifications {
+            notification.show_async().await.unwrap();
+        }
+    }
+    started.elapsed() / ROUNDS
+}
+
+async fn batched(notifications: &[Notification]) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        for id in notify_rust::send_all(notifications).await.unwrap() {
+            id.unwrap();
+        }
+    }
+    started.elapsed() / ROUNDS
+}
+
+fn main() {
+    let _server = TestServer::start();
+    let notifications = (0..BURST)
+        .map(|n| {
+            Notification::new()
+                .summary("New mail")
+                .body(&format!("message {}", n))
+                .finalize()
+        })
+        .collect::<Vec<_>>();
+
+    zbus::block_on(async {
+        // connect before measuring, both share the connection
+        notifications[0].show_async().await.unwrap();
+        let serial = one_by_one(&notifications).await;
+        let batch = batched(&notifications).await;
+        println!(
+            "{} notifications: {:?} one by one, {:?} with send_all()",
+            BURST, serial, batch
+        );
+    });
+}
@@ -0,0 +1,38 @@
+//! Latency of blocking sends in a loop on the dbus-rs backend, which keeps the connection of a dropped handle.
+//!
+//! Needs a session bus without a notification server, an embedded one answers the calls:
+//! `DBUSRS=1 dbus-run-session cargo bench --features d,d_vendored --bench sequential_shows`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use notify_rust::Notification;
+
+const ROUNDS: u32 = 100;
+
+fn main() {
+    let _server = TestServer::start();
+    let notification = Notification::new().summary("Volume").body("42%").finalize();
+
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        // dropping the handle leaves its connection to the next send
+        drop(notification.show().unwrap());
+    }
+    let reused = started.elapsed() / ROUNDS;
+
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        // taken apart, the connection is closed instead
+        drop(notification.show().unwrap().into_parts());
+    }
+    let reconnected: Duration = started.elapsed() / ROUNDS;
+
+    println!(
+        "per show(): {:?} reusing the connection, {:?} connecting every time",
+        reused, reconnected
+    );
+}
@@ -0,0 +1,52 @@
+//! 100 `show_async()` calls over the shared connection against 100 that each connect first, as they used to.
+//!
+//! Needs a session bus without a notification server, an embedded one answers the calls:
+//! `dbus-run-session cargo bench --bench show_async_connection`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use notify_rust::{Notification, NotificationDispatcher};
+
+const SENDS: u32 = 100;
+
+async fn connecting_each_time(notification: &Notification) -> Duration {
+    let started = Instant::now();
+    for _ in 0..SENDS {
+        let connection = zbus::Connection::session().await.unwrap();
+        NotificationDispatcher::from_connection(connection)
+            .show_async(notification)
+            .await
+            .unwrap();
+    }
+    started.elapsed()
+}
+
+async fn shared(notification: &Notification) -> Duration {
+    let started = Instant::now();
+    for _ in 0..SENDS {
+        notification.show_async().await.unwrap();
+    }
+    started.elapsed()
+}
+
+fn main() {
+    let _server = TestServer::start();
+    let notification = Notification::new()
+        .summary("Log line")
+        .body("forwarded")
+        .finalize();
+
+    zbus::block_on(async {
+        // the first send pays for the shared connection, as any first send does
+        let before = connecting_each_time(&notification).await;
+        let after = shared(&notification).await;
+        println!(
+            "{} sends: {:?} connecting each time, {:?} over the shared connection",
+            SENDS, before, after
+        );
+    });
+}
@@ -0,0 +1,46 @@
+//! Latency of a send that waits for the server's reply against one that doesn't.
+//!
+//! Needs a session bus without a notification server, an embedded one answers the calls:
+//! `dbus-run-session cargo bench --bench show_nowait`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use notify_rust::Notification;
+
+const ROUNDS: u32 = 200;
+
+async fn replied(notification: &Notification) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        notification.show_async().await.unwrap();
+    }
+    started.elapsed() / ROUNDS
+}
+
+async fn nowait(notification: &Notification) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        notification.show_nowait_async().await.unwrap();
+    }
+    started.elapsed() / ROUNDS
+}
+
+fn main() {
+    let _server = TestServer::start();
+    let notification = Notification::new().summary("Volume").body("42%").finalize();
+
+    zbus::block_on(async {
+        // connect before measuring, both share the connection
+        notification.show_async().await.unwrap();
+        let replied = replied(&notification).await;
+        let nowait = nowait(&notification).await;
+        println!(
+            "per notification: {:?} waiting for the reply, {:?} with show_nowait()",
+            replied, nowait
+        );
+    });
+}
@@ -22,7 +22,7 @@ fn main() {
         notification
             .body(&format!("T-minus {}", 10 - i))
             .appname(&format!("countdown_{}", 10 - i));
-        notification.update();
+        notification.update().unwrap();
         println!("{}", 10 - i);
     }
 
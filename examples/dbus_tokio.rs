@@ -0,0 +1,25 @@
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn main() {
+    println!("this is an xdg only feature")
+}
+
+// cargo run --example dbus_tokio --no-default-features --features dbus_tokio
+#[cfg(all(unix, not(target_os = "macos")))]
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use notify_rust::{dbus_tokio, Event, Notification};
+
+    let info = dbus_tokio::get_server_information_async().await?;
+    let handle = dbus_tokio::show_async(
+        Notification::new()
+            .summary("Sent through dbus-tokio")
+            .body(&format!("{} shows this without blocking.", info.name))
+            .action("default", "Open"),
+    )
+    .await?;
+    match handle.wait_for_action_async().await? {
+        Event::ActionInvoked(action) => println!("invoked {action:?}"),
+        event => println!("{event:?}"),
+    }
+    Ok(())
+}
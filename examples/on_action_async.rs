@@ -0,0 +1,29 @@
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn main() {
+    println!("this is an xdg only feature")
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use notify_rust::Notification;
+    use std::time::Duration;
+
+    Notification::new()
+        .summary("async callbacks")
+        .body("the callbacks may await whatever they need")
+        .action("save", "save")
+        .action("discard", "discard")
+        .show_async()
+        .await?
+        .on_action_async("save", || async {
+            println!("saving...");
+            async_std::task::sleep(Duration::from_secs(1)).await;
+            println!("saved");
+        })
+        .on_action("discard", || println!("discarded"))
+        .on_close(|reason| println!("closed: {reason:?}"))
+        .listen_async()
+        .await?;
+    Ok(())
+}
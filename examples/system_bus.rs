@@ -0,0 +1,43 @@
+//! Sends to a notification bridge on the system bus, e.g. from a daemon without a session.
+//!
+//! The system bus only lets the bridge own its name, and others talk to it, with a policy like this
+//! in `/etc/dbus-1/system.d/de.hoodie.Notification.bridge.conf`:
+//!
+//! ```xml
+//! <!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+//!  "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+//! <busconfig>
+//!   <policy user="bridge">
+//!     <allow own="de.hoodie.Notification.bridge"/>
+//!   </policy>
+//!   <policy context="default">
+//!     <allow send_destination="de.hoodie.Notification.bridge"
+//!            send_interface="org.freedesktop.Notifications"/>
+//!   </policy>
+//! </busconfig>
+//! ```
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn main() {
+    println!("this is an xdg only feature")
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use notify_rust::{error::ErrorKind, Notification};
+
+    let sent = Notification::new()
+        .summary("Backup finished")
+        .body("sent from the system bus")
+        .on_system_bus(true)
+        .show_at_bus("bridge");
+    match sent {
+        Ok(handle) => println!("shown as {}", handle.id()),
+        Err(error) if matches!(error.kind(), ErrorKind::PermissionDenied(_)) => {
+            eprintln!("the system bus policy does not allow this: {}", error);
+            eprintln!("install the busconfig from the top of examples/system_bus.rs");
+        }
+        Err(error) => return Err(error.into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn main() {
+    println!("this is an xdg only feature")
+}
+
+// cargo run --example tokio --no-default-features --features tokio
+#[cfg(all(unix, not(target_os = "macos")))]
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let handle = notify_rust::Notification::new()
+        .summary("Sent from tokio")
+        .body("Close me and I'll tell you how.")
+        .show_async()
+        .await?;
+    let reason = handle.wait_for_closed_async().await?;
+    println!("the notification was closed: {reason:?}");
+    Ok(())
+}
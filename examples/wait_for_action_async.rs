@@ -0,0 +1,25 @@
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn main() {
+    println!("this is an xdg only feature")
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use notify_rust::{Event, Notification};
+
+    let handle = Notification::new()
+        .summary("async actions")
+        .body("this waits for you without blocking a thread")
+        .action("default", "default")
+        .action("clicked", "click here")
+        .show_async()
+        .await?;
+
+    match handle.wait_for_action_async().await? {
+        Event::ActionInvoked(action) => println!("you clicked {action:?}"),
+        Event::Closed(reason) => println!("the notification was closed: {reason:?}"),
+        other => println!("{other:?}"),
+    }
+    Ok(())
+}
@@ -4,10 +4,7 @@ fn main() {
 }
 
 #[cfg(all(unix, not(target_os = "macos")))]
-
 fn main() {
-    use notify_rust::CloseReason;
-
     zbus::block_on(async {
         let handle = notify_rust::Notification::new()
             .summary("Don't Mind me")
@@ -17,9 +14,10 @@ fn main() {
             .await;
 
         match handle {
-            Ok(handle) => handle.on_close(|reason: CloseReason| {
-                println!("the notification was closed reason: {reason:?}")
-            }),
+            Ok(handle) => match handle.wait_for_closed_async().await {
+                Ok(reason) => println!("the notification was closed reason: {reason:?}"),
+                Err(error) => println!("failed to wait for the notification {error}"),
+            },
             Err(error) => println!("failed to send notification {error}"),
         }
     })
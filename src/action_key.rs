@@ -0,0 +1,128 @@
+use std::{borrow::Cow, fmt, ops::Deref};
+
+/// Identifies an action, as opposed to the label the server displays for it.
+///
+/// The server only ever reports keys back, comparing an invoked action against a label never matches.
+/// `ActionKey` derefs to `str` and compares with plain strings, so it can be used wherever a key was a `&str` before.
+///
+/// ```
+/// # use notify_rust::ActionKey;
+/// let key = ActionKey::from("open");
+/// assert_eq!(key, "open");
+/// assert_eq!(key.len(), 4);
+/// assert!(!key.is_default());
+/// assert!(ActionKey::DEFAULT.is_default());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActionKey(Cow<'static, str>);
+
+impl ActionKey {
+    /// The key of the default action, usually invoked by clicking the notification itself.
+    pub const DEFAULT: ActionKey = ActionKey(Cow::Borrowed("default"));
+
+    /// Wraps `key`.
+    pub fn new(key: impl Into<String>) -> ActionKey {
+        ActionKey(Cow::Owned(key.into()))
+    }
+
+    /// The key as sent to the server.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// This is [`ActionKey::DEFAULT`].
+    pub fn is_default(&self) -> bool {
+        *self == ActionKey::DEFAULT
+    }
+}
+
+impl Deref for ActionKey {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ActionKey {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for ActionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for ActionKey {
+    fn from(key: &str) -> Self {
+        ActionKey::new(key)
+    }
+}
+
+impl From<String> for ActionKey {
+    fn from(key: String) -> Self {
+        ActionKey::new(key)
+    }
+}
+
+impl From<&ActionKey> for ActionKey {
+    fn from(key: &ActionKey) -> Self {
+        key.clone()
+    }
+}
+
+impl From<ActionKey> for String {
+    fn from(key: ActionKey) -> Self {
+        key.0.into_owned()
+    }
+}
+
+impl PartialEq<str> for ActionKey {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ActionKey {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<ActionKey> for str {
+    fn eq(&self, other: &ActionKey) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<ActionKey> for &str {
+    fn eq(&self, other: &ActionKey) -> bool {
+        *self == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ActionKey;
+
+    #[test]
+    fn owned_and_borrowed_keys_are_equal() {
+        assert_eq!(ActionKey::new("default"), ActionKey::DEFAULT);
+        assert_eq!(
+            ActionKey::from(String::from("open")),
+            ActionKey::from("open")
+        );
+        assert_eq!(String::from(ActionKey::DEFAULT), "default");
+        assert_eq!(ActionKey::DEFAULT.to_string(), "default");
+    }
+
+    #[test]
+    fn default_is_detected_by_key() {
+        assert!(ActionKey::from("default").is_default());
+        assert!(!ActionKey::from("Default").is_default());
+        assert!(!ActionKey::from("open").is_default());
+    }
+}
@@ -0,0 +1,467 @@
+//! Typed view on the list returned by [`get_capabilities()`](`crate::get_capabilities`).
+//!
+//! Servers advertise the optional parts of the specification they implement as plain strings.
+//! [`Capability`] gives those strings names, and [`Notification::adapt_to`] uses them to strip
+//! everything from a notification that the server would not understand anyway.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use crate::{hints::Hint, notification::Notification};
+
+/// A single capability as advertised by the notification server.
+///
+/// ## Specification
+/// As listed under [Table 7. Server Capabilities](https://specifications.freedesktop.org/notification-spec/latest/protocol.html#command-get-capabilities)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// The server will provide the specified actions to the user.
+    Actions,
+    /// Supports using icons instead of text for displaying actions.
+    ActionIcons,
+    /// Supports body text.
+    Body,
+    /// The server supports hyperlinks in the notifications.
+    BodyHyperlinks,
+    /// The server supports images in the notifications.
+    BodyImages,
+    /// Supports markup in the body text.
+    BodyMarkup,
+    /// The server will render an animation of all the frames in a given image array.
+    IconMulti,
+    /// Supports display of exactly 1 frame of any given image array.
+    IconStatic,
+    /// The server supports persistence of notifications.
+    Persistence,
+    /// The server supports sounds on notifications.
+    Sound,
+    /// Supports inline replies (KDE and GNOME extension).
+    InlineReply,
+    /// Anything not covered by the specification, usually prefixed with `x-vendor-`.
+    Unknown(String),
+}
+
+impl Capability {
+    /// The string the server uses for this capability.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Capability::Actions => "actions",
+            Capability::ActionIcons => "action-icons",
+            Capability::Body => "body",
+            Capability::BodyHyperlinks => "body-hyperlinks",
+            Capability::BodyImages => "body-images",
+            Capability::BodyMarkup => "body-markup",
+            Capability::IconMulti => "icon-multi",
+            Capability::IconStatic => "icon-static",
+            Capability::Persistence => "persistence",
+            Capability::Sound => "sound",
+            Capability::InlineReply => "inline-reply",
+            Capability::Unknown(other) => other,
+        }
+    }
+}
+
+impl From<&str> for Capability {
+    fn from(raw: &str) -> Self {
+        match raw {
+            "actions" => Capability::Actions,
+            "action-icons" => Capability::ActionIcons,
+            "body" => Capability::Body,
+            "body-hyperlinks" => Capability::BodyHyperlinks,
+            "body-images" => Capability::BodyImages,
+            "body-markup" => Capability::BodyMarkup,
+            "icon-multi" => Capability::IconMulti,
+            "icon-static" => Capability::IconStatic,
+            "persistence" => Capability::Persistence,
+            "sound" => Capability::Sound,
+            "inline-reply" => Capability::InlineReply,
+            other => Capability::Unknown(other.to_owned()),
+        }
+    }
+}
+
+impl From<String> for Capability {
+    fn from(raw: String) -> Self {
+        match Capability::from(raw.as_str()) {
+            Capability::Unknown(_) => Capability::Unknown(raw),
+            known => known,
+        }
+    }
+}
+
+impl FromStr for Capability {
+    type Err = Infallible;
+
+    /// Never fails, strings outside the specification become [`Capability::Unknown`].
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        Ok(Capability::from(raw))
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for Capability {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// The capabilities of one server, collected from [`server_capabilities()`](crate::server_capabilities)
+/// or from the strings of [`get_capabilities()`](crate::get_capabilities).
+///
+/// ```no_run
+/// # use notify_rust::*;
+/// # fn _doc() -> Result<(), Box<dyn std::error::Error>> {
+/// let capabilities = server_capabilities()?.into_iter().collect::<CapabilitySet>();
+/// if capabilities.supports(Capability::Actions) {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CapabilitySet(Vec<Capability>);
+
+impl CapabilitySet {
+    /// The server advertised `capability`.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.0.contains(&capability)
+    }
+
+    /// The capabilities in the order the server listed them.
+    pub fn iter(&self) -> std::slice::Iter<'_, Capability> {
+        self.0.iter()
+    }
+}
+
+impl<C: Into<Capability>> FromIterator<C> for CapabilitySet {
+    fn from_iter<I: IntoIterator<Item = C>>(capabilities: I) -> Self {
+        let mut set = Vec::new();
+        for capability in capabilities.into_iter().map(Into::into) {
+            if !set.contains(&capability) {
+                set.push(capability);
+            }
+        }
+        CapabilitySet(set)
+    }
+}
+
+impl IntoIterator for CapabilitySet {
+    type Item = Capability;
+    type IntoIter = std::vec::IntoIter<Capability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CapabilitySet {
+    type Item = &'a Capability;
+    type IntoIter = std::slice::Iter<'a, Capability>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Something [`Notification::adapt_to`] removed because the server lacks the capability for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Adaptation {
+    /// The server does not support `actions`, these were dropped.
+    DroppedActions(Vec<String>),
+    /// The server does not support `body-markup`, tags were stripped from the body.
+    StrippedMarkup,
+    /// The server does not support `body`, the body was dropped.
+    DroppedBody(String),
+    /// The server supports neither `icon-static` nor `icon-multi`, these image hints were dropped.
+    DroppedImageHints(Vec<Hint>),
+}
+
+impl fmt::Display for Adaptation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Adaptation::DroppedActions(actions) => {
+                write!(
+                    f,
+                    "dropped {} action(s), server lacks \"actions\"",
+                    actions.len() / 2
+                )
+            }
+            Adaptation::StrippedMarkup => {
+                write!(f, "stripped markup, server lacks \"body-markup\"")
+            }
+            Adaptation::DroppedBody(_) => write!(f, "dropped body, server lacks \"body\""),
+            Adaptation::DroppedImageHints(hints) => write!(
+                f,
+                "dropped {} image hint(s), server lacks \"icon-static\" and \"icon-multi\"",
+                hints.len()
+            ),
+        }
+    }
+}
+
+impl Notification {
+    /// Removes everything the server can't display according to its `capabilities`.
+    ///
+    /// This lets you write one builder chain and still send something sensible to minimal servers:
+    ///
+    /// * actions are dropped without `actions`
+    /// * markup is stripped from the body without `body-markup`
+    /// * the body is dropped entirely without `body`
+    /// * image hints are dropped without `icon-static` or `icon-multi`
+    ///
+    /// Returns what was removed, so you can log it.
+    ///
+    /// ```no_run
+    /// # use notify_rust::*;
+    /// # fn _doc() -> Result<(), Box<dyn std::error::Error>> {
+    /// let capabilities = get_capabilities()?;
+    /// let mut notification = Notification::new();
+    /// notification
+    ///     .summary("Build finished")
+    ///     .body("<b>all</b> tests passed")
+    ///     .action("open", "Open Log");
+    ///
+    /// for adaptation in notification.adapt_to(&capabilities) {
+    ///     println!("{}", adaptation);
+    /// }
+    /// notification.show()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn adapt_to<C: AsRef<str>>(&mut self, capabilities: &[C]) -> Vec<Adaptation> {
+        let supports = |capability: Capability| {
+            capabilities
+                .iter()
+                .any(|c| c.as_ref() == capability.as_str())
+        };
+        let mut adaptations = Vec::new();
+
+        if !supports(Capability::Actions) && !self.actions.is_empty() {
+            adaptations.push(Adaptation::DroppedActions(std::mem::take(
+                &mut self.actions,
+            )));
+            self.icon_actions = false;
+        }
+
+        if !supports(Capability::Body) {
+            if !self.body.is_empty() {
+                adaptations.push(Adaptation::DroppedBody(std::mem::take(&mut self.body)));
+            }
+        } else if !supports(Capability::BodyMarkup) {
+            let stripped = strip_markup(&self.body);
+            if stripped != self.body {
+                self.body = stripped;
+                adaptations.push(Adaptation::StrippedMarkup);
+            }
+        }
+
+        if !supports(Capability::IconStatic) && !supports(Capability::IconMulti) {
+            let image_hints = self
+                .hints
+                .iter()
+                .filter(|hint| is_image_hint(hint))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !image_hints.is_empty() {
+                self.hints.retain(|hint| !is_image_hint(hint));
+                adaptations.push(Adaptation::DroppedImageHints(image_hints));
+            }
+        }
+
+        adaptations
+    }
+}
+
+fn is_image_hint(hint: &Hint) -> bool {
+    match hint {
+        Hint::ImagePath(_) => true,
+        #[cfg(feature = "images")]
+        Hint::ImageData(_) => true,
+        _ => false,
+    }
+}
+
+/// Removes all tags and resolves the entities the specification allows in `body-markup`.
+fn strip_markup(body: &str) -> String {
+    let mut text = String::with_capacity(body.len());
+    let mut in_tag = false;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' if !in_tag
+                && chars
+                    .peek()
+                    .map_or(false, |n| n.is_alphabetic() || *n == '/') =>
+            {
+                in_tag = true;
+            }
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn everything() -> Notification {
+        Notification::new()
+            .summary("summary")
+            .body("<b>bold</b> &amp; <a href=\"https://example.com\">linked</a>")
+            .action("default", "Default")
+            .hint(Hint::ImagePath("/tmp/image.png".into()))
+            .hint(Hint::Category("test".into()))
+            .finalize()
+    }
+
+    const ALL: &[&str] = &["actions", "body", "body-markup", "icon-static"];
+
+    #[test]
+    fn capability_strings_roundtrip() {
+        for raw in ["actions", "body-markup", "persistence", "x-vendor-thing"] {
+            assert_eq!(Capability::from(raw).as_str(), raw);
+        }
+        assert_eq!(
+            Capability::from("x-vendor-thing"),
+            Capability::Unknown("x-vendor-thing".into())
+        );
+    }
+
+    #[test]
+    fn known_capabilities_parse_and_display() {
+        let known = [
+            ("actions", Capability::Actions),
+            ("action-icons", Capability::ActionIcons),
+            ("body", Capability::Body),
+            ("body-hyperlinks", Capability::BodyHyperlinks),
+            ("body-images", Capability::BodyImages),
+            ("body-markup", Capability::BodyMarkup),
+            ("icon-multi", Capability::IconMulti),
+            ("icon-static", Capability::IconStatic),
+            ("persistence", Capability::Persistence),
+            ("sound", Capability::Sound),
+            ("inline-reply", Capability::InlineReply),
+        ];
+        for (raw, capability) in known {
+            assert_eq!(raw.parse::<Capability>(), Ok(capability.clone()));
+            assert_eq!(Capability::from(raw.to_owned()), capability);
+            assert_eq!(capability.to_string(), raw);
+        }
+    }
+
+    #[test]
+    fn unknown_capabilities_keep_their_string() {
+        for raw in ["x-vendor-thing", "Actions", "actions ", ""] {
+            let capability = raw.parse::<Capability>().unwrap();
+            assert_eq!(capability, Capability::Unknown(raw.into()));
+            assert_eq!(Capability::from(raw.to_owned()), capability);
+            assert_eq!(capability.to_string(), raw);
+        }
+    }
+
+    #[test]
+    fn capability_sets_answer_supports() {
+        let set = ["body", "x-vendor-thing", "actions", "body"]
+            .into_iter()
+            .collect::<CapabilitySet>();
+        assert!(set.supports(Capability::Actions));
+        assert!(set.supports(Capability::Unknown("x-vendor-thing".into())));
+        assert!(!set.supports(Capability::BodyMarkup));
+        assert_eq!(
+            set.iter().map(Capability::as_str).collect::<Vec<_>>(),
+            ["body", "x-vendor-thing", "actions"]
+        );
+        assert!(!CapabilitySet::default().supports(Capability::Body));
+    }
+
+    #[test]
+    fn full_capabilities_change_nothing() {
+        let mut notification = everything();
+        assert!(notification.adapt_to(ALL).is_empty());
+        assert_eq!(notification.actions.len(), 2);
+        assert!(notification.body.contains("<b>"));
+    }
+
+    #[test]
+    fn drops_actions() {
+        let mut notification = everything();
+        let adaptations = notification.adapt_to(&["body", "body-markup", "icon-static"]);
+        assert_eq!(
+            adaptations,
+            vec![Adaptation::DroppedActions(vec![
+                "default".into(),
+                "Default".into()
+            ])]
+        );
+        assert!(notification.actions.is_empty());
+    }
+
+    #[test]
+    fn strips_markup() {
+        let mut notification = everything();
+        let adaptations = notification.adapt_to(&["actions", "body", "icon-static"]);
+        assert_eq!(adaptations, vec![Adaptation::StrippedMarkup]);
+        assert_eq!(notification.body, "bold & linked");
+    }
+
+    #[test]
+    fn plain_body_needs_no_stripping() {
+        let mut notification = Notification::new().body("1 < 2").finalize();
+        assert!(notification.adapt_to(&["body"]).is_empty());
+        assert_eq!(notification.body, "1 < 2");
+    }
+
+    #[test]
+    fn drops_body() {
+        let mut notification = everything();
+        let adaptations = notification.adapt_to(&["actions", "body-markup", "icon-static"]);
+        assert!(matches!(
+            adaptations.as_slice(),
+            [Adaptation::DroppedBody(body)] if body.starts_with("<b>bold</b>")
+        ));
+        assert!(notification.body.is_empty());
+    }
+
+    #[test]
+    fn drops_image_hints() {
+        let mut notification = everything();
+        let adaptations = notification.adapt_to(&["actions", "body", "body-markup"]);
+        assert_eq!(
+            adaptations,
+            vec![Adaptation::DroppedImageHints(vec![Hint::ImagePath(
+                "/tmp/image.png".into()
+            )])]
+        );
+        assert_eq!(
+            notification.hints.iter().collect::<Vec<_>>(),
+            vec![&Hint::Category("test".into())]
+        );
+
+        let mut multi = everything();
+        assert!(multi
+            .adapt_to(&["actions", "body", "body-markup", "icon-multi"])
+            .is_empty());
+    }
+
+    #[test]
+    fn accepts_typed_capabilities() {
+        let capabilities = ALL
+            .iter()
+            .copied()
+            .map(Capability::from)
+            .collect::<Vec<_>>();
+        assert!(everything().adapt_to(&capabilities).is_empty());
+    }
+}
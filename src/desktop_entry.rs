@@ -0,0 +1,190 @@
+//! Finds the desktop entry of the running application, see [`Notification::identify_sender`](crate::Notification::identify_sender).
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The desktop entry that runs the current executable, `None` unless exactly one does.
+pub(crate) fn current() -> Option<String> {
+    let exe = fs::read_link("/proc/self/exe").ok()?;
+    let path = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default();
+    find(&exe, &application_dirs(), &path)
+}
+
+/// The `applications` directories under `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`, in order of precedence.
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    let data_dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    data_home
+        .into_iter()
+        .chain(env::split_paths(&data_dirs))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// The id of the only desktop entry in `applications` whose `Exec=` runs `exe`.
+///
+/// Commands without a slash are looked up in `path`, like a shell would.
+/// An entry shadows those with the same id in later directories.
+pub(crate) fn find(exe: &Path, applications: &[PathBuf], path: &[PathBuf]) -> Option<String> {
+    let exe = canonical(exe);
+    let mut seen = HashSet::new();
+    let mut found = None;
+    for (id, file) in applications.iter().flat_map(|dir| desktop_files(dir, "")) {
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let runs_exe = fs::read_to_string(&file)
+            .ok()
+            .and_then(|entry| exec_command(&entry))
+            .and_then(|command| resolve(&command, path))
+            .map_or(false, |command| canonical(&command) == exe);
+        if runs_exe {
+            if found.is_some() {
+                // not confident which one it is
+                return None;
+            }
+            found = Some(id);
+        }
+    }
+    found
+}
+
+/// The `.desktop` files below `dir` with their ids, subdirectories become `-` separated prefixes.
+fn desktop_files(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>(),
+        Err(_) => return Vec::new(),
+    };
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        let name = match entry.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        if entry.is_dir() {
+            files.extend(desktop_files(&entry, &format!("{}{}-", prefix, name)));
+        } else if let Some(id) = name.strip_suffix(".desktop") {
+            files.push((format!("{}{}", prefix, id), entry));
+        }
+    }
+    files
+}
+
+/// The program of the `Exec=` key in the `[Desktop Entry]` group, without its arguments.
+fn exec_command(entry: &str) -> Option<String> {
+    let mut in_main_group = false;
+    for line in entry.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+        } else if let Some(exec) = line.strip_prefix("Exec=").filter(|_| in_main_group) {
+            let exec = exec.trim_start();
+            let command = match exec.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next(),
+                None => exec.split_whitespace().next(),
+            };
+            return command
+                .filter(|command| !command.is_empty())
+                .map(String::from);
+        }
+    }
+    None
+}
+
+/// Where `command` is found, either a path already or the first match in `path`.
+fn resolve(command: &str, path: &[PathBuf]) -> Option<PathBuf> {
+    if command.contains('/') {
+        return Some(PathBuf::from(command));
+    }
+    path.iter()
+        .map(|dir| dir.join(command))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `path` with all links resolved, as it is if that fails.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{exec_command, find};
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    fn find_fixture(exe: impl AsRef<Path>) -> Option<String> {
+        let fixtures = fixtures();
+        find(
+            exe.as_ref(),
+            &[fixtures.join("applications")],
+            &[fixtures.join("bin")],
+        )
+    }
+
+    #[test]
+    fn finds_the_entry_by_absolute_exec() {
+        assert_eq!(
+            find_fixture("/opt/fixture/bin/editor").as_deref(),
+            Some("editor")
+        );
+        assert_eq!(
+            find_fixture("/opt/fixture/bin/my terminal").as_deref(),
+            Some("tools-terminal")
+        );
+    }
+
+    #[test]
+    fn finds_the_entry_by_command_in_path() {
+        let exe = fixtures().join("bin/fixture-viewer");
+        assert_eq!(find_fixture(exe).as_deref(), Some("viewer"));
+    }
+
+    #[test]
+    fn no_confident_match_is_none() {
+        // twin.desktop and twin-beta.desktop both run it
+        assert_eq!(find_fixture("/opt/fixture/bin/twin"), None);
+        // only run by an action of editor.desktop
+        assert_eq!(find_fixture("/opt/fixture/bin/launcher"), None);
+        assert_eq!(find_fixture("/opt/fixture/bin/unknown"), None);
+    }
+
+    #[test]
+    fn earlier_directories_shadow_later_ones() {
+        let applications = fixtures().join("applications");
+        let found = find(
+            Path::new("/opt/fixture/bin/editor"),
+            &[applications.clone(), applications],
+            &[],
+        );
+        assert_eq!(found.as_deref(), Some("editor"));
+    }
+
+    #[test]
+    fn exec_command_skips_arguments_and_other_groups() {
+        let entry =
+            "[Desktop Action open]\nExec=other\n\n[Desktop Entry]\nName=App\nExec=app --flag %u\n";
+        assert_eq!(exec_command(entry).as_deref(), Some("app"));
+        assert_eq!(
+            exec_command("[Desktop Entry]\nExec=\"/opt/my app\" %f").as_deref(),
+            Some("/opt/my app")
+        );
+        assert_eq!(exec_command("[Desktop Entry]\nName=No Exec"), None);
+    }
+}
@@ -2,7 +2,7 @@
 
 #[cfg(all(feature = "images", unix, not(target_os = "macos")))]
 use crate::image::ImageError;
-use std::{fmt, num};
+use std::{fmt, num, time::Duration};
 /// Convenient wrapper around `std::Result`.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -41,6 +41,54 @@ pub enum ErrorKind {
     Image(ImageError),
 
     ImplementationMissing,
+
+    /// `show_with_retry()` gave up, this wraps the error of the last attempt
+    RetriesExhausted {
+        attempts: u32,
+        last: Box<Error>,
+    },
+
+    /// a callback of an `ActionListener` failed
+    Callback(Box<dyn std::error::Error + Send + Sync>),
+
+    /// the server has no notification with this id (anymore)
+    NotFound(u32),
+
+    /// the server did not answer within the `send_timeout()`
+    ReplyTimeout(Duration),
+
+    /// the bus policy does not allow talking to the server, e.g. on the system bus
+    PermissionDenied(String),
+
+    /// the server showed a new notification instead of replacing `requested`, see `Notification::require_stable_id()`
+    IdChanged {
+        requested: u32,
+        returned: u32,
+    },
+
+    /// nobody owns the server's bus name, this wraps the `ServiceUnknown` or `NameHasNoOwner` error of the bus
+    NoServer(Box<dyn std::error::Error + Send + Sync>),
+
+    /// `DBUS_SESSION_BUS_ADDRESS` is not set and there is no bus at any of the `tried` addresses
+    NoSessionBus {
+        tried: Vec<String>,
+    },
+
+    /// `Notification::actions` is not a list of key and label pairs, or a key is empty
+    InvalidActions(String),
+
+    /// hint `key` has the D-Bus `signature`, which doesn't fit the `expected` one of the hints dict
+    HintEncoding {
+        key: String,
+        signature: String,
+        expected: String,
+    },
+
+    /// a custom bus name breaks the D-Bus naming rules
+    InvalidBusName(InvalidBusName),
+
+    /// part of a message can't be encoded for D-Bus
+    Encoding(String),
 }
 
 impl fmt::Display for Error {
@@ -64,11 +112,278 @@ impl fmt::Display for Error {
                 f,
                 r#"No Dbus implementation available, please compile with either feature ="z" or feature="d""#
             ),
+            ErrorKind::RetriesExhausted { attempts, ref last } => {
+                write!(f, "{} (gave up after {} attempts)", last, attempts)
+            }
+            ErrorKind::Callback(ref e) => write!(f, "{}", e),
+            ErrorKind::NotFound(id) => write!(f, "there is no notification {}", id),
+            ErrorKind::ReplyTimeout(timeout) => {
+                write!(f, "the server did not reply within {:?}", timeout)
+            }
+            ErrorKind::PermissionDenied(ref e) => write!(f, "permission denied: {}", e),
+            ErrorKind::IdChanged {
+                requested,
+                returned,
+            } => write!(
+                f,
+                "the server did not replace notification {}, it showed {} instead",
+                requested, returned
+            ),
+            ErrorKind::NoServer(ref e) => write!(f, "no notification server is running: {}", e),
+            ErrorKind::InvalidActions(ref e) => write!(f, "invalid actions: {}", e),
+            ErrorKind::HintEncoding {
+                ref key,
+                ref signature,
+                ref expected,
+            } => write!(
+                f,
+                "hint {:?} is encoded as {}, but the hints dict takes {}",
+                key, signature, expected
+            ),
+            ErrorKind::InvalidBusName(ref e) => write!(f, "{}", e),
+            ErrorKind::Encoding(ref e) => write!(f, "can't encode {}", e),
+            ErrorKind::NoSessionBus { ref tried } if tried.is_empty() => write!(
+                f,
+                "no session bus, neither DBUS_SESSION_BUS_ADDRESS nor XDG_RUNTIME_DIR is set"
+            ),
+            ErrorKind::NoSessionBus { ref tried } => write!(
+                f,
+                "no session bus, DBUS_SESSION_BUS_ADDRESS is not set and nothing listens at {}",
+                tried.join(", ")
+            ),
         }
     }
 }
 
-impl std::error::Error for Error {}
+/// A bus name that breaks the rules for D-Bus well-known names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidBusName {
+    name: String,
+    reason: String,
+}
+
+impl InvalidBusName {
+    pub(crate) fn new(name: &str, reason: impl Into<String>) -> InvalidBusName {
+        InvalidBusName {
+            name: name.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The rejected name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Which rule it breaks.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for InvalidBusName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid bus name {:?}: {}", self.name, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidBusName {}
+
+impl Error {
+    /// What went wrong.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::RetriesExhausted { ref last, .. } => Some(last.as_ref()),
+            ErrorKind::Callback(ref e) | ErrorKind::NoServer(ref e) => Some(e.as_ref()),
+            ErrorKind::InvalidBusName(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Error {
+    /// The error means that nobody owns the notification server's bus name (yet).
+    ///
+    /// This is the case while the server has not started up, anything else is not worth retrying.
+    pub(crate) fn is_server_missing(&self) -> bool {
+        matches!(self.kind, ErrorKind::NoServer(_))
+    }
+
+    /// The server answered with an error, it is there and understood the call.
+    pub(crate) fn is_error_reply(&self) -> bool {
+        #[allow(dead_code)]
+        const NO_REPLY: &str = "org.freedesktop.DBus.Error.NoReply";
+
+        if self.is_server_missing() {
+            return false;
+        }
+        match self.kind {
+            #[cfg(feature = "dbus")]
+            ErrorKind::Dbus(ref e) => e.name().map_or(false, |name| name != NO_REPLY),
+
+            #[cfg(feature = "zbus")]
+            ErrorKind::Zbus(zbus::Error::MethodError(ref name, _, _)) => name.as_str() != NO_REPLY,
+
+            #[cfg(feature = "zbus")]
+            ErrorKind::Zbus(zbus::Error::FDO(ref e)) => {
+                !matches!(**e, zbus::fdo::Error::NoReply(_))
+            }
+
+            _ => false,
+        }
+    }
+
+    /// The server answered a call about a notification with an error of its own, i.e. it doesn't know the id.
+    ///
+    /// The specification only says that `CloseNotification` fails for an unknown id, not with which error.
+    /// Errors of the bus itself, or ones saying the server doesn't implement the call, are about something else.
+    pub(crate) fn is_unknown_id(&self) -> bool {
+        const ABOUT_THE_CALL: &[&str] = &[
+            ACCESS_DENIED,
+            "org.freedesktop.DBus.Error.AuthFailed",
+            "org.freedesktop.DBus.Error.Disconnected",
+            "org.freedesktop.DBus.Error.LimitsExceeded",
+            "org.freedesktop.DBus.Error.NoMemory",
+            "org.freedesktop.DBus.Error.NotSupported",
+            "org.freedesktop.DBus.Error.Timeout",
+            "org.freedesktop.DBus.Error.TimedOut",
+            "org.freedesktop.DBus.Error.UnknownInterface",
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            "org.freedesktop.DBus.Error.UnknownObject",
+        ];
+
+        if !self.is_error_reply() {
+            return false;
+        }
+        let name = match self.kind {
+            #[cfg(feature = "dbus")]
+            ErrorKind::Dbus(ref e) => e.name().map(str::to_owned),
+
+            #[cfg(feature = "zbus")]
+            ErrorKind::Zbus(zbus::Error::MethodError(ref name, _, _)) => {
+                Some(name.as_str().to_owned())
+            }
+
+            #[cfg(feature = "zbus")]
+            ErrorKind::Zbus(zbus::Error::FDO(ref e)) => {
+                Some(zbus::DBusError::name(&**e).as_str().to_owned())
+            }
+
+            _ => None,
+        };
+        name.map_or(false, |name| !ABOUT_THE_CALL.contains(&name.as_str()))
+    }
+
+    /// The error means that the connection to the bus is gone, a new one may work.
+    ///
+    /// A restarted bus shows up as a broken socket, or as calls that never got a reply.
+    #[cfg(feature = "zbus")]
+    pub(crate) fn is_disconnected(&self) -> bool {
+        const NO_REPLY: &str = "org.freedesktop.DBus.Error.NoReply";
+        const DISCONNECTED: &str = "org.freedesktop.DBus.Error.Disconnected";
+
+        match self.kind {
+            ErrorKind::Zbus(zbus::Error::InputOutput(_)) => true,
+            ErrorKind::Zbus(zbus::Error::MethodError(ref name, _, _)) => {
+                matches!(name.as_str(), NO_REPLY | DISCONNECTED)
+            }
+            ErrorKind::Zbus(zbus::Error::FDO(ref e)) => matches!(
+                **e,
+                zbus::fdo::Error::NoReply(_) | zbus::fdo::Error::Disconnected(_)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Marks this as the last error after `attempts` tries.
+    pub(crate) fn after_attempts(self, attempts: u32) -> Error {
+        Error {
+            kind: ErrorKind::RetriesExhausted {
+                attempts,
+                last: Box::new(self),
+            },
+        }
+    }
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+#[test]
+fn test_is_server_missing() {
+    use zbus::fdo;
+
+    let missing = Error::from(zbus::Error::FDO(Box::new(fdo::Error::ServiceUnknown(
+        "The name is not activatable".into(),
+    ))));
+    assert!(missing.is_server_missing());
+    assert!(matches!(missing.kind(), ErrorKind::NoServer(_)));
+    let source = std::error::Error::source(&missing).unwrap();
+    assert!(source.downcast_ref::<zbus::Error>().is_some());
+
+    let no_owner = Error::from(zbus::Error::MethodError(
+        zbus::names::OwnedErrorName::try_from(NAME_HAS_NO_OWNER).unwrap(),
+        None,
+        zbus::message::Message::method_call("/", "Ping")
+            .unwrap()
+            .build(&())
+            .unwrap(),
+    ));
+    assert!(no_owner.is_server_missing());
+    assert!(!missing.after_attempts(3).is_server_missing());
+
+    let rejected = Error::from(zbus::Error::FDO(Box::new(fdo::Error::InvalidArgs(
+        "no summary".into(),
+    ))));
+    assert!(!rejected.is_server_missing());
+    assert!(!Error::from("invalid subpath").is_server_missing());
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+#[test]
+fn test_access_denied_is_permission_denied() {
+    let denied = Error::from(zbus::Error::FDO(Box::new(zbus::fdo::Error::AccessDenied(
+        "Rejected send message".into(),
+    ))));
+    assert!(
+        matches!(denied.kind, ErrorKind::PermissionDenied(ref e) if e == "Rejected send message")
+    );
+    assert!(!denied.is_error_reply());
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+#[test]
+fn test_is_unknown_id() {
+    use zbus::fdo;
+
+    let reply = |name: &str| {
+        Error::from(zbus::Error::MethodError(
+            zbus::names::OwnedErrorName::try_from(name).unwrap(),
+            None,
+            zbus::message::Message::method_call("/", "CloseNotification")
+                .unwrap()
+                .build(&())
+                .unwrap(),
+        ))
+    };
+    assert!(reply("org.freedesktop.DBus.Error.InvalidArgs").is_unknown_id());
+    assert!(reply("org.freedesktop.Notifications.InvalidId").is_unknown_id());
+    assert!(!reply("org.freedesktop.DBus.Error.UnknownMethod").is_unknown_id());
+    assert!(!reply("org.freedesktop.DBus.Error.NoReply").is_unknown_id());
+    assert!(!reply(ACCESS_DENIED).is_unknown_id());
+    assert!(!reply(SERVICE_UNKNOWN).is_unknown_id());
+
+    let unsupported = Error::from(zbus::Error::FDO(Box::new(fdo::Error::NotSupported(
+        "no closing".into(),
+    ))));
+    assert!(!unsupported.is_unknown_id());
+    assert!(!Error::from("invalid subpath").is_unknown_id());
+}
 
 impl From<&str> for Error {
     fn from(e: &str) -> Error {
@@ -78,9 +393,40 @@ impl From<&str> for Error {
     }
 }
 
+/// What the bus answers when its policy forbids a call.
+#[cfg(all(
+    any(feature = "dbus", feature = "zbus"),
+    unix,
+    not(target_os = "macos")
+))]
+const ACCESS_DENIED: &str = "org.freedesktop.DBus.Error.AccessDenied";
+
+/// What the bus answers when nobody owns the name a call is sent to.
+#[cfg(all(
+    any(feature = "dbus", feature = "zbus"),
+    unix,
+    not(target_os = "macos")
+))]
+const SERVICE_UNKNOWN: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
+
+/// What the bus answers when asked about a name that nobody owns.
+#[cfg(all(
+    any(feature = "dbus", feature = "zbus"),
+    unix,
+    not(target_os = "macos")
+))]
+const NAME_HAS_NO_OWNER: &str = "org.freedesktop.DBus.Error.NameHasNoOwner";
+
 #[cfg(all(feature = "dbus", unix, not(target_os = "macos")))]
 impl From<dbus::Error> for Error {
     fn from(e: dbus::Error) -> Error {
+        if e.name() == Some(ACCESS_DENIED) {
+            let message = e.message().unwrap_or_default().to_owned();
+            return ErrorKind::PermissionDenied(message).into();
+        }
+        if matches!(e.name(), Some(SERVICE_UNKNOWN | NAME_HAS_NO_OWNER)) {
+            return ErrorKind::NoServer(Box::new(e)).into();
+        }
         Error {
             kind: ErrorKind::Dbus(e),
         }
@@ -90,6 +436,28 @@ impl From<dbus::Error> for Error {
 #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
 impl From<zbus::Error> for Error {
     fn from(e: zbus::Error) -> Error {
+        match e {
+            zbus::Error::MethodError(ref name, ref message, _)
+                if name.as_str() == ACCESS_DENIED =>
+            {
+                return ErrorKind::PermissionDenied(message.clone().unwrap_or_default()).into();
+            }
+            zbus::Error::MethodError(ref name, _, _)
+                if matches!(name.as_str(), SERVICE_UNKNOWN | NAME_HAS_NO_OWNER) =>
+            {
+                return ErrorKind::NoServer(Box::new(e)).into();
+            }
+            zbus::Error::FDO(ref fdo) => match **fdo {
+                zbus::fdo::Error::AccessDenied(ref message) => {
+                    return ErrorKind::PermissionDenied(message.clone()).into();
+                }
+                zbus::fdo::Error::ServiceUnknown(_) | zbus::fdo::Error::NameHasNoOwner(_) => {
+                    return ErrorKind::NoServer(Box::new(e)).into();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
         Error {
             kind: ErrorKind::Zbus(e),
         }
@@ -114,6 +482,14 @@ impl From<ImageError> for Error {
     }
 }
 
+impl From<InvalidBusName> for Error {
+    fn from(e: InvalidBusName) -> Error {
+        Error {
+            kind: ErrorKind::InvalidBusName(e),
+        }
+    }
+}
+
 impl From<num::ParseIntError> for Error {
     fn from(e: num::ParseIntError) -> Error {
         Error {
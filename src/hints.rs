@@ -10,11 +10,11 @@ pub(crate) mod message;
 use crate::image::Image;
 
 #[cfg(all(feature = "images", feature = "zbus", unix, not(target_os = "macos")))]
-use crate::image::image_spec_str;
+use crate::image::{image_spec_str, known_spec_version};
 use crate::Urgency;
 
 #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))] use crate::notification::Notification;
-#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))] use std::collections::HashMap;
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))] use std::collections::{BTreeMap, HashMap};
 
 mod constants;
 
@@ -144,7 +144,107 @@ impl Hint {
 }
 
 #[cfg(all(unix, not(target_os = "macos")))]
-impl Hint {}
+impl Hint {
+    /// The key this hint is sent under.
+    ///
+    /// Image data is sent under a key depending on the spec version, this returns the current one.
+    pub(crate) fn key(&self) -> &str {
+        use self::constants::*;
+        match self {
+            Hint::ActionIcons(_)       => ACTION_ICONS,
+            Hint::Category(_)          => CATEGORY,
+            Hint::DesktopEntry(_)      => DESKTOP_ENTRY,
+            #[cfg(feature = "images")]
+            Hint::ImageData(_)         => crate::image::constants::IMAGE_DATA,
+            Hint::ImagePath(_)         => IMAGE_PATH,
+            Hint::Resident(_)          => RESIDENT,
+            Hint::SoundFile(_)         => SOUND_FILE,
+            Hint::SoundName(_)         => SOUND_NAME,
+            Hint::SuppressSound(_)     => SUPPRESS_SOUND,
+            Hint::Transient(_)         => TRANSIENT,
+            Hint::X(_)                 => X,
+            Hint::Y(_)                 => Y,
+            Hint::Urgency(_)           => URGENCY,
+            Hint::Custom(key, _)       => key,
+            Hint::CustomInt(key, _)    => key,
+            Hint::Invalid              => INVALID,
+        }
+    }
+
+    /// Sounds are played again every time the notification is sent, updates included.
+    pub(crate) fn is_one_shot(&self) -> bool {
+        use self::constants::*;
+        matches!(self.key(), SOUND_FILE | SOUND_NAME)
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn test_effective_hints() {
+    use crate::notification::Notification;
+
+    let mut notification = Notification::new();
+    notification
+        .hint(Hint::Urgency(Urgency::Low))
+        .hint(Hint::Category("first".into()))
+        .hint(Hint::Category("second".into()))
+        .hint(Hint::Custom("urgency".into(), "custom".into()))
+        .hint(Hint::CustomInt("x-count".into(), 1))
+        .hint(Hint::Custom("x-count".into(), "one".into()))
+        .action_with_icon("media-playback-start", "Play");
+    // sneaking a duplicate past `hint()`, the custom hint still wins
+    notification.hints.insert(Hint::Urgency(Urgency::Critical));
+
+    assert_eq!(notification.effective_hints().collect::<Vec<_>>(), vec![
+        &Hint::ActionIcons(true),
+        &Hint::Category("second".into()),
+        &Hint::Custom("urgency".into(), "custom".into()),
+        &Hint::Custom("x-count".into(), "one".into()),
+    ]);
+
+    #[cfg(feature = "zbus")]
+    assert_eq!(
+        hints_to_map(&notification).keys().copied().collect::<Vec<_>>(),
+        notification.effective_hints().map(Hint::key).collect::<Vec<_>>()
+    );
+
+    #[cfg(feature = "dbus")]
+    {
+        use dbus::arg::messageitem::MessageItem;
+        let packed_keys = match crate::xdg::dbus_rs::pack_hints(&notification).unwrap() {
+            MessageItem::Dict(dict) => dict.into_vec().into_iter().map(|(key, _)| match key {
+                MessageItem::Str(key) => key,
+                other => panic!("unexpected key {:?}", other),
+            }).collect::<Vec<_>>(),
+            other => panic!("unexpected hints {:?}", other),
+        };
+        assert_eq!(packed_keys, notification.effective_hints().map(Hint::key).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(all(feature = "dbus", unix, not(target_os = "macos")))]
+#[test]
+fn mismatched_hint_values_name_the_hint() {
+    use dbus::arg::messageitem::MessageItem;
+    use crate::error::ErrorKind;
+
+    let variant = |value: MessageItem| MessageItem::Variant(Box::new(value));
+    let error = crate::xdg::dbus_rs::hint_dict(vec![
+        (MessageItem::Str("category".into()), variant(MessageItem::Str("email".into()))),
+        (MessageItem::Str("x-count".into()), MessageItem::Int32(3)),
+    ]).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        ErrorKind::HintEncoding { key, signature, expected }
+            if key == "x-count" && signature == "{si}" && expected == "{sv}"
+    ), "{}", error);
+    assert_eq!(error.to_string(), r#"hint "x-count" is encoded as {si}, but the hints dict takes {sv}"#);
+
+    assert!(crate::xdg::dbus_rs::hint_dict(vec![
+        (MessageItem::Str("x-count".into()), variant(MessageItem::Int32(3))),
+        (MessageItem::Str("category".into()), variant(MessageItem::Str("email".into()))),
+    ]).is_ok());
+}
 
 #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
 #[test]
@@ -158,18 +258,191 @@ fn test_hints_to_map() {
         .hint(Hint::Custom("f00".into(), "bar3".into()))
         .finalize();
 
-     assert_eq!(hints_to_map(&n1), maplit::hashmap!{
+     assert_eq!(hints_to_map(&n1), maplit::btreemap!{
          "foo" => zvariant::Value::Str("bar2".into()),
          "f00" => zvariant::Value::Str("bar3".into())
      });
 }
 
 #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
-pub(crate) fn hints_to_map(notification: &Notification) -> HashMap::<&str, zvariant::Value<'_>> {
-    notification
-        .get_hints()
-        .map(Into::into)
-        .collect()
+#[test]
+fn raw_hints_win_over_typed_ones() {
+    let notification = Notification::new()
+        .hint(Hint::Custom("x-shared".into(), "typed".into()))
+        .hint(Hint::Custom("x-typed".into(), "typed".into()))
+        .raw_hints_extend(maplit::hashmap!{
+            "x-shared".to_owned() => zvariant::OwnedValue::from(1u32),
+            "x-raw".to_owned() => zvariant::OwnedValue::from(2u32),
+        })
+        .finalize();
+
+    assert_eq!(build_hint_map(&notification), maplit::hashmap!{
+        "x-shared".to_owned() => zvariant::Value::U32(1),
+        "x-typed".to_owned() => zvariant::Value::Str("typed".into()),
+        "x-raw".to_owned() => zvariant::Value::U32(2),
+    });
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+pub(crate) fn hints_to_map(notification: &Notification) -> BTreeMap::<&str, zvariant::Value<'_>> {
+    collect_hints(notification, |value| zvariant::Value::clone(value))
+}
+
+/// The hints of the `Notify` call for `notification`, like [`hints_to_map`] but with image data borrowed.
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+pub(crate) fn hints_to_args(notification: &Notification) -> BTreeMap::<&str, HintValue<'_>> {
+    collect_hints(notification, |value| HintValue::Value(zvariant::Value::clone(value)))
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+fn collect_hints<'a, V>(notification: &'a Notification, raw: impl Fn(&'a zvariant::OwnedValue) -> V) -> BTreeMap::<&'a str, V>
+where
+    V: Clone,
+    (&'a str, V): From<&'a Hint>,
+{
+    let spec_version = notification.compat_image_hints.then(crate::xdg::cached_spec_version).flatten();
+    let mut map: BTreeMap<_, _> = notification
+        .effective_hints()
+        .flat_map(|hint| {
+            let compat_keys = notification.compat_image_hints.then(|| compat_image_keys(hint, spec_version.as_deref())).flatten();
+            let (key, value): (&str, V) = hint.into();
+            match compat_keys {
+                Some(keys) => keys.iter().map(|key| (*key, value.clone())).collect(),
+                None => vec![(key, value)],
+            }
+        })
+        .collect();
+    // raw hints win over typed ones of the same key
+    map.extend(notification.raw_hints.iter().map(|(key, value)| (key.as_str(), raw(value))));
+    map
+}
+
+/// A hint value as it goes into the `Notify` call.
+///
+/// Images are serialized straight from their shared data,
+/// a [`zvariant::Value`] would hold a copy with every byte wrapped in a `Value` of its own.
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+#[derive(Clone, Debug)]
+pub(crate) enum HintValue<'a> {
+    Value(zvariant::Value<'a>),
+    #[cfg(feature = "images")]
+    Image(&'a Image),
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+impl serde::Serialize for HintValue<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            HintValue::Value(value) => value.serialize(serializer),
+            #[cfg(feature = "images")]
+            HintValue::Image(image) => zvariant::as_value::Serialize(&image.as_tuple()).serialize(serializer),
+        }
+    }
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+impl zvariant::Type for HintValue<'_> {
+    const SIGNATURE: &'static zvariant::Signature = &zvariant::Signature::Variant;
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+impl<'a> From<&'a Hint> for (&'a str, HintValue<'a>) {
+    fn from(hint: &'a Hint) -> Self {
+        match hint {
+            #[cfg(feature = "images")]
+            Hint::ImageData(image) => (image_spec_str(known_spec_version()), HintValue::Image(image)),
+            _ => {
+                let (key, value) = hint.into();
+                (key, HintValue::Value(value))
+            }
+        }
+    }
+}
+
+/// The hints the zbus backend sends for `notification`, including those of [`Notification::raw_hints_extend()`].
+///
+/// For talking to a server yourself, e.g. through [`NotificationsProxy`](crate::NotificationsProxy).
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+pub fn build_hint_map(notification: &Notification) -> HashMap<String, zvariant::Value<'_>> {
+    hints_to_map(notification).into_iter().map(|(key, value)| (key.to_owned(), value)).collect()
+}
+
+/// Keys an image hint has to be sent under for a server reporting `spec_version`.
+///
+/// Spec 1.0 and 1.1 used underscores (`icon_data`, `image_data`, `image_path`), 1.2 switched to dashes.
+/// If the version is unknown or unparsable every known form is returned.
+/// Returns `None` for hints that are not affected.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn compat_image_keys(hint: &Hint, spec_version: Option<&str>) -> Option<&'static [&'static str]> {
+    use crate::miniver::Version;
+    use constants::*;
+
+    let version = spec_version.and_then(|version| version.parse::<Version>().ok());
+    match hint {
+        Hint::ImagePath(_) => Some(match version {
+            Some(version) if version < Version::new(1, 2) => &[IMAGE_PATH_1_1],
+            Some(_) => &[IMAGE_PATH],
+            None => &[IMAGE_PATH, IMAGE_PATH_1_1],
+        }),
+
+        #[cfg(feature = "images")]
+        Hint::ImageData(_) => {
+            use crate::image::constants::*;
+            use std::cmp::Ordering;
+            Some(match version.map(|version| version.cmp(&Version::new(1, 1))) {
+                Some(Ordering::Less)    => &[IMAGE_DATA_1_0],
+                Some(Ordering::Equal)   => &[IMAGE_DATA_1_1],
+                Some(Ordering::Greater) => &[IMAGE_DATA],
+                None                    => &[IMAGE_DATA, IMAGE_DATA_1_1, IMAGE_DATA_1_0],
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn test_compat_image_keys() {
+    let path = Hint::ImagePath("/tmp/image.png".into());
+    assert_eq!(compat_image_keys(&path, Some("1.0")), Some(&["image_path"][..]));
+    assert_eq!(compat_image_keys(&path, Some("1.1")), Some(&["image_path"][..]));
+    assert_eq!(compat_image_keys(&path, Some("1.2")), Some(&["image-path"][..]));
+    assert_eq!(compat_image_keys(&path, Some("1.3")), Some(&["image-path"][..]));
+    assert_eq!(compat_image_keys(&path, None), Some(&["image-path", "image_path"][..]));
+    for garbage in ["", "1", "one.two", "1.x", "ಠ_ಠ"] {
+        assert_eq!(compat_image_keys(&path, Some(garbage)), Some(&["image-path", "image_path"][..]));
+    }
+
+    assert_eq!(compat_image_keys(&Hint::Category("foo".into()), Some("1.1")), None);
+    assert_eq!(compat_image_keys(&Hint::Category("foo".into()), None), None);
+
+    #[cfg(feature = "images")]
+    {
+        let data = Hint::ImageData(Image::from_rgb(1, 1, vec![0, 0, 0]).unwrap());
+        assert_eq!(compat_image_keys(&data, Some("1.0")), Some(&["icon_data"][..]));
+        assert_eq!(compat_image_keys(&data, Some("1.1")), Some(&["image_data"][..]));
+        assert_eq!(compat_image_keys(&data, Some("1.2")), Some(&["image-data"][..]));
+        assert_eq!(compat_image_keys(&data, Some("garbage")), Some(&["image-data", "image_data", "icon_data"][..]));
+    }
+}
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+#[test]
+fn test_hints_to_map_compat() {
+    let plain = Notification::new()
+        .hint(Hint::ImagePath("/tmp/image.png".into()))
+        .finalize();
+    assert_eq!(hints_to_map(&plain).keys().collect::<Vec<_>>(), vec![&"image-path"]);
+
+    // no server information was requested, so the version is unknown and both forms are sent
+    let compat = Notification::new()
+        .hint(Hint::ImagePath("/tmp/image.png".into()))
+        .compat_image_hints(true)
+        .finalize();
+    assert_eq!(hints_to_map(&compat), maplit::btreemap!{
+        "image-path" => zvariant::Value::Str("/tmp/image.png".into()),
+        "image_path" => zvariant::Value::Str("/tmp/image.png".into())
+    });
 }
 
 #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
@@ -184,7 +457,7 @@ impl<'a> From<&'a Hint> for (&'a str, zvariant::Value<'a>) {
             #[cfg(all(feature = "zbus", feature = "images", unix, not(target_os = "macos")))]
             //Hint::ImageData(image)         => (image_spec(*crate::SPEC_VERSION).as_str(), ImagePayload::from(*image).into()),
             Hint::ImageData(image)         => (
-                image_spec_str(*crate::SPEC_VERSION),
+                image_spec_str(known_spec_version()),
                 zvariant::Value::Structure(
                     image.to_tuple().into()
                 )
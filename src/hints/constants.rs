@@ -4,6 +4,7 @@ pub const ACTION_ICONS: &str    = "action-icons";
 pub const CATEGORY: &str        = "category";
 pub const DESKTOP_ENTRY: &str   = "desktop-entry";
 pub const IMAGE_PATH: &str      = "image-path";
+pub const IMAGE_PATH_1_1: &str  = "image_path";
 pub const RESIDENT: &str        = "resident";
 pub const SOUND_FILE: &str      = "sound-file";
 pub const SOUND_NAME: &str      = "sound-name";
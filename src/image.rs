@@ -7,16 +7,23 @@ use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::miniver::Version;
 
-mod constants {
+pub(crate) mod constants {
     pub const IMAGE_DATA: &str = "image-data";
     pub const IMAGE_DATA_1_1: &str = "image_data";
     pub const IMAGE_DATA_1_0: &str = "icon_data";
+
+    /// The struct the spec sends all image data keys as.
+    #[cfg(feature = "dbus")]
+    pub const IMAGE_DATA_SIGNATURE: &str = "(iiibiiay)";
 }
 
 /// Image representation for images. Send via `Notification::image_data()`
+///
+/// Clones share the pixel data, so keeping a notification with a large image around in its handle stays cheap.
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct Image {
     width: i32,
@@ -25,7 +32,7 @@ pub struct Image {
     alpha: bool,
     bits_per_sample: i32,
     channels: i32,
-    data: Vec<u8>,
+    data: Arc<[u8]>,
 }
 
 impl Image {
@@ -50,13 +57,19 @@ impl Image {
                 height,
                 bits_per_sample,
                 channels,
-                data,
+                data: data.into(),
                 rowstride: width * channels,
                 alpha,
             })
         }
     }
 
+    /// How many bytes of pixel data are sent.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn byte_len(&self) -> usize {
+        self.data.len()
+    }
+
     /// Creates an image from a raw vector of bytes
     pub fn from_rgb(width: i32, height: i32, data: Vec<u8>) -> Result<Self, ImageError> {
         let channels = 3i32;
@@ -72,7 +85,13 @@ impl Image {
     }
 
     ///  Attempts to open the given path as image
+    ///
+    /// Animated images (GIF, APNG and WebP) are reduced to their first frame,
+    /// notification servers only display a single image anyway.
     pub fn open<T: AsRef<Path> + Sized>(path: T) -> Result<Self, ImageError> {
+        if let Some(frame) = open_first_frame(path.as_ref())? {
+            return Image::try_from(frame);
+        }
         let dyn_img = image::open(&path).map_err(ImageError::CantOpen)?;
         Image::try_from(dyn_img)
     }
@@ -86,9 +105,76 @@ impl Image {
             self.alpha,
             self.bits_per_sample,
             self.channels,
-            self.data.clone(),
+            self.data.to_vec(),
         )
     }
+
+    /// Like [`to_tuple`](Self::to_tuple), borrowing the data instead of copying it.
+    #[cfg(any(feature = "dbus", feature = "zbus"))]
+    pub(crate) fn as_tuple(&self) -> (i32, i32, i32, bool, i32, i32, &[u8]) {
+        (
+            self.width,
+            self.height,
+            self.rowstride,
+            self.alpha,
+            self.bits_per_sample,
+            self.channels,
+            &self.data,
+        )
+    }
+}
+
+/// Decodes the first frame if `path` is an animated image, `None` for everything else.
+fn open_first_frame(path: &Path) -> Result<Option<image::RgbaImage>, ImageError> {
+    use image::{
+        codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+        AnimationDecoder, ImageFormat, ImageReader,
+    };
+
+    let reader = ImageReader::open(path)
+        .and_then(ImageReader::with_guessed_format)
+        .map_err(image::ImageError::IoError)?;
+    let format = match reader.format() {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+    let reader = reader.into_inner();
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader)?;
+            if !decoder.is_apng()? {
+                return Ok(None);
+            }
+            decoder.apng()?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+
+    let mut frames = frames.take(2);
+    let first = match frames.next() {
+        Some(frame) => frame.map_err(|error| ImageError::CantDecodeFrame {
+            format,
+            frame: 0,
+            error,
+        })?,
+        None => return Ok(None),
+    };
+    if frames.next().is_some() {
+        log::debug!(
+            "{} is an animated {:?} image, using frame 0 and discarding the rest",
+            path.display(),
+            format
+        );
+    }
+    Ok(Some(first.into_buffer()))
 }
 
 impl TryFrom<DynamicImage> for Image {
@@ -134,6 +220,15 @@ pub enum ImageError {
     CantOpen(image::ImageError),
     /// Can't convert from given input
     CantConvert,
+    /// Can't decode the frame of an animated image that would have been used
+    CantDecodeFrame {
+        /// format of the animated image
+        format: image::ImageFormat,
+        /// index of the frame that failed to decode
+        frame: usize,
+        /// the underlying decoding error
+        error: image::ImageError,
+    },
 }
 
 impl Error for ImageError {
@@ -141,7 +236,7 @@ impl Error for ImageError {
         use ImageError::*;
         match self {
             TooBig | WrongDataSize | CantConvert => None,
-            CantOpen(e) => Some(e),
+            CantOpen(e) | CantDecodeFrame { error: e, .. } => Some(e),
         }
     }
 }
@@ -160,6 +255,15 @@ impl fmt::Display for ImageError {
             ),
             CantOpen(e) => writeln!(f, "Can't open given path {}", e),
             CantConvert => writeln!(f, "Can't convert from given input"),
+            CantDecodeFrame {
+                format,
+                frame,
+                error,
+            } => writeln!(
+                f,
+                "Can't decode frame {} of animated {:?} image {}",
+                frame, format, error
+            ),
         }
     }
 }
@@ -174,6 +278,16 @@ pub(crate) fn image_spec(version: Version) -> String {
     }
 }
 
+/// The spec version the server reported last, 1.1 like [`SPEC_VERSION`](crate::SPEC_VERSION) if it didn't yet.
+///
+/// Unlike `SPEC_VERSION` this never asks the server, the zbus backend does so before sending an image.
+#[cfg(feature = "zbus")]
+pub(crate) fn known_spec_version() -> Version {
+    crate::xdg::cached_spec_version()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or_else(|| Version::new(1, 1))
+}
+
 /// matching image data key for each spec version
 #[cfg(feature = "zbus")]
 pub(crate) fn image_spec_str(version: Version) -> &'static str {
@@ -212,18 +326,73 @@ impl std::ops::Deref for ImageMessage {
 #[cfg(feature = "dbus")]
 impl From<ImageMessage> for MessageItem {
     fn from(img_msg: ImageMessage) -> Self {
-        let img = img_msg.0;
-
-        let bytes = img.data.into_iter().map(MessageItem::Byte).collect();
-
-        MessageItem::Struct(vec![
-            MessageItem::Int32(img.width),
-            MessageItem::Int32(img.height),
-            MessageItem::Int32(img.rowstride),
-            MessageItem::Bool(img.alpha),
-            MessageItem::Int32(img.bits_per_sample),
-            MessageItem::Int32(img.channels),
-            MessageItem::Array(MessageItemArray::new(bytes, "ay".into()).unwrap()),
-        ])
+        let (width, height, rowstride, alpha, bits_per_sample, channels, data) = img_msg.as_tuple();
+
+        // an empty array can't tell its element type, so it is named explicitly
+        let bytes = data.iter().copied().map(MessageItem::Byte).collect();
+        let bytes = MessageItemArray::new(bytes, "ay".into()).expect("bytes are an `ay`");
+
+        let image = MessageItem::Struct(vec![
+            MessageItem::Int32(width),
+            MessageItem::Int32(height),
+            MessageItem::Int32(rowstride),
+            MessageItem::Bool(alpha),
+            MessageItem::Int32(bits_per_sample),
+            MessageItem::Int32(channels),
+            MessageItem::Array(bytes),
+        ]);
+        debug_assert_eq!(&*image.signature(), constants::IMAGE_DATA_SIGNATURE);
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_FRAMES_GIF: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/two_frames.gif");
+
+    #[test]
+    fn animated_gif_uses_first_frame() {
+        let image = Image::open(TWO_FRAMES_GIF).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert!(image.alpha);
+        assert_eq!(*image.data, *[255, 0, 0, 255].repeat(4));
+    }
+
+    #[test]
+    fn clones_share_the_pixels() {
+        let image = Image::from_rgb(512, 512, vec![0; 512 * 512 * 3]).unwrap();
+        let clone = image.clone();
+        assert!(Arc::ptr_eq(&image.data, &clone.data));
+        assert_eq!(image, clone);
+    }
+
+    #[cfg(feature = "dbus")]
+    #[test]
+    fn image_messages_have_the_spec_signature() {
+        let image = Image::from_rgba(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let item = MessageItem::from(ImageMessage::from(image));
+        assert_eq!(&*item.signature(), constants::IMAGE_DATA_SIGNATURE);
+
+        let (key, value) = crate::hints::message::HintMessage::wrap_hint(crate::Hint::ImageData(
+            Image::from_rgb(1, 1, vec![0, 0, 0]).unwrap(),
+        ));
+        assert_eq!(key, MessageItem::Str(image_spec(*crate::SPEC_VERSION)));
+        assert_eq!(&*value.signature(), "v");
+        match value {
+            MessageItem::Variant(inner) => {
+                assert_eq!(&*inner.signature(), constants::IMAGE_DATA_SIGNATURE)
+            }
+            other => panic!("not a variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn still_images_are_not_animated() {
+        let octodex = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/octodex.jpg");
+        assert!(open_first_frame(Path::new(octodex)).unwrap().is_none());
+        assert!(Image::open(octodex).is_ok());
     }
 }
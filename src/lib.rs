@@ -41,8 +41,10 @@
 //!     .show().unwrap();
 //! ```
 //!
-//! Careful! There are no checks whether you use hints twice.
-//! It is possible to set `urgency=Low` AND `urgency=Critical`, in which case the behavior of the server is undefined.
+//! Setting a hint again replaces the one with the same key, the last call wins.
+//! Setting `urgency=Low` and then `urgency=Critical` sends only `urgency=Critical`.
+//! Earlier versions sent both and left it to the server which one to use,
+//! if you relied on that, set the hint you want last.
 //!
 //! ## Example 3: Ask the user to do something
 //!
@@ -90,22 +92,50 @@
 //! |  `fn icon(...)`     |  ✔︎    |       |        |
 //! |  `fn auto_icon(...)`|  ✔︎    |       |        |
 //! |  `fn hint(...)`     |  ✔︎    | ❌    | ❌    |
+//! |  `fn identify_sender()` |  ✔︎    | ❌    | ❌    |
 //! |  `fn timeout(...)`  |  ✔︎    |       |  ✔︎    |
 //! |  `fn urgency(...)`  |  ✔︎    | ❌    | ❌    |
 //! |  `fn action(...)`   |  ✔︎    |       |        |
+//! |  `fn action_with_icon(...)` |  ✔︎    | ❌    | ❌    |
+//! |  `fn adapt_to(...)` |  ✔︎    | ❌    | ❌    |
 //! |  `fn id(...)`       |  ✔︎    |       |        |
 //! |  `fn finalize(...)` |  ✔︎    | ✔︎     |  ✔︎    |
 //! |  `fn show(...)`     |  ✔︎    | ✔︎     |  ✔︎    |
+//! |  `fn show_with_retry(...)` |  ✔︎    | ❌    | ❌    |
+//! |  `fn show_nowait()` |  ✔︎    | ❌    | ❌    |
+//! |  `fn show_and_wait()`  |  ✔︎    | ❌    | ❌    |
 //!
 //! ## `NotificationHandle`
 //!
 //! | method                   | XDG | macOS | windows |
 //! |--------------------------|-----|-------|---------|
 //! | `fn wait_for_action(...)`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_for_action_timeout(...)`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_for_action_async(...)`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_cancelable()`  |  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_for_closed(...)`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_for_closed_async()`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait()`               |  ✔︎  |  ❌  |   ❌   |
+//! | `fn wait_async()`         |  ✔︎  |  ❌  |   ❌   |
+//! | `fn events()`            |  ✔︎  |  ❌  |   ❌   |
+//! | `fn events_async()`      |  ✔︎  |  ❌  |   ❌   |
+//! | `fn poll_event()`        |  ✔︎  |  ❌  |   ❌   |
 //! | `fn close(...)`          |  ✔︎  |  ❌  |   ❌   |
+//! | `fn close_and_wait()`    |  ✔︎  |  ❌  |   ❌   |
+//! | `fn close_on_drop(...)`  |  ✔︎  |  ❌  |   ❌   |
 //! | `fn on_close(...)`       |  ✔︎  |  ❌  |   ❌   |
+//! | `fn on_action(...)`      |  ✔︎  |  ❌  |   ❌   |
+//! | `fn on_action_async(...)`|  ✔︎  |  ❌  |   ❌   |
+//! | `fn spawn_listener(...)` |  ✔︎  |  ❌  |   ❌   |
 //! | `fn update(...)`         |  ✔︎  |  ❌  |   ❌   |
+//! | `fn reshow()`            |  ✔︎  |  ❌  |   ❌   |
+//! | `fn update_with(...)`    |  ✔︎  |  ❌  |   ❌   |
+//! | `fn set_summary(...)`, `fn set_body(...)`, `fn set_value(...)` |  ✔︎  |  ❌  |   ❌   |
+//! | `fn last_activation_token()` |  ✔︎  |  ❌  |   ❌   |
 //! | `fn id(...)`             |  ✔︎  |  ❌  |   ❌   |
+//! | `fn connection()`        |  ✔︎  |  ❌  |   ❌   |
+//! | `fn into_parts()`        |  ✔︎  |  ❌  |   ❌   |
+//! | `fn from_parts(...)`     |  ✔︎  |  ❌  |   ❌   |
 //!
 //! ## Functions
 //!
@@ -113,6 +143,12 @@
 //! |--------------------------------------------|-----|-------|---------|
 //! | `fn get_capabilities(...)`                 | ✔︎   |   ❌ |  ❌    |
 //! | `fn get_server_information(...)`           | ✔︎   |   ❌ |  ❌    |
+//! | `fn get_capabilities_on(...)`              | ✔︎   |   ❌ |  ❌    |
+//! | `fn get_server_information_on(...)`        | ✔︎   |   ❌ |  ❌    |
+//! | `fn server_capabilities(...)`              | ✔︎   |   ❌ |  ❌    |
+//! | `fn get_capabilities_via(...)`             | ✔︎   |   ❌ |  ❌    |
+//! | `fn get_server_information_via(...)`       | ✔︎   |   ❌ |  ❌    |
+//! | `fn subscribe_all(...)`                    | ✔︎   |   ❌ |  ❌    |
 //! | `fn set_application(...)`                  | ❌  |   ✔︎  |  ❌    |
 //! | `fn get_bundle_identifier_or_default(...)` | ❌  |   ✔︎  |  ❌    |
 //!
@@ -160,6 +196,11 @@ extern crate winrt_notification;
 #[cfg(all(feature = "images", unix, not(target_os = "macos")))]
 extern crate lazy_static;
 
+mod action_key;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod capabilities;
+#[cfg(target_os = "linux")]
+mod desktop_entry;
 pub mod error;
 mod hints;
 mod miniver;
@@ -191,8 +232,34 @@ pub use macos::NotificationHandle;
     not(target_os = "macos")
 ))]
 pub use crate::xdg::{
-    dbus_stack, get_capabilities, get_server_information, handle_action, ActionResponse,
-    CloseHandler, CloseReason, DbusStack, NotificationHandle,
+    close_notification, close_notification_at_bus, close_notification_on, dbus_stack,
+    get_capabilities, get_capabilities_at_bus, get_capabilities_on, get_capabilities_via,
+    get_capabilities_within, get_server_information, get_server_information_at_bus,
+    get_server_information_on, get_server_information_via, get_server_information_within,
+    handle_action, handle_action_until, handle_action_via, server_capabilities, subscribe_all,
+    ActionListener, ActionResponse, BusSelection, CallbackResult, CloseHandler, CloseReason,
+    ConnectionRef, DbusStack, Event, Events, HandleConnection, ListenerHandle, NotificationHandle,
+    StopHandle, Subscription, WaitError, WaitTimedOut,
+};
+
+#[cfg(all(feature = "dbus", unix, not(target_os = "macos")))]
+pub use crate::xdg::{call_raw_dbus, SignalDispatcher};
+
+#[cfg(all(feature = "dbus_tokio", unix, not(target_os = "macos")))]
+pub use crate::xdg::dbus_tokio;
+
+#[cfg(all(feature = "dbus", feature = "zbus", unix, not(target_os = "macos")))]
+pub use crate::xdg::DBUS_SWITCH_VAR;
+
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+pub use crate::xdg::{
+    call_raw_zbus, NotificationDispatcher, NotificationsProxy, NotificationsProxyBlocking,
+};
+
+#[cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+pub use crate::xdg::{
+    capabilities_cached, close_notification_async, get_capabilities_async,
+    get_server_information_async, reset_connection, send_all, subscribe_all_async, WaitCanceller,
 };
 
 // #[cfg(all(feature = "server", unix, not(target_os = "macos")))]
@@ -200,6 +267,12 @@ pub use crate::xdg::{
 
 pub use crate::hints::Hint;
 
+#[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+pub use crate::hints::build_hint_map;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use crate::capabilities::{Adaptation, Capability, CapabilitySet};
+
 #[cfg(all(feature = "images", unix, not(target_os = "macos")))]
 pub use crate::image::{Image, ImageError};
 
@@ -209,7 +282,7 @@ pub use crate::image::{Image, ImageError};
 )]
 pub use crate::urgency::Urgency;
 
-pub use crate::{notification::Notification, timeout::Timeout};
+pub use crate::{action_key::ActionKey, notification::Notification, timeout::Timeout};
 
 #[cfg(all(feature = "images", unix, not(target_os = "macos")))]
 lazy_static! {
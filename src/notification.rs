@@ -13,10 +13,28 @@ use crate::macos;
 #[cfg(target_os = "windows")]
 use crate::windows;
 
-use crate::{error::*, timeout::Timeout};
+use crate::{action_key::ActionKey, error::*, timeout::Timeout};
 
 #[cfg(all(unix, not(target_os = "macos")))]
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::time::Duration;
+
+/// The hint KDE and GNOME use to tell which process sent a notification.
+#[cfg(all(unix, not(target_os = "macos")))]
+const SENDER_PID: &str = "sender-pid";
+
+/// Upper bound for the delay between two attempts of `show_with_retry()`.
+#[cfg(all(unix, not(target_os = "macos")))]
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(4);
+
+/// Delay after the failed `attempt`, starting at `backoff` and doubling each time.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn retry_delay(backoff: Duration, attempt: u32) -> Duration {
+    2u32.checked_pow(attempt.saturating_sub(1))
+        .and_then(|factor| backoff.checked_mul(factor))
+        .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY))
+}
 
 // Returns the name of the current executable, used as a default for `Notification.appname`.
 fn exe_name() -> String {
@@ -91,6 +109,29 @@ pub struct Notification {
     #[cfg(all(unix, not(target_os = "macos")))]
     pub(crate) bus: xdg::NotificationBus,
 
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) compat_image_hints: bool,
+
+    /// Set by `action_with_icon()`, implies `Hint::ActionIcons(true)`.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) icon_actions: bool,
+
+    /// Set by `on_bus()` and `on_system_bus()`, the bus the notification is sent on.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) bus_selection: xdg::BusSelection,
+
+    /// How long sending waits for the server's reply, see `Notification::send_timeout()`.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) send_timeout: Option<Duration>,
+
+    /// Set by `raw_hints_extend()`, sent as they are on top of `hints`.
+    #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+    pub(crate) raw_hints: HashMap<String, zbus::zvariant::OwnedValue>,
+
+    /// Set by `require_stable_id()`, replacing a notification must keep its id.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) require_stable_id: bool,
+
     /// Lifetime of the Notification in ms. Often not respected by server, sorry.
     pub timeout: Timeout, // both gnome and galago want allow for -1
 
@@ -113,9 +154,7 @@ impl Notification {
     #[doc(hidden)]
     #[deprecated(note = "this is a test only feature")]
     pub fn at_bus(sub_bus: &str) -> Notification {
-        let bus = xdg::NotificationBus::custom(sub_bus)
-            .ok_or("invalid subpath")
-            .unwrap();
+        let bus = xdg::NotificationBus::custom(sub_bus).unwrap_or_else(|error| panic!("{}", error));
         Notification {
             bus,
             ..Notification::default()
@@ -186,6 +225,21 @@ impl Notification {
         Ok(self)
     }
 
+    /// Send image hints under the key names of older specification versions.
+    ///
+    /// Spec 1.0 and 1.1 servers expect `icon_data`, `image_data` and `image_path` instead of
+    /// `image-data` and `image-path`, and won't show any image if they receive the wrong one.
+    /// When enabled the key is picked according to the `spec_version` of the last call to
+    /// [`get_server_information()`](`crate::get_server_information`).
+    /// If that is unknown the hint is sent under all known names.
+    ///
+    /// (xdg only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn compat_image_hints(&mut self, compat: bool) -> &mut Notification {
+        self.compat_image_hints = compat;
+        self
+    }
+
     /// Wrapper for `Hint::SoundName`
     #[cfg(all(unix, not(target_os = "macos")))]
     pub fn sound_name(&mut self, name: &str) -> &mut Notification {
@@ -238,6 +292,7 @@ impl Notification {
     ///
     /// This method will add a hint to the internal hint [`HashSet`].
     /// Hints must be of type [`Hint`].
+    /// A hint with the same key that was set before is replaced, see [`effective_hints()`](#method.effective_hints).
     ///
     /// Many of these are again wrapped by more convenient functions such as:
     ///
@@ -265,23 +320,115 @@ impl Notification {
     pub fn hint(&mut self, hint: Hint) -> &mut Notification {
         match hint {
             Hint::CustomInt(k, v) => {
+                self.hints_unique.retain(|(key, _), _| *key != k);
                 self.hints_unique
                     .insert((k.clone(), CustomHintType::Int), Hint::CustomInt(k, v));
             }
             Hint::Custom(k, v) => {
+                self.hints_unique.retain(|(key, _), _| *key != k);
                 self.hints_unique
                     .insert((k.clone(), CustomHintType::String), Hint::Custom(k, v));
             }
             _ => {
+                self.hints.retain(|existing| existing.key() != hint.key());
                 self.hints.insert(hint);
             }
         }
         self
     }
 
+    /// Tells the server which application sends the notification, per-app settings like those of GNOME need that.
+    ///
+    /// Sets the `sender-pid` hint to the current process.
+    /// On Linux it also sets [`Hint::DesktopEntry`] if exactly one `.desktop` file
+    /// under `$XDG_DATA_DIRS/applications` runs this executable, see [`std::env::current_exe`].
+    /// If none or several do, only the pid is sent. A desktop entry that was set before is kept.
+    ///
+    /// (xdg only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn identify_sender(&mut self) -> &mut Notification {
+        if let Ok(pid) = i32::try_from(std::process::id()) {
+            self.hint(Hint::CustomInt(SENDER_PID.into(), pid));
+        }
+        #[cfg(target_os = "linux")]
+        {
+            let has_entry = self
+                .hints
+                .iter()
+                .any(|hint| matches!(hint, Hint::DesktopEntry(_)));
+            if !has_entry {
+                if let Some(entry) = crate::desktop_entry::current() {
+                    self.hint(Hint::DesktopEntry(entry));
+                }
+            }
+        }
+        self
+    }
+
+    /// Removes the hints that should not be repeated by an update, see [`Hint::is_one_shot`].
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) fn remove_one_shot_hints(&mut self) {
+        self.hints.retain(|hint| !hint.is_one_shot());
+        self.hints_unique.retain(|_, hint| !hint.is_one_shot());
+    }
+
+    /// Hints in order of increasing precedence, may contain several hints with the same key.
     #[cfg(all(unix, not(target_os = "macos")))]
     pub(crate) fn get_hints(&self) -> impl Iterator<Item = &Hint> {
-        self.hints.iter().chain(self.hints_unique.values())
+        static IMPLIED_ACTION_ICONS: Hint = Hint::ActionIcons(true);
+        let implied = self.implies_action_icons().then_some(&IMPLIED_ACTION_ICONS);
+        implied
+            .into_iter()
+            .chain(self.hints.iter())
+            .chain(self.hints_unique.values())
+    }
+
+    /// All hints exactly as they are going to be sent, one per key, ordered by key.
+    ///
+    /// Should several hints end up with the same key, custom hints ([`Hint::Custom`], [`Hint::CustomInt`])
+    /// take precedence over the built-in ones, those over hints implied by the builder
+    /// (e.g. `Hint::ActionIcons` through [`action_with_icon()`](#method.action_with_icon)).
+    /// Among hints of the same kind, the last call to [`hint()`](#method.hint) wins.
+    /// Which one is picked is unspecified only if you inserted conflicting hints into `hints` directly.
+    ///
+    /// (xdg only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn effective_hints(&self) -> impl Iterator<Item = &Hint> {
+        self.get_hints()
+            .map(|hint| (hint.key(), hint))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+    }
+
+    /// The actions are pairs of a key and a label, and no key is empty.
+    ///
+    /// Anything else can only come from changing `actions` directly, servers would pair up keys and labels wrongly.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) fn check_actions(&self) -> Result<()> {
+        if self.actions.len() % 2 != 0 {
+            let dangling = self.actions.last().map(String::as_str).unwrap_or_default();
+            return Err(
+                ErrorKind::InvalidActions(format!("action {:?} has no label", dangling)).into(),
+            );
+        }
+        if let Some(pair) = self.actions.chunks_exact(2).find(|pair| pair[0].is_empty()) {
+            return Err(ErrorKind::InvalidActions(format!(
+                "action {:?} has an empty key",
+                pair[1]
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Icon-named actions were added, but `Hint::ActionIcons` was not set either way.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn implies_action_icons(&self) -> bool {
+        self.icon_actions
+            && !self
+                .hints
+                .iter()
+                .any(|hint| matches!(hint, Hint::ActionIcons(_)))
     }
 
     /// Set the `timeout`.
@@ -323,6 +470,36 @@ impl Notification {
         self
     }
 
+    /// Set the `timeout` as the specification's raw milliseconds.
+    ///
+    /// `-1` (or any other negative value) leaves the timeout to the server, `0` means never expire.
+    /// This is the same as `timeout(ms)`, but spells out that no conversion takes place.
+    pub fn timeout_ms_raw(&mut self, ms: i32) -> &mut Notification {
+        self.timeout = Timeout::from(ms);
+        self
+    }
+
+    /// Returns the current `timeout`.
+    ///
+    /// Use [`Timeout::as_millis_raw()`] to get what is sent on the wire.
+    pub fn get_timeout(&self) -> Timeout {
+        self.timeout
+    }
+
+    /// Set how long sending waits for the server to answer.
+    ///
+    /// Without it the dbus backend gives up after 2 seconds and zbus waits as long as the connection does.
+    /// A server that does not answer in time fails the send with [`ErrorKind::ReplyTimeout`],
+    /// the notification may still show up once it catches up.
+    /// This applies to `show()` and `update()`, not to `send_all()`.
+    ///
+    /// This is unrelated to [`timeout()`](#method.timeout), which is how long the notification is displayed.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn send_timeout(&mut self, timeout: Duration) -> &mut Notification {
+        self.send_timeout = Some(timeout);
+        self
+    }
+
     /// Set the `urgency`.
     ///
     /// Pick between Medium, Low and High.
@@ -357,14 +534,52 @@ impl Notification {
     /// Add an action.
     ///
     /// This adds a single action to the internal list of actions.
+    /// The `identifier` is what is reported back once the action is invoked, never the `label`.
+    /// Use [`ActionKey::DEFAULT`] for the action invoked by clicking the notification itself.
     ///
     /// (xdg only)
-    pub fn action(&mut self, identifier: &str, label: &str) -> &mut Notification {
-        self.actions.push(identifier.to_owned());
+    pub fn action(&mut self, identifier: impl Into<ActionKey>, label: &str) -> &mut Notification {
+        self.actions.push(identifier.into().into());
         self.actions.push(label.to_owned());
         self
     }
 
+    /// Add an action that the server should display as an icon.
+    ///
+    /// Like [`action()`](#method.action), but the identifier is also an icon name from the icon theme.
+    /// Unless you set [`Hint::ActionIcons`] yourself, `Hint::ActionIcons(true)` is sent along
+    /// with the notification, otherwise servers would show the raw icon name as label.
+    ///
+    /// (xdg only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn action_with_icon(&mut self, icon_name: &str, label: &str) -> &mut Notification {
+        self.icon_actions = true;
+        self.action(icon_name, label)
+    }
+
+    /// Replace the label of an already added action.
+    ///
+    /// Useful for re-sending a notification with the same action keys in a different language.
+    /// Only the label of the first action with a matching `identifier` is replaced, the identifier
+    /// and the order of actions stay the same.
+    ///
+    /// Returns `false` if no action with this `identifier` exists.
+    ///
+    /// (xdg only)
+    pub fn relabel_action(&mut self, identifier: &str, label: &str) -> bool {
+        match self
+            .actions
+            .chunks_exact_mut(2)
+            .find(|pair| pair[0] == identifier)
+        {
+            Some(pair) => {
+                label.clone_into(&mut pair[1]);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Set an Id ahead of time
     ///
     /// Setting the id ahead of time allows overriding a known other notification.
@@ -381,6 +596,12 @@ impl Notification {
     ///
     /// Part of the builder pattern, returns a complete copy of the built notification.
     pub fn finalize(&self) -> Notification {
+        #[cfg(all(unix, not(target_os = "macos")))]
+        if self.implies_action_icons() {
+            let mut finalized = self.clone();
+            finalized.hints.insert(Hint::ActionIcons(true));
+            return finalized;
+        }
         self.clone()
     }
 
@@ -415,6 +636,11 @@ impl Notification {
 
     /// Sends Notification to D-Bus.
     ///
+    /// All calls share one session connection, opened by the first one and reopened should it break,
+    /// see [`reset_connection()`](crate::reset_connection).
+    /// zbus runs that connection on its own executor thread, so it does not matter which runtime calls this,
+    /// unless the `tokio` feature is enabled, then it has to be called inside a tokio runtime.
+    ///
     /// Returns a handle to a notification
     #[cfg(all(unix, not(target_os = "macos")))]
     #[cfg(all(feature = "async", feature = "zbus"))]
@@ -422,17 +648,250 @@ impl Notification {
         xdg::show_notification_async(self).await
     }
 
+    /// Sends the notification without waiting for the server to answer, e.g. for a volume display while a slider is dragged.
+    ///
+    /// The server is told not to reply, so there is no id and no handle.
+    /// Errors only come from building the message or from the connection, a notification the server rejects is lost silently.
+    /// Show the first one with [`show()`](#method.show) and send the rest with its [`id()`](#method.id),
+    /// so they replace it instead of piling up.
+    ///
+    /// With zbus this sends through the connection [`show_async()`](#method.show_async) shares.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn show_nowait(&self) -> Result<()> {
+        xdg::show_notification_nowait(self)
+    }
+
+    /// Async version of [`show_nowait()`](#method.show_nowait).
+    ///
+    /// (zbus only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn show_nowait_async(&self) -> Result<()> {
+        xdg::show_notification_nowait_async(self).await
+    }
+
+    /// Like [`show_async()`](#method.show_async), but sends through a `connection` you already have.
+    ///
+    /// The handle keeps a clone of `connection` and waits for signals on it, no other connection is opened.
+    /// It has to be a session connection, that is where notification servers live,
+    /// [`at_bus()`](#method.at_bus) only changes the name the notification is sent to.
+    /// A peer-to-peer connection straight to a server works as well, e.g. over a socket pair in tests without any bus.
+    ///
+    /// (zbus only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn show_async_on(
+        &self,
+        connection: &zbus::Connection,
+    ) -> Result<xdg::NotificationHandle> {
+        xdg::show_notification_on(self, connection).await
+    }
+
+    /// Blocking version of [`show_async_on()`](#method.show_async_on).
+    ///
+    /// (zbus only)
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg(feature = "zbus")]
+    pub fn show_on(&self, connection: &zbus::Connection) -> Result<xdg::NotificationHandle> {
+        zbus::block_on(xdg::show_notification_on(self, connection))
+    }
+
+    /// Like [`show()`](#method.show), but sends to the bus a notification built with [`at_bus()`](#method.at_bus) goes to.
+    ///
+    /// Fails without sending anything if `sub_bus` doesn't make a valid bus name.
+    /// The handle listens for the signals of the server at that bus, and updates and closes the notification there.
+    ///
+    /// This is for testing purposes only and will not work with actual implementations.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn show_at_bus(&self, sub_bus: &str) -> Result<xdg::NotificationHandle> {
+        let bus = xdg::custom_bus(sub_bus)?;
+        Notification {
+            bus,
+            ..self.clone()
+        }
+        .show()
+    }
+
+    /// Send to a server on the system bus instead of the session bus.
+    ///
+    /// Meant for daemons without a session, talking to a bridge that relays to the desktops.
+    /// The system bus only lets through what its policy allows, the server needs a busconfig like this
+    /// (e.g. in `/etc/dbus-1/system.d/`), otherwise sending fails with [`ErrorKind::PermissionDenied`]:
+    ///
+    /// ```xml
+    /// <busconfig>
+    ///   <policy user="bridge">
+    ///     <allow own="de.hoodie.Notification.bridge"/>
+    ///   </policy>
+    ///   <policy context="default">
+    ///     <allow send_destination="de.hoodie.Notification.bridge"
+    ///            send_interface="org.freedesktop.Notifications"/>
+    ///   </policy>
+    /// </busconfig>
+    /// ```
+    ///
+    /// The handle listens on the system bus as well.
+    /// `show_async_on()` and the `NotificationDispatcher` send through their connection regardless.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn on_system_bus(&mut self, system_bus: bool) -> &mut Notification {
+        self.bus_selection = if system_bus {
+            xdg::BusSelection::System
+        } else {
+            xdg::BusSelection::Session
+        };
+        self
+    }
+
+    /// Sends the notification on `bus`, like [`on_system_bus()`](Notification::on_system_bus) but also to any bus address.
+    ///
+    /// ```no_run
+    /// # use notify_rust::*;
+    /// Notification::new()
+    ///     .summary("from the appliance")
+    ///     .on_bus(BusSelection::Address("unix:path=/run/bridge/bus".into()))
+    ///     .show()?;
+    /// # Ok::<(), notify_rust::error::Error>(())
+    /// ```
+    ///
+    /// The handle listens on that bus as well, with the same exceptions as `on_system_bus()`.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn on_bus(&mut self, bus: xdg::BusSelection) -> &mut Notification {
+        self.bus_selection = bus;
+        self
+    }
+
+    /// Adds hints as raw D-Bus values, for servers that expect values [`Hint`] can't express, like nested dicts.
+    ///
+    /// These are sent as they are and take the place of typed hints with the same key.
+    /// See [`build_hint_map()`](crate::build_hint_map) for everything that is sent.
+    ///
+    /// (zbus only, the dbus backend leaves them out)
+    #[cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+    pub fn raw_hints_extend(
+        &mut self,
+        hints: impl IntoIterator<Item = (String, zbus::zvariant::OwnedValue)>,
+    ) -> &mut Notification {
+        self.raw_hints.extend(hints);
+        self
+    }
+
+    /// Fail when the server does not replace the notification in place.
+    ///
+    /// Sending with an [`id()`](#method.id), and every `update()`, asks the server to replace that notification.
+    /// Some servers show a new one with a new id instead, e.g. once the old one expired.
+    /// By default the handle just follows the new id and [`was_replaced()`](crate::NotificationHandle::was_replaced) tells the difference,
+    /// with this the send fails with [`ErrorKind::IdChanged`] as well.
+    /// The notification is shown regardless, an updated handle still follows the new id.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn require_stable_id(&mut self, require: bool) -> &mut Notification {
+        self.require_stable_id = require;
+        self
+    }
+
     /// Sends Notification to D-Bus.
     ///
     /// Returns a handle to a notification
     #[cfg(all(unix, not(target_os = "macos")))]
-    #[cfg(feature = "async")]
+    #[cfg(all(feature = "async", feature = "zbus"))]
     // #[cfg(test)]
     pub async fn show_async_at_bus(&self, sub_bus: &str) -> Result<xdg::NotificationHandle> {
-        let bus = xdg::NotificationBus::custom(sub_bus).ok_or("invalid subpath")?;
+        let bus = xdg::custom_bus(sub_bus)?;
         xdg::show_notification_async_at_bus(self, bus).await
     }
 
+    /// Like [`show()`](#method.show), but keeps trying while no notification server is running.
+    ///
+    /// Programs started along with the desktop session can race the notification server,
+    /// so the first attempts may fail with `ServiceUnknown`.
+    /// Only that and `NameHasNoOwner` are retried, any other error is returned right away.
+    ///
+    /// Waits `backoff` after the first failed attempt and doubles the delay after each one, up to 4 seconds.
+    /// Should all `attempts` fail, the last error is returned, annotated with the number of attempts.
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # use std::time::Duration;
+    /// Notification::new()
+    ///     .summary("Daemon started")
+    ///     .show_with_retry(5, Duration::from_millis(250))
+    ///     .unwrap();
+    /// ```
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn show_with_retry(
+        &self,
+        attempts: u32,
+        backoff: Duration,
+    ) -> Result<xdg::NotificationHandle> {
+        let mut attempt = 1;
+        loop {
+            match self.show() {
+                Err(error) if error.is_server_missing() && attempt < attempts => {
+                    std::thread::sleep(retry_delay(backoff, attempt));
+                    attempt += 1;
+                }
+                Err(error) if error.is_server_missing() => {
+                    return Err(error.after_attempts(attempt))
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Async version of [`show_with_retry()`](#method.show_with_retry).
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn show_with_retry_async(
+        &self,
+        attempts: u32,
+        backoff: Duration,
+    ) -> Result<xdg::NotificationHandle> {
+        let mut attempt = 1;
+        loop {
+            match self.show_async().await {
+                Err(error) if error.is_server_missing() && attempt < attempts => {
+                    xdg::timer::sleep(retry_delay(backoff, attempt)).await;
+                    attempt += 1;
+                }
+                Err(error) if error.is_server_missing() => {
+                    return Err(error.after_attempts(attempt))
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Shows the notification and blocks until something happens to it.
+    ///
+    /// Returns the first [`Event`](xdg::Event), handy when you need the chosen action as a value
+    /// rather than inside a closure as with [`wait_for_action`](xdg::NotificationHandle::wait_for_action).
+    ///
+    /// ```no_run
+    /// # use notify_rust::{Event, Notification};
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let install = match Notification::new()
+    ///     .summary("Update available")
+    ///     .action("install", "Install")
+    ///     .action("later", "Later")
+    ///     .show_and_wait()?
+    /// {
+    ///     Event::ActionInvoked(action) => action == "install",
+    ///     _ => false,
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn show_and_wait(&self) -> Result<xdg::Event> {
+        self.show()?.wait()
+    }
+
+    /// Async version of [`show_and_wait()`](#method.show_and_wait).
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn show_and_wait_async(&self) -> Result<xdg::Event> {
+        self.show_async().await?.wait_async().await
+    }
+
     /// Sends Notification to `NSUserNotificationCenter`.
     ///
     /// Returns an `Ok` no matter what, since there is currently no way of telling the success of
@@ -481,6 +940,13 @@ impl Default for Notification {
             actions: Vec::new(),
             timeout: Timeout::Default,
             bus: Default::default(),
+            compat_image_hints: false,
+            icon_actions: false,
+            bus_selection: xdg::BusSelection::Session,
+            send_timeout: None,
+            #[cfg(feature = "zbus")]
+            raw_hints: HashMap::new(),
+            require_stable_id: false,
             id: None,
         }
     }
@@ -517,3 +983,191 @@ impl Default for Notification {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_actions() -> Notification {
+        Notification::new()
+            .action("archive", "Archive")
+            .action("delete", "Delete")
+            .finalize()
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn actions_come_in_pairs_with_keys() {
+        let mut notification = with_actions();
+        assert!(notification.check_actions().is_ok());
+
+        notification.actions.push("dangling".into());
+        let error = notification.check_actions().unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::InvalidActions(_)),
+            "{}",
+            error
+        );
+        assert_eq!(
+            error.to_string(),
+            r#"invalid actions: action "dangling" has no label"#
+        );
+
+        notification.actions.pop();
+        notification.action("", "Nameless");
+        let error = notification.check_actions().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"invalid actions: action "Nameless" has an empty key"#
+        );
+    }
+
+    #[test]
+    fn relabel_existing_action() {
+        let mut notification = with_actions();
+        assert!(notification.relabel_action("delete", "Löschen"));
+        assert_eq!(
+            notification.actions,
+            vec!["archive", "Archive", "delete", "Löschen"]
+        );
+    }
+
+    #[test]
+    fn relabel_missing_action() {
+        let mut notification = with_actions();
+        assert!(!notification.relabel_action("spam", "Spam"));
+        // labels are not keys
+        assert!(!notification.relabel_action("Archive", "Archivieren"));
+        assert_eq!(
+            notification.actions,
+            vec!["archive", "Archive", "delete", "Delete"]
+        );
+    }
+
+    #[test]
+    fn relabel_repeatedly() {
+        let mut notification = with_actions();
+        for label in ["Archivieren", "Archiver", "Archive"] {
+            assert!(notification.relabel_action("archive", label));
+            assert_eq!(notification.actions.len(), 4);
+            assert_eq!(notification.actions[1], label);
+        }
+        assert_eq!(
+            notification.actions,
+            vec!["archive", "Archive", "delete", "Delete"]
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn action_icons_hints(notification: &Notification) -> Vec<&Hint> {
+        notification
+            .get_hints()
+            .filter(|hint| matches!(hint, Hint::ActionIcons(_)))
+            .collect()
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn icon_actions_imply_action_icons() {
+        let notification = Notification::new()
+            .action_with_icon("media-playback-start", "Play")
+            .finalize();
+        assert!(notification.hints.contains(&Hint::ActionIcons(true)));
+        assert_eq!(
+            action_icons_hints(&notification),
+            vec![&Hint::ActionIcons(true)]
+        );
+
+        // also applies when sent without finalizing
+        let mut unfinalized = Notification::new();
+        unfinalized.action_with_icon("media-playback-start", "Play");
+        assert!(unfinalized.hints.is_empty());
+        assert_eq!(
+            action_icons_hints(&unfinalized),
+            vec![&Hint::ActionIcons(true)]
+        );
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn explicit_action_icons_false_wins() {
+        let notification = Notification::new()
+            .hint(Hint::ActionIcons(false))
+            .action_with_icon("media-playback-start", "Play")
+            .finalize();
+        assert_eq!(
+            action_icons_hints(&notification),
+            vec![&Hint::ActionIcons(false)]
+        );
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn plain_actions_imply_nothing() {
+        let notification = with_actions();
+        assert!(action_icons_hints(&notification).is_empty());
+        assert!(notification.hints.is_empty());
+    }
+
+    #[test]
+    fn raw_timeout() {
+        let mut notification = Notification::new();
+        assert_eq!(notification.get_timeout(), Timeout::Default);
+        assert_eq!(notification.timeout_ms_raw(0).get_timeout(), Timeout::Never);
+        assert_eq!(
+            notification.timeout_ms_raw(1500).get_timeout(),
+            Timeout::Milliseconds(1500)
+        );
+        assert_eq!(
+            notification.timeout_ms_raw(-1).get_timeout(),
+            Timeout::Default
+        );
+        assert_eq!(
+            notification
+                .timeout_ms_raw(-5)
+                .get_timeout()
+                .as_millis_raw(),
+            -1
+        );
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn retry_delay_doubles_up_to_cap() {
+        let backoff = Duration::from_millis(300);
+        let delays = (1..=6)
+            .map(|attempt| retry_delay(backoff, attempt).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![300, 600, 1200, 2400, 4000, 4000]);
+        assert_eq!(retry_delay(backoff, u32::MAX), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(Duration::ZERO, 3), Duration::ZERO);
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn identify_sender_keeps_an_explicit_desktop_entry() {
+        let notification = Notification::new()
+            .hint(Hint::DesktopEntry("org.example.App".into()))
+            .identify_sender()
+            .finalize();
+        let pid = i32::try_from(std::process::id()).unwrap();
+        assert!(notification
+            .get_hints()
+            .any(|hint| *hint == Hint::CustomInt(SENDER_PID.into(), pid)));
+        assert_eq!(
+            notification
+                .get_hints()
+                .filter(|hint| matches!(hint, Hint::DesktopEntry(_)))
+                .collect::<Vec<_>>(),
+            [&Hint::DesktopEntry("org.example.App".into())]
+        );
+    }
+
+    #[test]
+    fn relabel_ignores_dangling_identifier() {
+        let mut notification = with_actions();
+        notification.actions.push("dangling".into());
+        assert!(!notification.relabel_action("dangling", "Label"));
+        assert_eq!(notification.actions.len(), 5);
+    }
+}
@@ -55,16 +55,73 @@ impl From<Duration> for Timeout {
     }
 }
 
-impl From<Timeout> for i32 {
-    fn from(timeout: Timeout) -> Self {
-        match timeout {
+impl Timeout {
+    /// The timeout as the specification puts it on the wire.
+    ///
+    /// `-1` leaves the timeout to the server and `0` means the notification never expires.
+    /// Note that this makes `Timeout::Milliseconds(0)` indistinguishable from `Timeout::Never`,
+    /// converting it back yields the latter.
+    /// Durations that exceed `i32::MAX` milliseconds are saturated.
+    ///
+    /// ```
+    /// # use notify_rust::Timeout;
+    /// assert_eq!(Timeout::Default.as_millis_raw(), -1);
+    /// assert_eq!(Timeout::Never.as_millis_raw(), 0);
+    /// assert_eq!(Timeout::Milliseconds(42).as_millis_raw(), 42);
+    /// assert_eq!(Timeout::from(Timeout::Milliseconds(0).as_millis_raw()), Timeout::Never);
+    /// ```
+    pub fn as_millis_raw(self) -> i32 {
+        match self {
             Timeout::Default => -1,
             Timeout::Never => 0,
-            Timeout::Milliseconds(ms) => ms as i32,
+            Timeout::Milliseconds(ms) => i32::try_from(ms).unwrap_or(i32::MAX),
         }
     }
 }
 
+#[test]
+fn timeout_as_millis_raw() {
+    assert_eq!(Timeout::Default.as_millis_raw(), -1);
+    assert_eq!(Timeout::Never.as_millis_raw(), 0);
+    assert_eq!(Timeout::Milliseconds(0).as_millis_raw(), 0);
+    assert_eq!(Timeout::Milliseconds(1).as_millis_raw(), 1);
+    assert_eq!(Timeout::Milliseconds(u32::MAX).as_millis_raw(), i32::MAX);
+}
+
+#[test]
+fn timeout_raw_roundtrip() {
+    // every power of two, its neighbours and a spread of arbitrary values in between
+    let samples = (0..32)
+        .flat_map(|shift| {
+            let power = 1u32 << shift;
+            [power - 1, power, power.saturating_add(1)]
+        })
+        .chain((0..1000u32).map(|i| i.wrapping_mul(2_654_435_761) >> 1))
+        .filter(|ms| *ms > 0 && *ms <= i32::MAX as u32);
+
+    for ms in samples {
+        let timeout = Timeout::Milliseconds(ms);
+        assert_eq!(Timeout::from(timeout.as_millis_raw()), timeout);
+    }
+    for timeout in [Timeout::Default, Timeout::Never] {
+        assert_eq!(Timeout::from(timeout.as_millis_raw()), timeout);
+    }
+    for raw in [i32::MIN, -2, -1, 0, 1, i32::MAX] {
+        assert_eq!(Timeout::from(raw).as_millis_raw(), raw.max(-1));
+    }
+    // zero milliseconds means never on the wire
+    assert_eq!(
+        Timeout::from(Timeout::Milliseconds(0).as_millis_raw()),
+        Timeout::Never
+    );
+}
+
+impl From<Timeout> for i32 {
+    fn from(timeout: Timeout) -> Self {
+        timeout.as_millis_raw()
+    }
+}
+
 impl FromStr for Timeout {
     type Err = ParseIntError;
 
@@ -77,6 +134,7 @@ impl FromStr for Timeout {
     }
 }
 
+#[allow(dead_code)]
 pub struct TimeoutMessage(Timeout);
 
 impl From<Timeout> for TimeoutMessage {
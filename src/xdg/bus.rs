@@ -1,4 +1,6 @@
-use crate::xdg::NOTIFICATION_DEFAULT_BUS;
+use crate::error::InvalidBusName;
+use crate::xdg::{check_bus_name, NOTIFICATION_DEFAULT_BUS};
+use std::fmt;
 
 fn skip_first_slash(s: &str) -> &str {
     if let Some('/') = s.chars().next() {
@@ -50,19 +52,36 @@ impl NotificationBus {
     }
 
     #[cfg(feature = "zbus")]
-    pub fn custom(custom_path: &str) -> Option<Self> {
-        let name =
-            zbus::names::WellKnownName::try_from(Self::namespaced_custom(custom_path)?).ok()?;
-        Some(Self(name.to_string().into()))
+    pub fn custom(custom_path: &str) -> Result<Self, InvalidBusName> {
+        let name = Self::namespaced_custom(custom_path)
+            .ok_or_else(|| InvalidBusName::new(custom_path, "is no valid path"))?;
+        check_bus_name(&name)?;
+        zbus::names::WellKnownName::try_from(name.as_str())
+            .map_err(|error| InvalidBusName::new(&name, error.to_string()))?;
+        Ok(Self(name.into()))
     }
 
     #[cfg(all(feature = "dbus", not(feature = "zbus")))]
-    pub fn custom(custom_path: &str) -> Option<Self> {
-        let name = dbus::strings::BusName::new(Self::namespaced_custom(custom_path)?).ok()?;
-        Some(Self(name.to_string().into()))
+    pub fn custom(custom_path: &str) -> Result<Self, InvalidBusName> {
+        let name = Self::namespaced_custom(custom_path)
+            .ok_or_else(|| InvalidBusName::new(custom_path, "is no valid path"))?;
+        check_bus_name(&name)?;
+        dbus::strings::BusName::new(name.as_str())
+            .map_err(|error| InvalidBusName::new(&name, error))?;
+        Ok(Self(name.into()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 
     pub fn into_name(self) -> BusNameType {
         self.0
     }
 }
+
+impl fmt::Display for NotificationBus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
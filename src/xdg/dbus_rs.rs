@@ -1,24 +1,34 @@
 use dbus::{
     arg::messageitem::{MessageItem, MessageItemArray},
-    ffidisp::{BusType, Connection, ConnectionItem},
-    Message,
+    ffidisp::{BusType, Connection},
+    message::MatchRule,
+    Message, MessageType,
+};
+
+use std::{
+    cell::RefCell,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
 };
 
 use super::{
-    bus::NotificationBus, ActionResponse, ActionResponseHandler, CloseReason,
+    bus::NotificationBus, listener::Stop, trace, ActionResponseHandler, ActivationToken,
+    BusSelection, CloseReason, Event, TokenPairing, WaitError, WaitTimedOut,
     NOTIFICATION_INTERFACE,
 };
 
 use crate::{
     error::*,
-    hints::message::HintMessage,
+    hints::{compat_image_keys, message::HintMessage},
     notification::Notification,
-    xdg::{ServerInformation, NOTIFICATION_OBJECTPATH},
+    xdg::{self, ServerInformation, NOTIFICATION_OBJECTPATH},
 };
 
 pub mod bus {
 
-    use crate::xdg::NOTIFICATION_DEFAULT_BUS;
+    use crate::error::InvalidBusName;
+    use crate::xdg::{check_bus_name, NOTIFICATION_DEFAULT_BUS};
 
     fn skip_first_slash(s: &str) -> &str {
         if let Some('/') = s.chars().next() {
@@ -28,6 +38,7 @@ pub mod bus {
         }
     }
 
+    use std::fmt;
     use std::path::PathBuf;
 
     type BusNameType = dbus::strings::BusName<'static>;
@@ -53,15 +64,29 @@ pub mod bus {
             .into()
         }
 
-        pub fn custom(custom_path: &str) -> Option<Self> {
-            let name = dbus::strings::BusName::new(Self::namespaced_custom(custom_path)?).ok()?;
-            Some(Self(name))
+        pub fn custom(custom_path: &str) -> Result<Self, InvalidBusName> {
+            let name = Self::namespaced_custom(custom_path)
+                .ok_or_else(|| InvalidBusName::new(custom_path, "is no valid path"))?;
+            check_bus_name(&name)?;
+            dbus::strings::BusName::new(name.clone())
+                .map(Self)
+                .map_err(|error| InvalidBusName::new(&name, error))
+        }
+
+        pub fn as_str(&self) -> &str {
+            &self.0
         }
 
         pub fn into_name(self) -> BusNameType {
             self.0
         }
     }
+
+    impl fmt::Display for NotificationBus {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
 }
 
 /// A handle to a shown notification.
@@ -72,6 +97,18 @@ pub struct DbusNotificationHandle {
     pub(crate) id: u32,
     pub(crate) connection: Connection,
     pub(crate) notification: Notification,
+    pub(crate) activation_token: ActivationToken,
+    /// The unique name of the server that answered `Notify`, only its signals are listened to.
+    pub(crate) server: Option<String>,
+    /// The last send replaced a notification in place.
+    pub(crate) replaced: bool,
+    /// The session bus address of a connection opened here, it is kept for later sends once the handle is dropped.
+    address: Option<String>,
+    polling: bool,
+    polled_token: Option<String>,
+    polled_close: bool,
+    /// The hints as last sent, dropped by `notification_mut()` so `update()` packs them again.
+    hints: Option<MessageItem>,
 }
 
 impl DbusNotificationHandle {
@@ -84,51 +121,319 @@ impl DbusNotificationHandle {
             id,
             connection,
             notification,
+            activation_token: ActivationToken::default(),
+            server: None,
+            replaced: false,
+            address: None,
+            polling: false,
+            polled_token: None,
+            polled_close: false,
+            hints: None,
+        }
+    }
+
+    /// The notification to change before the next `update()`.
+    pub(crate) fn notification_mut(&mut self) -> &mut Notification {
+        self.hints = None;
+        &mut self.notification
+    }
+
+    /// The bus name of the server the notification was sent to.
+    fn bus(&self) -> String {
+        self.notification.bus.clone().into_name().to_string()
+    }
+
+    pub fn into_parts(self) -> (u32, Connection, Notification) {
+        (self.id, self.connection, self.notification)
+    }
+
+    /// Keeps the connection for the next send of this thread, if it was opened by one.
+    pub(crate) fn recycle(mut self) {
+        self.stop_polling();
+        if let Some(address) = self.address.take() {
+            recycle(address, self.connection);
+        }
+    }
+
+    pub fn wait_for_action(&self, invocation_closure: impl ActionResponseHandler) {
+        wait_for_action_signal(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+            invocation_closure,
+        );
+    }
+
+    pub fn wait_for_action_timeout(
+        &self,
+        timeout: Duration,
+        invocation_closure: impl ActionResponseHandler,
+    ) -> std::result::Result<(), WaitTimedOut> {
+        let deadline = Instant::now() + timeout;
+        if wait_for_action_signal_until(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+            Some(deadline),
+            invocation_closure,
+        ) {
+            Ok(())
+        } else {
+            Err(WaitTimedOut)
+        }
+    }
+
+    pub fn wait_for_closed(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<CloseReason, WaitError> {
+        let deadline = Instant::now() + timeout;
+        let mut events = DbusEvents::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+        .map_err(|_| WaitError::ServerGone)?;
+        while let Some(event) = events.next_until(Some(deadline)) {
+            match event {
+                Event::Closed(reason) => return Ok(reason),
+                Event::ServerGone => return Err(WaitError::ServerGone),
+                _ => {}
+            }
+        }
+        Err(WaitError::Elapsed)
+    }
+
+    /// Skips all other events, `None` if the server went away.
+    pub fn wait_for_close(&self) -> Result<Option<CloseReason>> {
+        wait_for_close_signal(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+        )
+    }
+
+    pub fn poll_event(&mut self) -> Option<Event> {
+        if self.polled_close {
+            return None;
+        }
+        let (bus, server) = (self.bus(), self.server.as_deref());
+        if !self.polling {
+            // the rules stay until the notification is closed or the server changes
+            for rule in &signal_rules(&bus, server) {
+                self.connection.add_match(rule).ok()?;
+            }
+            self.polling = true;
+        }
+        let id = self.id;
+        let mut pending = self.polled_token.take();
+        let event = self.connection.incoming(0).find_map(|message| {
+            if let Some(token) = parse_activation_token(&message, server, id) {
+                pending = Some(token);
+                return None;
+            }
+            parse_event(&message, &bus, server, id)
+        });
+        if let Some(ref event) = event {
+            self.activation_token.record(event, pending);
+            self.polled_close = event.is_final();
+            if self.polled_close {
+                self.stop_polling();
+            }
+        } else {
+            // the action may still be on its way
+            self.polled_token = pending;
         }
+        event
     }
 
-    pub fn wait_for_action(self, invocation_closure: impl ActionResponseHandler) {
-        wait_for_action_signal(&self.connection, self.id, invocation_closure);
+    pub fn wait_for_event(&self) -> Result<Event> {
+        wait_for_event(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
     }
 
-    pub fn close(self) {
-        let mut message = build_message("CloseNotification", Default::default());
-        message.append_items(&[self.id.into()]);
-        let _ = self.connection.send(message); // If closing fails there's nothing we could do anyway
+    /// Listens in a new thread with a connection of its own, this one can't be sent there.
+    ///
+    /// Returns once the thread has subscribed, so no signal emitted afterwards is missed.
+    pub(crate) fn spawn_listener(
+        &self,
+        stop: Stop,
+        mut handler: impl FnMut(Event) + Send + 'static,
+    ) -> Result<thread::JoinHandle<()>> {
+        let (bus, id, tokens) = (self.bus(), self.id, self.activation_token.clone());
+        let (server, selection) = (self.server.clone(), self.notification.bus_selection.clone());
+        let (subscribed_tx, subscribed_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let connection = match connect(&selection) {
+                Ok(connection) => connection,
+                Err(error) => return subscribed_tx.send(Err(error)).unwrap_or(()),
+            };
+            let mut events =
+                match DbusEvents::subscribe(&connection, &bus, server.as_deref(), id, &tokens) {
+                    Ok(events) => events,
+                    Err(error) => return subscribed_tx.send(Err(error)).unwrap_or(()),
+                };
+            stop.wake_through(connection.unique_name(), selection);
+            let _ = subscribed_tx.send(Ok(()));
+            while let Some(event) = events.next_before(None, Some(&stop)) {
+                handler(event);
+            }
+        });
+        subscribed_rx
+            .recv()
+            .unwrap_or_else(|_| Err("listener thread panicked".into()))?;
+        Ok(thread)
+    }
+
+    pub fn events(&self) -> Result<DbusEvents<'_>> {
+        DbusEvents::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+    }
+
+    pub fn close(&self) -> Result<()> {
+        close_notification_on(&self.connection, self.notification.bus.clone(), self.id)
+    }
+
+    pub fn close_and_wait(&self) -> Result<CloseReason> {
+        let mut events = DbusEvents::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )?;
+        self.close()?;
+        events
+            .find_map(|event| match event {
+                Event::Closed(reason) => Some(reason),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                ErrorKind::Msg(format!(
+                    "connection closed before notification {} was closed",
+                    self.id
+                ))
+                .into()
+            })
     }
 
     pub fn on_close<F>(self, closure: F)
     where
         F: FnOnce(CloseReason),
     {
-        self.wait_for_action(|action: &ActionResponse| {
-            if let ActionResponse::Closed(reason) = action {
-                closure(*reason);
+        if let Ok(Some(reason)) = self.wait_for_close() {
+            closure(reason);
+        }
+    }
+
+    pub fn update(&mut self) -> Result<u32> {
+        // nothing changed through `notification_mut()` since the last send, the hints are still packed
+        let hints = match self.hints {
+            Some(ref hints) => hints,
+            None => self.hints.insert(pack_hints(&self.notification)?),
+        };
+        let bus = self.notification.bus.clone();
+        let message = notify_message_with_hints(&self.notification, self.id, bus, hints)?;
+        let (id, server) = send_notify(&self.notification, self.id, &self.connection, message)?;
+        if server != self.server {
+            self.stop_polling();
+        }
+        let replaced = xdg::check_replaced(&self.notification, self.id, id);
+        self.replaced = matches!(replaced, Ok(true));
+        // the handle follows the new id either way, so it can still be closed
+        self.id = id;
+        self.server = server;
+        replaced.map(|_| id)
+    }
+
+    /// Removes the match rules `poll_event()` added.
+    fn stop_polling(&mut self) {
+        if self.polling {
+            for rule in &signal_rules(&self.bus(), self.server.as_deref()) {
+                let _ = self.connection.remove_match(rule);
             }
-        });
+            self.polling = false;
+        }
     }
 
-    pub fn update(&mut self) {
-        self.id = send_notification_via_connection(&self.notification, self.id, &self.connection)
-            .unwrap();
+    /// Like `update()`, but forgets that the notification was closed before.
+    pub fn reshow(&mut self) -> Result<u32> {
+        self.polled_close = false;
+        self.polled_token = None;
+        self.update()
     }
 }
 
-pub fn send_notification_via_connection(
+pub fn send_notification_via_connection_at_bus(
     notification: &Notification,
     id: u32,
     connection: &Connection,
-) -> Result<u32> {
-    send_notification_via_connection_at_bus(notification, id, connection, Default::default())
+    bus: NotificationBus,
+) -> Result<(u32, Option<String>)> {
+    let message = notify_message(notification, id, bus)?;
+    send_notify(notification, id, connection, message)
 }
 
-pub fn send_notification_via_connection_at_bus(
+/// Sends the `Notify` call `message` for `notification`, replacing `id` unless it is 0.
+fn send_notify(
     notification: &Notification,
     id: u32,
     connection: &Connection,
+    message: Message,
+) -> Result<(u32, Option<String>)> {
+    let span = trace::notify_span(notification, id).entered();
+    let timeout = notification.send_timeout.unwrap_or(DEFAULT_REPLY_TIMEOUT);
+    let reply = call_within(connection, message, timeout)?;
+    let (id, server) = notify_reply(&reply);
+    trace::record_id(&span, id);
+    Ok((id, server))
+}
+
+/// The id in the reply to `Notify` and its sender, the server that shows the notification.
+pub(crate) fn notify_reply(reply: &Message) -> (u32, Option<String>) {
+    let server = reply.sender().map(|sender| sender.to_string());
+    let id = match reply.get_items().first() {
+        Some(MessageItem::UInt32(ref id)) => *id,
+        _ => 0,
+    };
+    (id, server)
+}
+
+/// The `Notify` call for `notification` at `bus`, replacing `id` unless it is 0.
+pub(crate) fn notify_message(
+    notification: &Notification,
+    id: u32,
+    bus: NotificationBus,
+) -> Result<Message> {
+    notify_message_with_hints(notification, id, bus, &pack_hints(notification)?)
+}
+
+/// Like `notify_message()`, but with `hints` packed before.
+fn notify_message_with_hints(
+    notification: &Notification,
+    id: u32,
     bus: NotificationBus,
-) -> Result<u32> {
-    let mut message = build_message("Notify", bus);
+    hints: &MessageItem,
+) -> Result<Message> {
+    notification.check_actions()?;
+    let mut message = build_message("Notify", bus)?;
     let timeout: i32 = notification.timeout.into();
     message.append_items(&[
         notification.appname.to_owned().into(), // appname
@@ -136,17 +441,41 @@ pub fn send_notification_via_connection_at_bus(
         notification.icon.to_owned().into(),    // icon
         notification.summary.to_owned().into(), // summary (title)
         notification.body.to_owned().into(),    // body
-        pack_actions(notification),             // actions
-        pack_hints(notification)?,              // hints
-        timeout.into(),                         // timeout
+        pack_actions(notification)?,            // actions
     ]);
+    message.append_items(std::slice::from_ref(hints)); // hints
+    message.append_items(&[timeout.into()]); // timeout
+    Ok(message)
+}
 
-    let reply = connection.send_with_reply_and_block(message, 2000)?;
+/// Sends `notification` on the bus `selection` picks without a handle,
+/// returns its id along with the server that shows it.
+pub(crate) fn send_notification(
+    notification: &Notification,
+    selection: &BusSelection,
+) -> Result<(u32, Option<String>)> {
+    let requested = notification.id.unwrap_or(0);
+    let (id, server) = with_connection(selection, |connection| {
+        let bus = notification.bus.clone();
+        send_notification_via_connection_at_bus(notification, requested, connection, bus)
+    })?;
+    xdg::check_replaced(notification, requested, id)?;
+    Ok((id, server))
+}
 
-    match reply.get_items().first() {
-        Some(MessageItem::UInt32(ref id)) => Ok(*id),
-        _ => Ok(0),
-    }
+/// Sends `notification` and tells the server not to reply.
+pub fn send_notification_nowait(notification: &Notification) -> Result<()> {
+    with_connection(&notification.bus_selection, |connection| {
+        let id = notification.id.unwrap_or(0);
+        let _span = trace::notify_span(notification, id).entered();
+        let mut message = notify_message(notification, id, notification.bus.clone())?;
+        message.set_no_reply(true);
+        // flushed right away, dropping the connection afterwards loses nothing
+        connection
+            .send(message)
+            .map_err(|()| Error::from("failed to send the notification"))?;
+        Ok(())
+    })
 }
 
 pub fn connect_and_send_notification(
@@ -160,66 +489,203 @@ pub fn connect_and_send_notification_at_bus(
     notification: &Notification,
     bus: NotificationBus,
 ) -> Result<DbusNotificationHandle> {
-    let connection = Connection::get_private(BusType::Session)?;
     let inner_id = notification.id.unwrap_or(0);
-    let id = send_notification_via_connection_at_bus(notification, inner_id, &connection, bus)?;
+    let (address, connection, (id, server)) =
+        using_connection(&notification.bus_selection, |connection| {
+            send_notification_via_connection_at_bus(notification, inner_id, connection, bus.clone())
+        })?;
 
-    Ok(DbusNotificationHandle::new(
-        id,
-        connection,
-        notification.clone(),
-    ))
+    // the handle follows, updates and closes the notification at the same bus
+    let notification = Notification {
+        bus,
+        ..notification.clone()
+    };
+    let replaced = xdg::check_replaced(&notification, inner_id, id)?;
+    let mut handle = DbusNotificationHandle::new(id, connection, notification);
+    handle.server = server;
+    handle.replaced = replaced;
+    handle.address = address;
+    Ok(handle)
+}
+
+/// Closes notification `id` at `bus`.
+pub fn close_notification(id: u32, bus: NotificationBus) -> Result<()> {
+    with_connection(&BusSelection::Session, |connection| {
+        close_notification_on(connection, bus.clone(), id)
+    })
+}
+
+pub(crate) fn close_notification_on(
+    connection: &Connection,
+    bus: NotificationBus,
+    id: u32,
+) -> Result<()> {
+    let mut message = build_message("CloseNotification", bus)?;
+    message.append_items(&[id.into()]);
+    call_within(connection, message, DEFAULT_REPLY_TIMEOUT)?;
+    Ok(())
+}
+
+/// How long calls wait for a reply, unless the notification sets its `send_timeout()`.
+pub(crate) const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_millis(2000);
+
+fn timeout_ms(timeout: Duration) -> i32 {
+    i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX)
 }
 
-fn build_message(method_name: &str, bus: NotificationBus) -> Message {
+/// Sends `message` and waits up to `timeout` for the reply.
+///
+/// libdbus reports a call that ran out of time as `NoReply`, as it does when the server left without answering,
+/// only the first is an [`ErrorKind::ReplyTimeout`].
+fn call_within(connection: &Connection, message: Message, timeout: Duration) -> Result<Message> {
+    let (started, timeout_ms) = (Instant::now(), timeout_ms(timeout));
+    connection
+        .send_with_reply_and_block(message, timeout_ms)
+        .map_err(|error| {
+            if error.name() == Some("org.freedesktop.DBus.Error.NoReply")
+                && started.elapsed() >= Duration::from_millis(timeout_ms as u64)
+            {
+                ErrorKind::ReplyTimeout(timeout).into()
+            } else {
+                error.into()
+            }
+        })
+}
+
+/// The call of `method_name` on the server at `bus`, fails with [`ErrorKind::InvalidBusName`] if libdbus rejects the name.
+pub(crate) fn build_message(method_name: &str, bus: NotificationBus) -> Result<Message> {
+    let name = bus.into_name();
+    if let Err(error) = dbus::strings::BusName::new(&*name) {
+        return Err(InvalidBusName::new(&name, error).into());
+    }
     Message::new_method_call(
-        bus.into_name(),
+        name,
         NOTIFICATION_OBJECTPATH,
         NOTIFICATION_INTERFACE,
         method_name,
     )
-    .unwrap_or_else(|_| panic!("Error building message call {:?}.", method_name))
+    .map_err(|error| ErrorKind::Msg(format!("can't call {}: {}", method_name, error)).into())
+}
+
+/// Calls `member` of `interface` on the object of the notification server and returns the reply.
+pub fn call_raw(interface: &str, member: &str, args: &[MessageItem]) -> Result<Vec<MessageItem>> {
+    with_connection(&BusSelection::Session, |connection| {
+        let mut message = Message::new_method_call(
+            xdg::NOTIFICATION_DEFAULT_BUS,
+            NOTIFICATION_OBJECTPATH,
+            interface,
+            member,
+        )
+        .map_err(ErrorKind::Msg)?;
+        message.append_items(args);
+        let reply = call_within(connection, message, DEFAULT_REPLY_TIMEOUT)?;
+        Ok(reply.get_items())
+    })
 }
 
 pub fn pack_hints(notification: &Notification) -> Result<MessageItem> {
-    if !notification.hints.is_empty() || !notification.hints_unique.is_empty() {
-        let hints = notification
-            .get_hints()
+    let mut effective = notification.effective_hints().peekable();
+    if effective.peek().is_some() {
+        let spec_version = xdg::cached_spec_version();
+        let hints = effective
             .cloned()
-            .map(HintMessage::wrap_hint)
+            .flat_map(|hint| {
+                let compat_keys = notification
+                    .compat_image_hints
+                    .then(|| compat_image_keys(&hint, spec_version.as_deref()))
+                    .flatten();
+                let (key, value) = HintMessage::wrap_hint(hint);
+                match compat_keys {
+                    Some(keys) => keys
+                        .iter()
+                        .map(|key| (MessageItem::Str((*key).to_owned()), value.clone()))
+                        .collect(),
+                    None => vec![(key, value)],
+                }
+            })
             .collect::<Vec<(MessageItem, MessageItem)>>();
-
-        if let Ok(array) = MessageItem::new_dict(hints) {
-            return Ok(array);
-        }
+        return hint_dict(hints);
     }
 
-    Ok(MessageItem::Array(
-        MessageItemArray::new(vec![], "a{sv}".into()).unwrap(),
-    ))
+    empty_array("a{sv}")
 }
 
-pub fn pack_actions(notification: &Notification) -> MessageItem {
-    if !notification.actions.is_empty() {
-        let mut actions = vec![];
-        for action in &notification.actions {
-            actions.push(action.to_owned().into());
-        }
-        if let Ok(array) = MessageItem::new_array(actions) {
-            return array;
+/// An empty array of `signature`, which must be an array signature.
+fn empty_array(signature: &'static str) -> Result<MessageItem> {
+    MessageItemArray::new(vec![], signature.into())
+        .map(MessageItem::Array)
+        .map_err(|error| ErrorKind::Encoding(format!("an empty {}: {:?}", signature, error)).into())
+}
+
+/// The dict of `hints`, which must not be empty.
+///
+/// Fails with [`ErrorKind::HintEncoding`] naming the first entry that doesn't fit the signature of the first one.
+pub(crate) fn hint_dict(hints: Vec<(MessageItem, MessageItem)>) -> Result<MessageItem> {
+    let entry_signature = |(key, value): &(MessageItem, MessageItem)| {
+        format!("{{{}{}}}", key.signature(), value.signature())
+    };
+    if let Some(expected) = hints.first().map(entry_signature) {
+        if let Some(entry) = hints
+            .iter()
+            .find(|entry| entry_signature(entry) != expected)
+        {
+            let key = match entry.0 {
+                MessageItem::Str(ref key) => key.clone(),
+                ref key => format!("{:?}", key),
+            };
+            let signature = entry_signature(entry);
+            return Err(ErrorKind::HintEncoding {
+                key,
+                signature,
+                expected,
+            }
+            .into());
         }
     }
+    MessageItem::new_dict(hints)
+        .map_err(|error| ErrorKind::Encoding(format!("the hints: {:?}", error)).into())
+}
 
-    MessageItem::Array(MessageItemArray::new(vec![], "as".into()).unwrap())
+pub fn pack_actions(notification: &Notification) -> Result<MessageItem> {
+    if notification.actions.is_empty() {
+        return empty_array("as");
+    }
+    let actions = notification
+        .actions
+        .iter()
+        .map(|action| MessageItem::Str(action.to_owned()))
+        .collect();
+    MessageItem::new_array(actions)
+        .map_err(|error| ErrorKind::Encoding(format!("the actions: {:?}", error)).into())
 }
 
-pub fn get_capabilities() -> Result<Vec<String>> {
-    let mut capabilities = vec![];
+pub fn get_capabilities_at_bus(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<Vec<String>> {
+    with_connection(selection, |connection| {
+        get_capabilities_within(connection, bus.clone(), DEFAULT_REPLY_TIMEOUT)
+    })
+}
 
-    let message = build_message("GetCapabilities", Default::default());
-    let connection = Connection::get_private(BusType::Session)?;
-    let reply = connection.send_with_reply_and_block(message, 2000)?;
+pub fn get_capabilities_on(connection: &Connection) -> Result<Vec<String>> {
+    get_capabilities_within(connection, Default::default(), DEFAULT_REPLY_TIMEOUT)
+}
 
+/// Fails with [`ErrorKind::ReplyTimeout`] unless the server at `bus` answers within `timeout`.
+pub fn get_capabilities_within(
+    connection: &Connection,
+    bus: NotificationBus,
+    timeout: Duration,
+) -> Result<Vec<String>> {
+    let message = build_message("GetCapabilities", bus)?;
+    let reply = call_within(connection, message, timeout)?;
+    Ok(capabilities_reply(&reply))
+}
+
+/// The capabilities in the reply to `GetCapabilities`.
+pub(crate) fn capabilities_reply(reply: &Message) -> Vec<String> {
+    let mut capabilities = vec![];
     if let Some(MessageItem::Array(items)) = reply.get_items().first() {
         for item in items.iter() {
             if let MessageItem::Str(ref cap) = *item {
@@ -227,8 +693,7 @@ pub fn get_capabilities() -> Result<Vec<String>> {
             }
         }
     }
-
-    Ok(capabilities)
+    capabilities
 }
 
 fn unwrap_message_string(item: Option<&MessageItem>) -> String {
@@ -238,91 +703,567 @@ fn unwrap_message_string(item: Option<&MessageItem>) -> String {
     }
 }
 
-#[allow(clippy::get_first)]
-pub fn get_server_information() -> Result<ServerInformation> {
-    let message = build_message("GetServerInformation", Default::default());
-    let connection = Connection::get_private(BusType::Session)?;
-    let reply = connection.send_with_reply_and_block(message, 2000)?;
+pub fn get_server_information_at_bus(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<ServerInformation> {
+    with_connection(selection, |connection| {
+        get_server_information_within(connection, bus.clone(), DEFAULT_REPLY_TIMEOUT)
+    })
+}
 
+pub fn get_server_information_on(connection: &Connection) -> Result<ServerInformation> {
+    get_server_information_within(connection, Default::default(), DEFAULT_REPLY_TIMEOUT)
+}
+
+/// Fails with [`ErrorKind::ReplyTimeout`] unless the server at `bus` answers within `timeout`.
+pub fn get_server_information_within(
+    connection: &Connection,
+    bus: NotificationBus,
+    timeout: Duration,
+) -> Result<ServerInformation> {
+    let message = build_message("GetServerInformation", bus)?;
+    let reply = call_within(connection, message, timeout)?;
+    Ok(server_information_reply(&reply))
+}
+
+/// The information in the reply to `GetServerInformation`, its spec version is cached.
+#[allow(clippy::get_first)]
+pub(crate) fn server_information_reply(reply: &Message) -> ServerInformation {
     let items = reply.get_items();
 
-    Ok(ServerInformation {
+    let info = ServerInformation {
         name: unwrap_message_string(items.get(0)),
         vendor: unwrap_message_string(items.get(1)),
         version: unwrap_message_string(items.get(2)),
         spec_version: unwrap_message_string(items.get(3)),
-    })
+    };
+    xdg::cache_spec_version(&info);
+    info
 }
 
 /// Listens for the `ActionInvoked(UInt32, String)` Signal.
 ///
 /// No need to use this, check out `Notification::show_and_wait_for_action(FnOnce(action:&str))`
 pub fn handle_action(id: u32, func: impl ActionResponseHandler) {
-    let connection = Connection::get_private(BusType::Session).unwrap();
-    wait_for_action_signal(&connection, id, func);
+    handle_action_until(id, &BusSelection::Session, &Stop::default(), func).unwrap();
+}
+
+/// Like [`handle_action`], but on `selection` and returns without calling `func` once `stop` is set.
+///
+/// Fails if the bus can't be reached or the match rules can't be added.
+pub(crate) fn handle_action_until(
+    id: u32,
+    selection: &BusSelection,
+    stop: &Stop,
+    func: impl ActionResponseHandler,
+) -> Result<()> {
+    let connection = connect(selection)?;
+    let tokens = ActivationToken::default();
+    // without a handle the server is whoever owns the bus right now
+    let server = name_owner(&connection, xdg::NOTIFICATION_DEFAULT_BUS);
+    let mut events = DbusEvents::subscribe(
+        &connection,
+        xdg::NOTIFICATION_DEFAULT_BUS,
+        server.as_deref(),
+        id,
+        &tokens,
+    )?;
+    // the blocking read returns once `stop` pings the connection
+    stop.wake_through(connection.unique_name(), selection.clone());
+    while let Some(event) = events.next_before(None, Some(stop)) {
+        if let Some(response) = event.as_response() {
+            func.call(&response);
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The unique name of the current owner of `bus`, `None` if nobody owns it.
+fn name_owner(connection: &Connection, bus: &str) -> Option<String> {
+    let message = Message::new_method_call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "GetNameOwner",
+    )
+    .ok()?
+    .append1(bus);
+    let reply = connection.send_with_reply_and_block(message, 2000).ok()?;
+    reply.get1()
 }
 
 // Listens for the `ActionInvoked(UInt32, String)` signal.
-fn wait_for_action_signal(connection: &Connection, id: u32, handler: impl ActionResponseHandler) {
-    connection
-        .add_match(&format!(
-            "interface='{}',member='ActionInvoked'",
-            NOTIFICATION_INTERFACE
-        ))
-        .unwrap();
-    connection
-        .add_match(&format!(
-            "interface='{}',member='NotificationClosed'",
-            NOTIFICATION_INTERFACE
-        ))
-        .unwrap();
-
-    for item in connection.iter(1000) {
-        if let ConnectionItem::Signal(message) = item {
-            let items = message.get_items();
-
-            let (path, interface, member) = (
-                message.path().map_or_else(String::new, |p| {
-                    p.into_cstring().to_string_lossy().into_owned()
-                }),
-                message.interface().map_or_else(String::new, |p| {
-                    p.into_cstring().to_string_lossy().into_owned()
-                }),
-                message.member().map_or_else(String::new, |p| {
-                    p.into_cstring().to_string_lossy().into_owned()
-                }),
-            );
-            match (path.as_str(), interface.as_str(), member.as_str()) {
-                // match (protocol.unwrap(), iface.unwrap(), member.unwrap()) {
-                // Action Invoked
-                (path, interface, "ActionInvoked")
-                    if path == NOTIFICATION_OBJECTPATH && interface == NOTIFICATION_INTERFACE =>
-                {
-                    if let (&MessageItem::UInt32(nid), MessageItem::Str(ref action)) =
-                        (&items[0], &items[1])
-                    {
-                        if nid == id {
-                            handler.call(&ActionResponse::Custom(action));
-                            break;
-                        }
-                    }
+fn wait_for_action_signal(
+    connection: &Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+    tokens: &ActivationToken,
+    handler: impl ActionResponseHandler,
+) {
+    wait_for_action_signal_until(connection, bus, server, id, tokens, None, handler);
+}
+
+// Listens for the `ActionInvoked(UInt32, String)` signal until `deadline`,
+// returns `false` if the deadline passed without the handler being called.
+fn wait_for_action_signal_until(
+    connection: &Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+    tokens: &ActivationToken,
+    deadline: Option<Instant>,
+    handler: impl ActionResponseHandler,
+) -> bool {
+    let mut events = match DbusEvents::subscribe(connection, bus, server, id, tokens) {
+        Ok(events) => events,
+        Err(_) => return false,
+    };
+    while let Some(event) = events.next_until(deadline) {
+        if let Some(response) = event.as_response() {
+            handler.call(&response);
+            return true;
+        }
+    }
+    false
+}
+
+/// Waits for `NotificationClosed` of notification `id`, `None` if the server at `bus` went away first.
+///
+/// Only that signal is subscribed to, invoked actions don't even reach the connection.
+fn wait_for_close_signal(
+    connection: &Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+) -> Result<Option<CloseReason>> {
+    let rules = [closed_signal_rule(server), owner_changes_rule(bus)];
+    for rule in &rules {
+        connection.add_match(rule)?;
+    }
+    let reason = connection
+        .incoming(BLOCK)
+        .find_map(|message| match parse_event(&message, bus, server, id)? {
+            Event::Closed(reason) => Some(Some(reason)),
+            Event::ServerGone => Some(None),
+            _ => None,
+        })
+        .flatten();
+    for rule in &rules {
+        let _ = connection.remove_match(rule);
+    }
+    Ok(reason)
+}
+
+/// Calls `handler` with the events of all notifications in a new thread until `stop` is set.
+///
+/// Returns once the thread has subscribed.
+pub(crate) fn spawn_subscription(
+    stop: Stop,
+    mut handler: impl FnMut(u32, Event) + Send + 'static,
+) -> Result<thread::JoinHandle<()>> {
+    spawn_listening(
+        stop,
+        vec![notification_signals_rule(None)],
+        move |message| {
+            if let Some((id, event)) = parse_signal(message) {
+                handler(id, event);
+            }
+        },
+    )
+}
+
+/// Calls `handler` with every message of the session bus matching `rules` in a new thread until `stop` is set.
+///
+/// Returns once the thread has subscribed, the rules are removed again when it ends.
+pub(crate) fn spawn_listening(
+    stop: Stop,
+    rules: Vec<String>,
+    mut handler: impl FnMut(&Message) + Send + 'static,
+) -> Result<thread::JoinHandle<()>> {
+    let (subscribed_tx, subscribed_rx) = mpsc::channel::<Result<()>>();
+    let thread = thread::spawn(move || {
+        let connection = match session_connection() {
+            Ok(connection) => connection,
+            Err(error) => return subscribed_tx.send(Err(error)).unwrap_or(()),
+        };
+        for rule in &rules {
+            if let Err(error) = connection.add_match(rule) {
+                return subscribed_tx.send(Err(error.into())).unwrap_or(());
+            }
+        }
+        stop.wake_through(connection.unique_name(), BusSelection::Session);
+        let _ = subscribed_tx.send(Ok(()));
+        let mut incoming = connection.incoming(BLOCK);
+        while !stop.is_set() {
+            if let Some(message) = incoming.next() {
+                handler(&message);
+            }
+        }
+        for rule in &rules {
+            let _ = connection.remove_match(rule);
+        }
+    });
+    subscribed_rx
+        .recv()
+        .unwrap_or_else(|_| Err("subscription thread panicked".into()))?;
+    Ok(thread)
+}
+
+/// A new connection to the bus `selection` picks.
+fn connect(selection: &BusSelection) -> Result<Connection> {
+    match selection {
+        BusSelection::Session => session_connection(),
+        BusSelection::System => Ok(Connection::get_private(BusType::System)?),
+        BusSelection::Address(address) => open_session(address),
+    }
+}
+
+/// A new connection to the session bus at the address [`session_bus::address`](xdg::session_bus::address) finds.
+fn session_connection() -> Result<Connection> {
+    open_session(&xdg::session_bus::address()?)
+}
+
+fn open_session(address: &str) -> Result<Connection> {
+    let connection = Connection::open_private(address)?;
+    connection.register()?;
+    Ok(connection)
+}
+
+thread_local! {
+    /// A connection to a session bus or an address nothing uses right now, with the address it was opened at.
+    ///
+    /// Connections can't be sent to other threads, so every thread keeps its own.
+    static IDLE: RefCell<Option<(String, Connection)>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with a connection to the bus `selection` picks, like [`using_connection`].
+///
+/// A connection to any bus but the system bus is kept for the next call of this thread.
+fn with_connection<T>(
+    selection: &BusSelection,
+    f: impl FnMut(&Connection) -> Result<T>,
+) -> Result<T> {
+    let (address, connection, value) = using_connection(selection, f)?;
+    if let Some(address) = address {
+        recycle(address, connection);
+    }
+    Ok(value)
+}
+
+/// Calls `f` with the idle connection of this thread if it was opened at the address `selection` picks, or with a new one.
+///
+/// If the idle connection turns out to be broken, `f` is called again with a new one.
+/// Returns the bus address along with the connection, `None` for the system bus.
+fn using_connection<T>(
+    selection: &BusSelection,
+    mut f: impl FnMut(&Connection) -> Result<T>,
+) -> Result<(Option<String>, Connection, T)> {
+    let address = match selection {
+        BusSelection::Session => xdg::session_bus::address()?,
+        BusSelection::System => {
+            let connection = Connection::get_private(BusType::System)?;
+            let value = f(&connection)?;
+            return Ok((None, connection, value));
+        }
+        BusSelection::Address(address) => address.clone(),
+    };
+    let idle = IDLE.with(|idle| idle.borrow_mut().take());
+    if let Some((idle_address, connection)) = idle {
+        if idle_address == address && connection.is_connected() {
+            match f(&connection) {
+                Ok(value) => return Ok((Some(address), connection, value)),
+                // the bus went away since, a new connection may reach it again
+                Err(_) if !connection.is_connected() => {}
+                Err(error) => {
+                    recycle(address, connection);
+                    return Err(error);
                 }
+            }
+        }
+    }
+    let connection = open_session(&address)?;
+    match f(&connection) {
+        Ok(value) => Ok((Some(address), connection, value)),
+        Err(error) => {
+            recycle(address, connection);
+            Err(error)
+        }
+    }
+}
+
+/// Keeps `connection` to the bus at `address` for the next call of this thread.
+fn recycle(address: String, connection: Connection) {
+    if !connection.is_connected() {
+        return;
+    }
+    // whatever arrived in the meantime, e.g. signals of rules already removed
+    connection.incoming(0).for_each(drop);
+    IDLE.with(|idle| *idle.borrow_mut() = Some((address, connection)));
+}
+
+/// Wakes the listener thread blocked on the connection with the unique name `listener`.
+///
+/// A `Peer.Ping` without reply is enough, libdbus answers it internally and returns from the blocking read.
+pub(crate) fn wake_listener(listener: &str, selection: &BusSelection) {
+    let connection = connect(selection);
+    let ping = Message::new_method_call(listener, "/", "org.freedesktop.DBus.Peer", "Ping");
+    if let (Ok(connection), Ok(mut ping)) = (connection, ping) {
+        ping.set_no_reply(true);
+        let _ = connection.send(ping);
+    }
+}
+
+/// Timeout for blocking reads without deadline, libdbus gets it as `-1` and waits as long as it takes.
+const BLOCK: u32 = u32::MAX;
+
+/// Waits for the next [`Event`] of notification `id`.
+pub(crate) fn wait_for_event(
+    connection: &Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+    tokens: &ActivationToken,
+) -> Result<Event> {
+    match DbusEvents::subscribe(connection, bus, server, id, tokens)?.next() {
+        Some(event) => Ok(event),
+        None => Err(ErrorKind::Msg(format!(
+            "connection closed before notification {} was acted on",
+            id
+        ))
+        .into()),
+    }
+}
+
+/// The [`Event`]s of notification `id`, ends after it was closed or the server at `bus` went away.
+///
+/// The match rules are removed from the bus again on drop.
+#[derive(Debug)]
+pub struct DbusEvents<'a> {
+    connection: &'a Connection,
+    rules: [String; 2],
+    bus: String,
+    server: Option<String>,
+    id: u32,
+    tokens: ActivationToken,
+    pairing: TokenPairing,
+    ended: bool,
+}
+
+impl<'a> DbusEvents<'a> {
+    /// With a `server`, only its signals are let through, those of other servers on the bus are ignored.
+    /// The activation token of every event is recorded in `tokens`.
+    pub(crate) fn subscribe(
+        connection: &'a Connection,
+        bus: &str,
+        server: Option<&str>,
+        id: u32,
+        tokens: &ActivationToken,
+    ) -> Result<Self> {
+        let rules = signal_rules(bus, server);
+        for rule in &rules {
+            connection.add_match(rule)?;
+        }
+        Ok(DbusEvents {
+            connection,
+            rules,
+            bus: bus.to_owned(),
+            server: server.map(ToOwned::to_owned),
+            id,
+            tokens: tokens.clone(),
+            pairing: TokenPairing::default(),
+            ended: false,
+        })
+    }
+
+    /// Blocks until the next event or `deadline`.
+    pub fn next_until(&mut self, deadline: Option<Instant>) -> Option<Event> {
+        self.next_before(deadline, None)
+    }
 
-                // Notification Closed
-                (path, interface, "NotificationClosed")
-                    if path == NOTIFICATION_OBJECTPATH && interface == NOTIFICATION_INTERFACE =>
-                {
-                    if let (&MessageItem::UInt32(nid), &MessageItem::UInt32(reason)) =
-                        (&items[0], &items[1])
-                    {
-                        if nid == id {
-                            handler.call(&ActionResponse::Closed(reason.into()));
-                            break;
-                        }
-                    }
+    /// Like `next_until()`, but also returns `None` once `stop` is set.
+    pub(crate) fn next_before(
+        &mut self,
+        deadline: Option<Instant>,
+        stop: Option<&Stop>,
+    ) -> Option<Event> {
+        // idle waits block on the connection instead of waking up periodically
+        let mut incoming = self.connection.incoming(BLOCK);
+        while !self.ended {
+            if let Some((event, token)) = self.pairing.next() {
+                self.ended = event.is_final();
+                self.tokens.record(&event, token);
+                return Some(event);
+            }
+            if stop.map_or(false, Stop::is_set) {
+                return None;
+            }
+            if deadline.map_or(false, |deadline| deadline <= Instant::now()) {
+                return None;
+            }
+            // an action waits for its token a little longer
+            incoming.timeout_ms = match earliest(deadline, self.pairing.deadline()) {
+                Some(wake) => {
+                    let remaining = wake.saturating_duration_since(Instant::now());
+                    // round up, otherwise this would spin through the last millisecond
+                    let remaining_ms = (remaining.as_micros() + 999) / 1000;
+                    Some(u32::try_from(remaining_ms.min(i32::MAX as u128)).unwrap())
                 }
-                (..) => (),
+                None => Some(BLOCK),
+            };
+            let message = match incoming.next() {
+                Some(message) => message,
+                None => continue,
+            };
+            let server = self.server.as_deref();
+            if let Some(token) = parse_activation_token(&message, server, self.id) {
+                self.pairing.token(token);
+            } else if let Some(event) = parse_event(&message, &self.bus, server, self.id) {
+                self.pairing.event(event);
             }
         }
+        None
     }
 }
+
+fn earliest(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Iterator for DbusEvents<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.next_until(None)
+    }
+}
+
+impl Drop for DbusEvents<'_> {
+    fn drop(&mut self) {
+        for rule in &self.rules {
+            let _ = self.connection.remove_match(rule);
+        }
+    }
+}
+
+/// Matches all signals of the notification interface, only those sent by `server` if there is one.
+fn notification_signals_rule(server: Option<&str>) -> String {
+    let mut rule = MatchRule::new();
+    rule.msg_type = Some(MessageType::Signal);
+    rule.sender = server.map(Into::into);
+    rule.path = Some(NOTIFICATION_OBJECTPATH.into());
+    rule.interface = Some(NOTIFICATION_INTERFACE.into());
+    rule.match_str()
+}
+
+/// Matches only the `NotificationClosed` signals of `server`, or of anyone without one.
+fn closed_signal_rule(server: Option<&str>) -> String {
+    let mut rule = MatchRule::new_signal(NOTIFICATION_INTERFACE, "NotificationClosed");
+    rule.sender = server.map(Into::into);
+    rule.path = Some(NOTIFICATION_OBJECTPATH.into());
+    rule.match_str()
+}
+
+/// Matches owner changes of the well-known name `bus`.
+fn owner_changes_rule(bus: &str) -> String {
+    let mut rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged");
+    rule.sender = Some("org.freedesktop.DBus".into());
+    // `MatchRule` has no arguments, the name is appended by hand
+    format!("{},arg0='{}'", rule.match_str(), bus)
+}
+
+/// Matches the signals of `server`, or of anyone without one, and owner changes of `bus`.
+pub(crate) fn signal_rules(bus: &str, server: Option<&str>) -> [String; 2] {
+    [notification_signals_rule(server), owner_changes_rule(bus)]
+}
+
+/// `message` was sent by `server`, anything goes without one.
+///
+/// Other rules on the same connection may let signals of other servers through, they are filtered here.
+fn sent_by(message: &Message, server: Option<&str>) -> bool {
+    server.map_or(true, |server| message.sender().as_deref() == Some(server))
+}
+
+/// The token of an `ActivationToken` signal for notification `id`, it precedes `ActionInvoked`.
+fn parse_activation_token(message: &Message, server: Option<&str>, id: u32) -> Option<String> {
+    if message.msg_type() != MessageType::Signal
+        || !sent_by(message, server)
+        || message.path().as_deref() != Some(NOTIFICATION_OBJECTPATH)
+        || message.interface().as_deref() != Some(NOTIFICATION_INTERFACE)
+        || message.member().as_deref() != Some("ActivationToken")
+    {
+        return None;
+    }
+    match message.get_items().as_slice() {
+        [MessageItem::UInt32(nid), MessageItem::Str(token), ..] if *nid == id => {
+            Some(token.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Turns a signal into an [`Event`] if `server` sent it for notification `id` or it tells that `bus` lost its owner.
+pub(crate) fn parse_event(
+    message: &Message,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+) -> Option<Event> {
+    if message.msg_type() != MessageType::Signal {
+        return None;
+    }
+    if message.interface().as_deref() == Some("org.freedesktop.DBus") {
+        return server_gone(message, bus).then_some(Event::ServerGone);
+    }
+    if !sent_by(message, server) {
+        return None;
+    }
+    parse_signal(message)
+        .filter(|&(nid, _)| nid == id)
+        .map(|(_, event)| event)
+}
+
+/// `message` tells that `bus` lost its owner.
+pub(crate) fn server_gone(message: &Message, bus: &str) -> bool {
+    if message.msg_type() != MessageType::Signal
+        || message.interface().as_deref() != Some("org.freedesktop.DBus")
+    {
+        return false;
+    }
+    // `NameOwnerChanged(name, old_owner, new_owner)`, a successor may take over right away
+    match (message.member().as_deref(), message.get_items().as_slice()) {
+        (Some("NameOwnerChanged"), [MessageItem::Str(name), MessageItem::Str(old_owner), _]) => {
+            name == bus && !old_owner.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Turns a signal of the notification interface into the id of its notification and an [`Event`].
+pub(crate) fn parse_signal(message: &Message) -> Option<(u32, Event)> {
+    if message.msg_type() != MessageType::Signal
+        || message.path().as_deref() != Some(NOTIFICATION_OBJECTPATH)
+        || message.interface().as_deref() != Some(NOTIFICATION_INTERFACE)
+    {
+        return None;
+    }
+    let (id, event) = match (&*message.member()?, message.get_items().as_slice()) {
+        ("ActionInvoked", [MessageItem::UInt32(id), MessageItem::Str(action), ..]) => {
+            (*id, Event::ActionInvoked(action.clone()))
+        }
+        ("NotificationReplied", [MessageItem::UInt32(id), MessageItem::Str(text), ..]) => {
+            (*id, Event::Replied(text.clone()))
+        }
+        ("NotificationClosed", [MessageItem::UInt32(id), MessageItem::UInt32(reason), ..]) => {
+            (*id, Event::Closed((*reason).into()))
+        }
+        _ => return None,
+    };
+    trace::received(id, &event);
+    Some((id, event))
+}
@@ -0,0 +1,300 @@
+//! dbus-rs without blocking, on the tokio runtime of the caller.
+//!
+//! For applications on tokio that can't depend on zbus, the functions mirror the async ones of zbus.
+//! Messages are built and signals are read by the same code as on the blocking dbus-rs backend.
+//!
+//! ```no_run
+//! # async fn _doc() -> notify_rust::error::Result<()> {
+//! use notify_rust::{dbus_tokio, Event, Notification};
+//!
+//! let handle = dbus_tokio::show_async(
+//!     Notification::new()
+//!         .summary("Build finished")
+//!         .action("open", "Open Log"),
+//! )
+//! .await?;
+//! if let Event::ActionInvoked(action) = handle.wait_for_action_async().await? {
+//!     println!("{}", action);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Everything here must be awaited inside a tokio runtime, every connection is driven by a task of its own.
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use dbus::{
+    channel::{BusType, Channel, MatchingReceiver, Sender, Token},
+    message::MatchRule,
+    nonblock::{NonblockReply, SyncConnection},
+    Message,
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use super::{dbus_rs, trace, BusSelection, CloseReason, Event, NotificationBus, ServerInformation};
+use crate::{error::*, notification::Notification, xdg};
+
+/// A connection and the task that drives it, the task is stopped on drop.
+struct Driven {
+    connection: Arc<SyncConnection>,
+    driver: JoinHandle<()>,
+}
+
+impl Driven {
+    /// Connects to the bus `selection` picks, the connection is driven on the current tokio runtime.
+    fn connect(selection: &BusSelection) -> Result<Driven> {
+        let channel = match selection {
+            BusSelection::Session => open(&xdg::session_bus::address()?)?,
+            BusSelection::System => Channel::get_private(BusType::System)?,
+            BusSelection::Address(address) => open(address)?,
+        };
+        let (resource, connection) = ::dbus_tokio::connection::from_channel(channel)?;
+        let driver = tokio::spawn(async move {
+            let error = resource.await;
+            log::warn!("dbus-tokio connection is gone ({})", error);
+        });
+        Ok(Driven { connection, driver })
+    }
+
+    /// Sends `message` and waits for its reply, fails with [`ErrorKind::ReplyTimeout`] after `timeout`.
+    async fn call(&self, message: Message, timeout: Duration) -> Result<Message> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let token = self
+            .connection
+            .send_with_reply(
+                message,
+                SyncConnection::make_f(move |reply, _| {
+                    let _ = reply_tx.send(reply);
+                }),
+            )
+            .map_err(|()| Error::from("failed to send the message"))?;
+        match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(mut reply)) => {
+                reply.as_result()?;
+                Ok(reply)
+            }
+            Ok(Err(_)) => Err("connection closed before the reply arrived".into()),
+            Err(_) => {
+                self.connection.cancel_reply(token);
+                Err(ErrorKind::ReplyTimeout(timeout).into())
+            }
+        }
+    }
+}
+
+impl Drop for Driven {
+    fn drop(&mut self) {
+        self.driver.abort();
+    }
+}
+
+fn open(address: &str) -> Result<Channel> {
+    let mut channel = Channel::open_private(address)?;
+    channel.register()?;
+    Ok(channel)
+}
+
+/// The signals for notification `id` on a connection, the match rules are removed on drop.
+///
+/// Like [`DbusEvents`](super::dbus_rs::DbusEvents) with a `server`, only its signals are let through.
+struct Signals<'a> {
+    connection: &'a SyncConnection,
+    rules: [String; 2],
+    filter: Token,
+    messages: mpsc::UnboundedReceiver<Message>,
+    bus: String,
+    server: Option<String>,
+    id: u32,
+}
+
+impl<'a> Signals<'a> {
+    async fn subscribe(
+        connection: &'a SyncConnection,
+        bus: &str,
+        server: Option<&str>,
+        id: u32,
+    ) -> Result<Signals<'a>> {
+        let (sender, messages) = mpsc::unbounded_channel();
+        // everything that is no reply, `parse_event` picks the signals like on the blocking backend
+        let filter = connection.start_receive(
+            MatchRule::new(),
+            Box::new(move |message, _| sender.send(message).is_ok()),
+        );
+        let signals = Signals {
+            connection,
+            rules: dbus_rs::signal_rules(bus, server),
+            filter,
+            messages,
+            bus: bus.to_owned(),
+            server: server.map(ToOwned::to_owned),
+            id,
+        };
+        for rule in &signals.rules {
+            connection.add_match_no_cb(rule).await?;
+        }
+        Ok(signals)
+    }
+
+    async fn next(&mut self) -> Option<Event> {
+        while let Some(message) = self.messages.recv().await {
+            let server = self.server.as_deref();
+            if let Some(event) = dbus_rs::parse_event(&message, &self.bus, server, self.id) {
+                return Some(event);
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Signals<'_> {
+    fn drop(&mut self) {
+        self.connection.stop_receive(self.filter);
+        for rule in &self.rules {
+            // not waiting for the reply, so a cancelled wait cleans up as well
+            let remove = Message::new_method_call(
+                "org.freedesktop.DBus",
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "RemoveMatch",
+            );
+            if let Ok(remove) = remove {
+                let mut remove = remove.append1(rule);
+                remove.set_no_reply(true);
+                let _ = self.connection.send(remove);
+            }
+        }
+    }
+}
+
+/// A notification shown by [`show_async()`], with the connection it was sent through.
+pub struct AsyncHandle {
+    id: u32,
+    driven: Driven,
+    bus: NotificationBus,
+    server: Option<String>,
+}
+
+impl fmt::Debug for AsyncHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncHandle")
+            .field("id", &self.id)
+            .field("bus", &self.bus)
+            .field("server", &self.server)
+            .finish()
+    }
+}
+
+impl AsyncHandle {
+    /// The id of the notification.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Resolves to the next [`Event`] of this notification, like `NotificationHandle::wait_async()` with zbus.
+    ///
+    /// Dropping the future cancels the wait and unsubscribes from the signals again.
+    pub async fn wait_async(&self) -> Result<Event> {
+        let bus = self.bus.clone().into_name();
+        let mut signals = Signals::subscribe(
+            &self.driven.connection,
+            &bus,
+            self.server.as_deref(),
+            self.id,
+        )
+        .await?;
+        signals.next().await.ok_or_else(|| {
+            ErrorKind::Msg(format!(
+                "connection closed before notification {} was acted on",
+                self.id
+            ))
+            .into()
+        })
+    }
+
+    /// Waits for the user to act on the notification, an invoked action, a reply or its closing.
+    pub async fn wait_for_action_async(&self) -> Result<Event> {
+        self.wait_async().await
+    }
+
+    /// Waits for the notification to be closed, invoked actions are skipped.
+    ///
+    /// Fails if the server went away before it closed the notification.
+    pub async fn wait_for_closed_async(&self) -> Result<CloseReason> {
+        let bus = self.bus.clone().into_name();
+        let mut signals = Signals::subscribe(
+            &self.driven.connection,
+            &bus,
+            self.server.as_deref(),
+            self.id,
+        )
+        .await?;
+        while let Some(event) = signals.next().await {
+            match event {
+                Event::Closed(reason) => return Ok(reason),
+                Event::ServerGone => break,
+                _ => {}
+            }
+        }
+        Err(ErrorKind::Msg(format!(
+            "server went away before notification {} was closed",
+            self.id
+        ))
+        .into())
+    }
+
+    /// Asks the server to close the notification.
+    pub async fn close_async(self) -> Result<()> {
+        let mut message = dbus_rs::build_message("CloseNotification", self.bus.clone())?;
+        message.append_items(&[self.id.into()]);
+        self.driven
+            .call(message, dbus_rs::DEFAULT_REPLY_TIMEOUT)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Sends `notification` on the bus it names, like `Notification::show_async()` does with zbus.
+///
+/// The handle keeps a connection of its own to follow the notification.
+pub async fn show_async(notification: &Notification) -> Result<AsyncHandle> {
+    let driven = Driven::connect(&notification.bus_selection)?;
+    let requested = notification.id.unwrap_or(0);
+    let span = trace::notify_span(notification, requested);
+    let message = dbus_rs::notify_message(notification, requested, notification.bus.clone())?;
+    let timeout = notification
+        .send_timeout
+        .unwrap_or(dbus_rs::DEFAULT_REPLY_TIMEOUT);
+    let reply = driven.call(message, timeout).await?;
+    let (id, server) = dbus_rs::notify_reply(&reply);
+    trace::record_id(&span, id);
+    xdg::check_replaced(notification, requested, id)?;
+    Ok(AsyncHandle {
+        id,
+        driven,
+        bus: notification.bus.clone(),
+        server,
+    })
+}
+
+/// Like [`get_capabilities()`](crate::get_capabilities), but without blocking.
+pub async fn get_capabilities_async() -> Result<Vec<String>> {
+    let reply = call(&BusSelection::Session, "GetCapabilities").await?;
+    Ok(dbus_rs::capabilities_reply(&reply))
+}
+
+/// Like [`get_server_information()`](crate::get_server_information), but without blocking.
+pub async fn get_server_information_async() -> Result<ServerInformation> {
+    let reply = call(&BusSelection::Session, "GetServerInformation").await?;
+    Ok(dbus_rs::server_information_reply(&reply))
+}
+
+/// Calls `method` without arguments on the server on `selection` and returns the reply.
+async fn call(selection: &BusSelection, method: &str) -> Result<Message> {
+    let driven = Driven::connect(selection)?;
+    let message = dbus_rs::build_message(method, Default::default())?;
+    driven.call(message, dbus_rs::DEFAULT_REPLY_TIMEOUT).await
+}
@@ -0,0 +1,82 @@
+use zbus::block_on;
+
+use super::{zbus_rs, NotificationHandle};
+use crate::{error::Result, notification::Notification};
+
+/// Shows notifications through one shared session connection.
+///
+/// [`Notification::show()`] opens a new connection for every notification,
+/// keeping dozens of handles alive that way may run into the connection limit of the bus.
+/// Handles created by a dispatcher all use the same connection and otherwise behave just the same,
+/// each of them only receives the signals of its own notification.
+///
+/// Cloning a dispatcher is cheap, the clones share the connection too.
+///
+/// ```no_run
+/// # use notify_rust::{Notification, NotificationDispatcher};
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// let dispatcher = NotificationDispatcher::new()?;
+/// let mut handles = Vec::new();
+/// for download in ["a.iso", "b.iso", "c.iso"] {
+///     handles.push(dispatcher.show(
+///         Notification::new()
+///             .summary("Download finished")
+///             .body(download)
+///             .action("open", "Open"),
+///     )?);
+/// }
+/// // ... one connection, no matter how many handles are kept around
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (zbus only)
+#[derive(Clone, Debug)]
+pub struct NotificationDispatcher {
+    connection: zbus::Connection,
+}
+
+impl NotificationDispatcher {
+    /// Connects to the session bus.
+    pub fn new() -> Result<NotificationDispatcher> {
+        Ok(NotificationDispatcher {
+            connection: block_on(zbus_rs::session_connection())?,
+        })
+    }
+
+    /// Async version of [`new()`](#method.new).
+    #[cfg(feature = "async")]
+    pub async fn new_async() -> Result<NotificationDispatcher> {
+        Ok(NotificationDispatcher {
+            connection: zbus_rs::session_connection().await?,
+        })
+    }
+
+    /// Uses a connection you already have.
+    pub fn from_connection(connection: zbus::Connection) -> NotificationDispatcher {
+        NotificationDispatcher { connection }
+    }
+
+    /// The shared connection.
+    pub fn connection(&self) -> &zbus::Connection {
+        &self.connection
+    }
+
+    /// Sends `notification` through the shared connection.
+    pub fn show(&self, notification: &Notification) -> Result<NotificationHandle> {
+        block_on(self.send(notification))
+    }
+
+    /// Async version of [`show()`](#method.show).
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self, notification: &Notification) -> Result<NotificationHandle> {
+        self.send(notification).await
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<NotificationHandle> {
+        let bus = notification.bus.clone();
+        zbus_rs::send_notification_at_bus(notification, self.connection.clone(), bus)
+            .await
+            .map(Into::into)
+    }
+}
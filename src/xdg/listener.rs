@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::task::Waker;
+use std::thread;
+
+#[cfg(feature = "dbus")]
+use super::BusSelection;
+use super::{
+    ActionResponse, CloseHandler, CloseReason, Event, NotificationHandle, NotificationHandleInner,
+};
+use crate::action_key::ActionKey;
+use crate::error::{ErrorKind, Result};
+
+/// Tells a listener thread to stop and wakes it up right away, so it never has to poll a flag.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Stop(Arc<StopState>);
+
+#[derive(Debug, Default)]
+struct StopState {
+    stopped: AtomicBool,
+    // zbus listeners await `stopped()` next to their stream
+    waker: Mutex<Option<Waker>>,
+    // dbus listeners block on a connection of their own, a message to its unique name wakes them
+    #[cfg(feature = "dbus")]
+    listener: Mutex<Option<(String, BusSelection)>>,
+}
+
+impl Stop {
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.stopped.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set(&self) {
+        self.0.stopped.store(true, Ordering::SeqCst);
+        if let Some(waker) = self.0.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+        #[cfg(feature = "dbus")]
+        {
+            if let Some((listener, selection)) = self.0.listener.lock().unwrap().take() {
+                super::dbus_rs::wake_listener(&listener, &selection);
+            }
+        }
+    }
+
+    /// Lets `set()` wake the thread blocked on the connection with the unique name `listener`,
+    /// on the bus `selection` picks.
+    #[cfg(feature = "dbus")]
+    pub(crate) fn wake_through(&self, listener: String, selection: BusSelection) {
+        *self.0.listener.lock().unwrap() = Some((listener, selection));
+    }
+
+    /// Resolves once `set()` was called.
+    #[cfg(feature = "zbus")]
+    pub(crate) async fn stopped(&self) {
+        futures_lite::future::poll_fn(|cx| {
+            *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+            if self.is_set() {
+                std::task::Poll::Ready(())
+            } else {
+                std::task::Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+/// Cancels the wait of [`NotificationHandle::wait_cancelable`], it resolves to [`Event::Cancelled`].
+///
+/// Cloned cancellers all cancel the same wait, and can be sent to other threads.
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+#[derive(Clone, Debug)]
+pub struct WaitCanceller(pub(crate) Stop);
+
+#[cfg(all(feature = "async", feature = "zbus"))]
+impl WaitCanceller {
+    /// Wakes the wait up, cancelling after the wait returned does nothing.
+    pub fn cancel(&self) {
+        self.0.set();
+    }
+
+    /// `cancel()` was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_set()
+    }
+}
+
+/// Stops a [`handle_action_until`](super::handle_action_until) from waiting any longer.
+///
+/// Cloned handles all stop the same wait, and can be sent to other threads.
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle(pub(crate) Stop);
+
+impl StopHandle {
+    /// A handle that has not stopped anything yet.
+    pub fn new() -> Self {
+        StopHandle::default()
+    }
+
+    /// Wakes the wait up, it returns without calling its handler.
+    ///
+    /// Stopping before the wait started makes it return right away, stopping again does nothing.
+    pub fn stop(&self) {
+        self.0.set();
+    }
+
+    /// `stop()` was called.
+    pub fn is_stopped(&self) -> bool {
+        self.0.is_set()
+    }
+}
+
+/// Callbacks for the actions of a single notification, dispatched by key.
+///
+/// Created by [`NotificationHandle::on_action`].
+///
+/// Callbacks return either nothing or a `Result`, see [`CallbackResult`].
+/// The first error stops the dispatch, callbacks that would have run after the failing one are skipped,
+/// and [`listen()`](#method.listen) returns it as [`ErrorKind::Callback`].
+///
+/// ```no_run
+/// # use notify_rust::Notification;
+/// # fn archive() -> std::io::Result<()> { Ok(()) }
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// Notification::new()
+///     .summary("New mail")
+///     .action("archive", "Archive")
+///     .show()?
+///     .on_action("archive", archive)
+///     .listen()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ActionListener<'a> {
+    handle: NotificationHandle,
+    callbacks: Callbacks<'a>,
+}
+
+impl<'a> ActionListener<'a> {
+    pub(crate) fn new(handle: NotificationHandle) -> Self {
+        ActionListener {
+            handle,
+            callbacks: Callbacks::default(),
+        }
+    }
+
+    /// Calls `callback` when the action `key` is invoked, replaces earlier callbacks for the same key.
+    pub fn on_action<R: CallbackResult>(
+        self,
+        key: impl Into<ActionKey>,
+        callback: impl FnOnce() -> R + 'a,
+    ) -> Self {
+        self.on_action_with_token(key, move |_| callback())
+    }
+
+    /// Like [`on_action`](#method.on_action), but `callback` gets the activation token the server sent along with the action.
+    ///
+    /// Pass the token to `xdg_activation_v1` (or set `XDG_ACTIVATION_TOKEN`) to raise your window under Wayland,
+    /// see [`NotificationHandle::last_activation_token`].
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # fn raise_window(_token: Option<String>) {}
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// Notification::new()
+    ///     .summary("New message")
+    ///     .action("default", "Open")
+    ///     .show()?
+    ///     .on_action_with_token("default", raise_window)
+    ///     .listen()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_action_with_token<R: CallbackResult>(
+        mut self,
+        key: impl Into<ActionKey>,
+        callback: impl FnOnce(Option<String>) -> R + 'a,
+    ) -> Self {
+        let key = key.into();
+        self.callbacks.async_actions.remove(key.as_str());
+        self.callbacks.actions.insert(
+            key.into(),
+            Box::new(move |token| callback(token).into_result()),
+        );
+        self
+    }
+
+    /// Like [`on_action`](#method.on_action), but `callback` returns a future that is awaited once the action is invoked.
+    ///
+    /// The listener has stopped waiting for signals by then, so a slow callback holds nothing up.
+    /// [`listen()`](#method.listen) blocks on the future, [`listen_async()`](#method.listen_async) awaits it.
+    ///
+    /// ```no_run
+    /// # async fn _doc() -> notify_rust::error::Result<()> {
+    /// # use notify_rust::Notification;
+    /// Notification::new()
+    ///     .summary("Download finished")
+    ///     .action("open", "Open")
+    ///     .show_async()
+    ///     .await?
+    ///     .on_action_async("open", || async {
+    ///         // e.g. ask for a location through a portal
+    ///         println!("opened");
+    ///     })
+    ///     .listen_async()
+    ///     .await
+    /// # }
+    /// ```
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub fn on_action_async<F, Fut>(mut self, key: impl Into<ActionKey>, callback: F) -> Self
+    where
+        F: FnOnce() -> Fut + 'a,
+        Fut: Future + 'a,
+        Fut::Output: CallbackResult,
+    {
+        let key = key.into();
+        self.callbacks.actions.remove(key.as_str());
+        let callback: AsyncActionCallback<'a> =
+            Box::new(move || Box::pin(async move { callback().await.into_result() }));
+        self.callbacks.async_actions.insert(key.into(), callback);
+        self
+    }
+
+    /// Calls `callback` with the key of any action that has no callback of its own.
+    pub fn on_other_action<R: CallbackResult>(
+        mut self,
+        callback: impl FnOnce(&str) -> R + 'a,
+    ) -> Self {
+        self.callbacks.other_action = Some(Box::new(move |key| callback(key).into_result()));
+        self
+    }
+
+    /// Calls `handler` when the notification is closed, accepts `Fn()` and `Fn(CloseReason)`.
+    ///
+    /// Unlike actions, close handlers add up: every registered one is called exactly once, in the order they were registered.
+    pub fn on_close<A, R: CallbackResult>(mut self, handler: impl CloseHandler<A, R> + 'a) -> Self {
+        self.callbacks
+            .close
+            .push(Box::new(move |reason| handler.call(reason).into_result()));
+        self
+    }
+
+    /// Blocks until an action is invoked or the notification is closed and calls the matching callback.
+    ///
+    /// With close handlers, this keeps listening after an action until the notification is closed,
+    /// which servers usually do right after the action unless it is [`Hint::Resident`](crate::Hint::Resident).
+    /// Events are dispatched in the order they arrive.
+    ///
+    /// Returns the error of the first callback that failed.
+    pub fn listen(self) -> Result<()> {
+        let mut callbacks = self.callbacks;
+        let tokens = self.handle.activation_tokens();
+        // a single subscription, a close right behind the action is not missed
+        for event in self.handle.events()? {
+            if let Some(response) = event.as_response() {
+                if let Some(pending) = callbacks.dispatch(&response, tokens.get())? {
+                    futures_lite::future::block_on(pending)?;
+                }
+                if event.is_final() || callbacks.close.is_empty() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Async version of [`listen()`](#method.listen).
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn listen_async(self) -> Result<()> {
+        let mut callbacks = self.callbacks;
+        let events = self.handle.events_async().await?;
+        futures_lite::pin!(events);
+        while let Some(event) = futures_lite::StreamExt::next(&mut events).await {
+            if let Some(response) = event.as_response() {
+                let token = self.handle.last_activation_token();
+                if let Some(pending) = callbacks.dispatch(&response, token)? {
+                    pending.await?;
+                }
+                if event.is_final() || callbacks.close.is_empty() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ActionListener<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ActionListener")
+            .field("handle", &self.handle)
+            .field("close_handlers", &self.callbacks.close.len())
+            .field(
+                "actions",
+                &self
+                    .callbacks
+                    .actions
+                    .keys()
+                    .chain(self.callbacks.async_actions.keys())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// A listener running in a background thread, created by [`NotificationHandle::spawn_listener`].
+///
+/// Dropping this detaches the thread, it keeps going until the notification is closed.
+#[derive(Debug)]
+pub struct ListenerHandle {
+    handle: NotificationHandle,
+    thread: thread::JoinHandle<()>,
+    stop: Stop,
+    ended: Arc<AtomicBool>,
+}
+
+impl ListenerHandle {
+    pub(crate) fn spawn(
+        handle: NotificationHandle,
+        mut handler: impl FnMut(Event) + Send + 'static,
+    ) -> Result<Self> {
+        let stop = Stop::default();
+        let ended = Arc::new(AtomicBool::new(false));
+        let handler = {
+            let ended = Arc::clone(&ended);
+            move |event: Event| {
+                if event.is_final() {
+                    ended.store(true, Ordering::Relaxed);
+                }
+                handler(event);
+            }
+        };
+        let thread = match *handle.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => {
+                inner.spawn_listener(stop.clone(), handler)?
+            }
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => {
+                inner.spawn_listener(stop.clone(), handler)?
+            }
+        };
+        Ok(ListenerHandle {
+            handle,
+            thread,
+            stop,
+            ended,
+        })
+    }
+
+    /// Stops listening and returns the handle once the thread has finished.
+    ///
+    /// The thread is woken up, this does not wait for the next signal to arrive.
+    /// Events that arrived before are still delivered.
+    pub fn stop(self) -> NotificationHandle {
+        self.stop.set();
+        self.join()
+    }
+
+    /// Waits until the notification is closed and returns the handle.
+    ///
+    /// Panics if the handler panicked.
+    pub fn join(self) -> NotificationHandle {
+        if let Err(panic) = self.thread.join() {
+            std::panic::resume_unwind(panic);
+        }
+        if self.ended.load(Ordering::Relaxed) {
+            self.handle.observe(&ActionResponse::ServerGone);
+        }
+        self.handle
+    }
+}
+
+/// Receives the events of all notifications on the bus, created by [`subscribe_all`](super::subscribe_all).
+///
+/// Dropping this stops the subscription without waiting for it.
+#[derive(Debug)]
+pub struct Subscription {
+    thread: Option<thread::JoinHandle<()>>,
+    stop: Stop,
+}
+
+impl Subscription {
+    #[cfg(feature = "dbus")]
+    pub(crate) fn dbus(handler: impl FnMut(u32, Event) + Send + 'static) -> Result<Self> {
+        let stop = Stop::default();
+        let thread = super::dbus_rs::spawn_subscription(stop.clone(), handler)?;
+        Ok(Subscription {
+            thread: Some(thread),
+            stop,
+        })
+    }
+
+    #[cfg(feature = "zbus")]
+    pub(crate) fn zbus(handler: impl FnMut(u32, Event) + Send + 'static) -> Result<Self> {
+        let stop = Stop::default();
+        let thread = super::zbus_rs::spawn_subscription(stop.clone(), handler)?;
+        Ok(Subscription {
+            thread: Some(thread),
+            stop,
+        })
+    }
+
+    /// Unsubscribes and waits for the thread to finish.
+    ///
+    /// Panics if the handler panicked.
+    pub fn stop(mut self) {
+        self.stop.set();
+        if let Some(Err(panic)) = self.thread.take().map(thread::JoinHandle::join) {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stop.set();
+    }
+}
+
+/// What the callbacks of an [`ActionListener`] may return: `()` or a `Result` with any error that converts into a boxed one.
+///
+/// An error ends up in [`ErrorKind::Callback`].
+pub trait CallbackResult {
+    /// Turns this into the result [`ActionListener::listen`] returns.
+    fn into_result(self) -> Result<()>;
+}
+
+impl CallbackResult for () {
+    fn into_result(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<E> CallbackResult for std::result::Result<(), E>
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    fn into_result(self) -> Result<()> {
+        self.map_err(|error| ErrorKind::Callback(error.into()).into())
+    }
+}
+
+type ActionCallback<'a> = Box<dyn FnOnce(Option<String>) -> Result<()> + 'a>;
+type PendingCallback<'a> = Pin<Box<dyn Future<Output = Result<()>> + 'a>>;
+type AsyncActionCallback<'a> = Box<dyn FnOnce() -> PendingCallback<'a> + 'a>;
+type OtherActionCallback<'a> = Box<dyn FnOnce(&str) -> Result<()> + 'a>;
+type CloseCallback<'a> = Box<dyn FnOnce(CloseReason) -> Result<()> + 'a>;
+
+#[derive(Default)]
+struct Callbacks<'a> {
+    actions: HashMap<String, ActionCallback<'a>>,
+    async_actions: HashMap<String, AsyncActionCallback<'a>>,
+    other_action: Option<OtherActionCallback<'a>>,
+    close: Vec<CloseCallback<'a>>,
+}
+
+impl<'a> Callbacks<'a> {
+    /// Calls the matching callback, an async one is returned to be awaited instead.
+    ///
+    /// The activation `token` goes to callbacks that asked for it. Stops at the first callback that fails.
+    fn dispatch(
+        &mut self,
+        response: &ActionResponse,
+        token: Option<String>,
+    ) -> Result<Option<PendingCallback<'a>>> {
+        match *response {
+            ActionResponse::Custom(key) => {
+                if let Some(callback) = self.actions.remove(key) {
+                    callback(token)?;
+                } else if let Some(callback) = self.async_actions.remove(key) {
+                    return Ok(Some(callback()));
+                } else if let Some(callback) = self.other_action.take() {
+                    callback(key)?;
+                }
+            }
+            ActionResponse::Closed(reason) => {
+                for callback in self.close.drain(..) {
+                    callback(reason)?;
+                }
+            }
+            ActionResponse::ServerGone => {}
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn dispatches_by_key() {
+        let called = RefCell::new(Vec::new());
+        let mut callbacks = Callbacks::default();
+        callbacks.actions.insert(
+            "archive".into(),
+            Box::new(|_| {
+                called.borrow_mut().push("archive");
+                Ok(())
+            }),
+        );
+        callbacks.actions.insert(
+            "delete".into(),
+            Box::new(|_| {
+                called.borrow_mut().push("delete");
+                Ok(())
+            }),
+        );
+        callbacks
+            .dispatch(&ActionResponse::Custom("delete"), None)
+            .unwrap();
+        assert_eq!(*called.borrow(), vec!["delete"]);
+    }
+
+    #[test]
+    fn unknown_actions_hit_catch_all() {
+        let other = RefCell::new(None);
+        let mut callbacks = Callbacks {
+            other_action: Some(Box::new(|key: &str| {
+                *other.borrow_mut() = Some(key.to_owned());
+                Ok(())
+            })),
+            ..Default::default()
+        };
+        callbacks
+            .dispatch(&ActionResponse::Custom("snooze"), None)
+            .unwrap();
+        assert_eq!(other.borrow().as_deref(), Some("snooze"));
+
+        // no catch-all registered, nothing to call
+        Callbacks::default()
+            .dispatch(&ActionResponse::Custom("snooze"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn close_gets_reason() {
+        let reason = RefCell::new(None);
+        let mut callbacks = Callbacks {
+            close: vec![Box::new(|r| {
+                *reason.borrow_mut() = Some(r);
+                Ok(())
+            })],
+            ..Default::default()
+        };
+        callbacks
+            .dispatch(&ActionResponse::Closed(CloseReason::Expired), None)
+            .unwrap();
+        assert_eq!(*reason.borrow(), Some(CloseReason::Expired));
+    }
+
+    #[test]
+    fn failing_callback_stops_dispatch() {
+        let called = RefCell::new(Vec::new());
+        let mut callbacks = Callbacks::default();
+        for (name, fails) in [("first", false), ("second", true), ("third", false)] {
+            let called = &called;
+            callbacks.close.push(Box::new(move |_| {
+                called.borrow_mut().push(name);
+                if fails {
+                    Err(ErrorKind::Msg(format!("{} failed", name)).into())
+                } else {
+                    Ok(())
+                }
+            }));
+        }
+        let error = callbacks
+            .dispatch(&ActionResponse::Closed(CloseReason::Dismissed), None)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "second failed");
+        assert_eq!(*called.borrow(), ["first", "second"]);
+    }
+}
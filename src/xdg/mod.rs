@@ -7,12 +7,25 @@ use dbus::ffidisp::Connection as DbusConnection;
 #[cfg(feature = "zbus")]
 use zbus::{block_on, zvariant};
 
-use crate::{error::*, notification::Notification};
-
+use crate::{
+    action_key::ActionKey, capabilities::Capability, error::*, miniver::Version,
+    notification::Notification,
+};
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "dbus")]
-mod dbus_rs;
+pub(crate) mod dbus_rs;
+#[cfg(feature = "dbus_tokio")]
+pub mod dbus_tokio;
 #[cfg(all(feature = "dbus", not(feature = "zbus")))]
 use dbus_rs::bus;
 
@@ -20,10 +33,35 @@ use dbus_rs::bus;
 mod zbus_rs;
 #[cfg(all(feature = "zbus", not(feature = "dbus")))]
 use zbus_rs::bus;
+#[cfg(feature = "zbus")]
+pub use zbus_rs::{NotificationsProxy, NotificationsProxyBlocking};
 
 #[cfg(all(feature = "dbus", feature = "zbus"))]
 mod bus;
 
+mod listener;
+
+#[cfg(any(feature = "dbus", feature = "zbus"))]
+pub(crate) mod session_bus;
+
+#[cfg(any(feature = "dbus", feature = "zbus"))]
+mod trace;
+
+#[cfg(feature = "zbus")]
+pub(crate) mod timer;
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub use listener::WaitCanceller;
+pub use listener::{ActionListener, CallbackResult, ListenerHandle, StopHandle, Subscription};
+
+#[cfg(feature = "zbus")]
+mod dispatcher;
+#[cfg(feature = "zbus")]
+pub use dispatcher::NotificationDispatcher;
+#[cfg(feature = "dbus")]
+mod signal_dispatcher;
+#[cfg(feature = "dbus")]
+pub use signal_dispatcher::SignalDispatcher;
+
 // #[cfg(all(feature = "server", feature = "dbus", unix, not(target_os = "macos")))]
 // pub mod server_dbus;
 
@@ -50,6 +88,78 @@ pub static NOTIFICATION_OBJECTPATH: &str = "/org/freedesktop/Notifications";
 
 pub(crate) use bus::NotificationBus;
 
+/// The bus `de.hoodie.Notification.<sub_bus>`, fails with [`ErrorKind::InvalidBusName`] unless that is a valid name.
+pub(crate) fn custom_bus(sub_bus: &str) -> Result<NotificationBus> {
+    Ok(NotificationBus::custom(sub_bus)?)
+}
+
+/// Checks `name` against the rules for well-known bus names.
+///
+/// That is at most 255 characters, in at least two elements separated by `.`,
+/// each made of `[A-Za-z0-9_-]` and not starting with a digit.
+pub(crate) fn check_bus_name(name: &str) -> std::result::Result<(), InvalidBusName> {
+    const MAX_LEN: usize = 255;
+
+    if name.len() > MAX_LEN {
+        return Err(InvalidBusName::new(
+            name,
+            format!("longer than {} characters", MAX_LEN),
+        ));
+    }
+    let mut elements = 0;
+    for element in name.split('.') {
+        elements += 1;
+        let first = match element.chars().next() {
+            Some(first) => first,
+            None => return Err(InvalidBusName::new(name, "has an empty element")),
+        };
+        if first.is_ascii_digit() {
+            return Err(InvalidBusName::new(
+                name,
+                format!("element {:?} starts with a digit", element),
+            ));
+        }
+        if let Some(bad) = element
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '-'))
+        {
+            return Err(InvalidBusName::new(
+                name,
+                format!("element {:?} contains {:?}", element, bad),
+            ));
+        }
+    }
+    if elements < 2 {
+        return Err(InvalidBusName::new(name, "needs at least two elements"));
+    }
+    Ok(())
+}
+
+/// Which message bus to connect to, see [`Notification::on_bus()`](crate::Notification::on_bus).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BusSelection {
+    /// The session bus at `DBUS_SESSION_BUS_ADDRESS`, or at `$XDG_RUNTIME_DIR/bus` without it.
+    #[default]
+    Session,
+    /// The system bus, e.g. for a notification bridge on a headless machine.
+    System,
+    /// The bus at this D-Bus address, e.g. `unix:path=/run/my-bus`, like a private `dbus-daemon` for tests.
+    Address(String),
+}
+
+/// The `spec_version` most recently reported by [`get_server_information()`].
+static CACHED_SPEC_VERSION: Mutex<Option<String>> = Mutex::new(None);
+
+pub(crate) fn cache_spec_version(info: &ServerInformation) {
+    if let Ok(mut cached) = CACHED_SPEC_VERSION.lock() {
+        *cached = Some(info.spec_version.clone());
+    }
+}
+
+pub(crate) fn cached_spec_version() -> Option<String> {
+    CACHED_SPEC_VERSION.lock().ok()?.clone()
+}
+
 #[derive(Debug)]
 enum NotificationHandleInner {
     #[cfg(feature = "dbus")]
@@ -59,12 +169,98 @@ enum NotificationHandleInner {
     Zbus(zbus_rs::ZbusNotificationHandle),
 }
 
+/// The connection a [`NotificationHandle`] talks to the server through.
+///
+/// Returned by [`NotificationHandle::into_parts`] so it can be reused.
+#[derive(Debug)]
+pub enum HandleConnection {
+    /// Connection of the `dbus` backend.
+    #[cfg(feature = "dbus")]
+    Dbus(DbusConnection),
+
+    /// Connection of the `zbus` backend.
+    #[cfg(feature = "zbus")]
+    Zbus(zbus::Connection),
+}
+
+/// A borrowed connection of a [`NotificationHandle`], to make further calls without opening another one.
+///
+/// Returned by [`NotificationHandle::connection`], accepted by [`get_capabilities_on`] and [`get_server_information_on`].
+#[derive(Clone, Copy, Debug)]
+pub enum ConnectionRef<'a> {
+    /// Connection of the `dbus` backend.
+    #[cfg(feature = "dbus")]
+    Dbus(&'a DbusConnection),
+
+    /// Connection of the `zbus` backend, clone it to keep it around.
+    #[cfg(feature = "zbus")]
+    Zbus(&'a zbus::Connection),
+}
+
+#[cfg(feature = "dbus")]
+impl<'a> From<&'a DbusConnection> for ConnectionRef<'a> {
+    fn from(connection: &'a DbusConnection) -> Self {
+        ConnectionRef::Dbus(connection)
+    }
+}
+
+#[cfg(feature = "zbus")]
+impl<'a> From<&'a zbus::Connection> for ConnectionRef<'a> {
+    fn from(connection: &'a zbus::Connection) -> Self {
+        ConnectionRef::Zbus(connection)
+    }
+}
+
+#[cfg(feature = "dbus")]
+impl From<DbusConnection> for HandleConnection {
+    fn from(connection: DbusConnection) -> Self {
+        HandleConnection::Dbus(connection)
+    }
+}
+
+#[cfg(feature = "zbus")]
+impl From<zbus::Connection> for HandleConnection {
+    fn from(connection: zbus::Connection) -> Self {
+        HandleConnection::Zbus(connection)
+    }
+}
+
+/// The server replaced `requested` in place with `returned`, or failed to when that was required.
+#[cfg(any(feature = "dbus", feature = "zbus"))]
+pub(crate) fn check_replaced(
+    notification: &Notification,
+    requested: u32,
+    returned: u32,
+) -> Result<bool> {
+    if requested == 0 || requested == returned {
+        return Ok(requested != 0);
+    }
+    if notification.require_stable_id {
+        return Err(ErrorKind::IdChanged {
+            requested,
+            returned,
+        }
+        .into());
+    }
+    Ok(false)
+}
+
 /// A handle to a shown notification.
 ///
 /// This keeps a connection alive to ensure actions work on certain desktops.
+///
+/// Handles compare and hash by their [`id()`](#method.id) alone and borrow as `u32`,
+/// so a `HashSet<NotificationHandle>` can be searched by a bare id.
+/// Ids are only unique per server, handles of notifications sent to different servers may compare equal.
+/// [`update()`](#method.update) may change the id, don't update a handle while it is the key of a map.
 #[derive(Debug)]
 pub struct NotificationHandle {
-    inner: NotificationHandleInner,
+    // only `None` once taken apart by `into_parts()`
+    inner: Option<NotificationHandleInner>,
+    close_on_drop: AtomicBool,
+    // what was asked for with `close_on_drop()`, `reshow()` arms it again after a close
+    wants_close_on_drop: bool,
+    replay_sounds: bool,
 }
 
 #[allow(dead_code)]
@@ -75,9 +271,7 @@ impl NotificationHandle {
         connection: DbusConnection,
         notification: Notification,
     ) -> NotificationHandle {
-        NotificationHandle {
-            inner: dbus_rs::DbusNotificationHandle::new(id, connection, notification).into(),
-        }
+        dbus_rs::DbusNotificationHandle::new(id, connection, notification).into()
     }
 
     #[cfg(feature = "zbus")]
@@ -86,206 +280,991 @@ impl NotificationHandle {
         connection: zbus::Connection,
         notification: Notification,
     ) -> NotificationHandle {
-        NotificationHandle {
-            inner: zbus_rs::ZbusNotificationHandle::new(id, connection, notification).into(),
+        zbus_rs::ZbusNotificationHandle::new(id, connection, notification).into()
+    }
+
+    /// Rebuilds a handle from the parts returned by [`into_parts`](#method.into_parts).
+    ///
+    /// The id may also come from an earlier run, e.g. to close a notification after a restart.
+    /// `notification` should be the one that was shown, it is sent again by [`update`](#method.update).
+    ///
+    /// ```no_run
+    /// # use notify_rust::{HandleConnection, Notification, NotificationHandle};
+    /// # fn _doc(connection: HandleConnection) -> notify_rust::error::Result<()> {
+    /// let stored_id: u32 = 42; // e.g. read from disk
+    /// NotificationHandle::from_parts(stored_id, connection, Notification::new()).close()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_parts(
+        id: u32,
+        connection: impl Into<HandleConnection>,
+        notification: Notification,
+    ) -> NotificationHandle {
+        match connection.into() {
+            #[cfg(feature = "dbus")]
+            HandleConnection::Dbus(connection) => {
+                NotificationHandle::for_dbus(id, connection, notification)
+            }
+            #[cfg(feature = "zbus")]
+            HandleConnection::Zbus(connection) => {
+                NotificationHandle::for_zbus(id, connection, notification)
+            }
+        }
+    }
+
+    /// Takes the handle apart into its id, connection and notification.
+    ///
+    /// This never closes the notification, even with [`close_on_drop`](#method.close_on_drop) set.
+    pub fn into_parts(mut self) -> (u32, HandleConnection, Notification) {
+        match self.inner.take().expect("handle already taken apart") {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(inner) => {
+                let (id, connection, notification) = inner.into_parts();
+                (id, connection.into(), notification)
+            }
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(inner) => {
+                let (id, connection, notification) = inner.into_parts();
+                (id, connection.into(), notification)
+            }
         }
     }
 
     /// Waits for the user to act on a notification and then calls
     /// `invocation_closure` with the name of the corresponding action.
+    ///
+    /// Returns without calling `invocation_closure` if the notification server leaves the bus.
     pub fn wait_for_action<F>(self, invocation_closure: F)
     where
         F: FnOnce(&str),
     {
-        match self.inner {
+        self.wait_for_response(|action: &ActionResponse| match action {
+            ActionResponse::Custom(action) => invocation_closure(action),
+            ActionResponse::Closed(_reason) => invocation_closure("__closed"), // FIXME: remove backward compatibility with 5.0
+            ActionResponse::ServerGone => {}
+        });
+    }
+
+    /// The activation tokens this handle records, they stay readable once the handle is consumed.
+    pub(crate) fn activation_tokens(&self) -> ActivationToken {
+        match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(inner) => {
-                inner.wait_for_action(|action: &ActionResponse| match action {
-                    ActionResponse::Custom(action) => invocation_closure(action),
-                    ActionResponse::Closed(_reason) => invocation_closure("__closed"), // FIXME: remove backward compatibility with 5.0
-                });
-            }
+            NotificationHandleInner::Dbus(ref inner) => inner.activation_token.clone(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => inner.activation_token.clone(),
+        }
+    }
+
+    pub(crate) fn wait_for_response(self, handler: impl ActionResponseHandler) {
+        let handler = |response: &ActionResponse| {
+            self.observe(response);
+            handler.call(response);
+        };
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.wait_for_action(handler),
 
             #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(inner) => {
-                block_on(
-                    inner.wait_for_action(|action: &ActionResponse| match action {
-                        ActionResponse::Custom(action) => invocation_closure(action),
-                        ActionResponse::Closed(_reason) => invocation_closure("__closed"), // FIXME: remove backward compatibility with 5.0
-                    }),
-                );
-            }
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.wait_for_action(handler)),
         };
     }
 
-    /// Manually close the notification
+    /// Registers `callback` for the action `key` and returns an [`ActionListener`] to register more.
     ///
-    /// # Example
+    /// Nothing happens until you call [`ActionListener::listen`].
     ///
     /// ```no_run
-    /// # use notify_rust::*;
-    /// let handle: NotificationHandle = Notification::new()
-    ///     .summary("oh no")
-    ///     .hint(notify_rust::Hint::Transient(true))
-    ///     .body("I'll be here till you close me!")
-    ///     .hint(Hint::Resident(true)) // does not work on kde
-    ///     .timeout(Timeout::Never) // works on kde and gnome
+    /// # use notify_rust::Notification;
+    /// Notification::new()
+    ///     .summary("New mail")
+    ///     .action("archive", "Archive")
+    ///     .action("delete", "Delete")
     ///     .show()
+    ///     .unwrap()
+    ///     .on_action("archive", || println!("archived"))
+    ///     .on_action("delete", || println!("deleted"))
+    ///     .on_close(|reason| println!("closed: {:?}", reason))
+    ///     .listen()
     ///     .unwrap();
-    /// // ... and then later
-    /// handle.close();
     /// ```
-    pub fn close(self) {
-        match self.inner {
-            #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(inner) => inner.close(),
-            #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(inner) => block_on(inner.close()),
-        }
+    pub fn on_action<'a, R: CallbackResult>(
+        self,
+        key: impl Into<ActionKey>,
+        callback: impl FnOnce() -> R + 'a,
+    ) -> ActionListener<'a> {
+        ActionListener::new(self).on_action(key, callback)
     }
 
-    /// Executes a closure after the notification has closed.
+    /// Registers `callback` for the action `key`, see [`ActionListener::on_action_with_token`].
+    pub fn on_action_with_token<'a, R: CallbackResult>(
+        self,
+        key: impl Into<ActionKey>,
+        callback: impl FnOnce(Option<String>) -> R + 'a,
+    ) -> ActionListener<'a> {
+        ActionListener::new(self).on_action_with_token(key, callback)
+    }
+
+    /// Registers an async `callback` for the action `key`, see [`ActionListener::on_action_async`].
     ///
-    /// ## Example 1: *I don't care about why it closed* (the good ole API)
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub fn on_action_async<'a, F, Fut>(
+        self,
+        key: impl Into<ActionKey>,
+        callback: F,
+    ) -> ActionListener<'a>
+    where
+        F: FnOnce() -> Fut + 'a,
+        Fut: std::future::Future + 'a,
+        Fut::Output: CallbackResult,
+    {
+        ActionListener::new(self).on_action_async(key, callback)
+    }
+
+    /// Calls `handler` with every [`Event`] of the notification from a background thread.
+    ///
+    /// The thread ends after [`Event::Closed`] or [`Event::ServerGone`], or once you call [`ListenerHandle::stop`].
+    /// Either way you get the handle back from the [`ListenerHandle`].
     ///
     /// ```no_run
-    /// # use notify_rust::Notification;
-    /// Notification::new().summary("Time is running out")
-    ///                    .body("This will go away.")
-    ///                    .icon("clock")
-    ///                    .show()
-    ///                    .unwrap()
-    ///                    .on_close(|| println!("closed"));
+    /// # use notify_rust::{Event, Notification};
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let listener = Notification::new()
+    ///     .summary("Backup finished")
+    ///     .action("open", "Open folder")
+    ///     .show()?
+    ///     .spawn_listener(|event| {
+    ///         if let Event::ActionInvoked(action) = event {
+    ///             println!("{}", action);
+    ///         }
+    ///     })?;
+    /// // ... keep doing other work
+    /// listener.stop().close()?;
+    /// # Ok(())
+    /// # }
     /// ```
+    pub fn spawn_listener(
+        self,
+        handler: impl FnMut(Event) + Send + 'static,
+    ) -> Result<ListenerHandle> {
+        ListenerHandle::spawn(self, handler)
+    }
+
+    /// Like [`wait_for_action`](#method.wait_for_action), but gives up after `timeout`.
     ///
-    /// ## Example 2: *I **do** care about why it closed* (added in v4.5.0)
+    /// Some servers never close resident notifications by themselves,
+    /// waiting for those without a timeout may block forever.
+    /// Once the timeout has passed `invocation_closure` is not going to be called anymore.
     ///
     /// ```no_run
     /// # use notify_rust::Notification;
-    /// Notification::new().summary("Time is running out")
-    ///                    .body("This will go away.")
-    ///                    .icon("clock")
-    ///                    .show()
-    ///                    .unwrap()
-    ///                    .on_close(|reason| println!("closed: {:?}", reason));
+    /// # use std::time::Duration;
+    /// let handle = Notification::new()
+    ///     .summary("Update available")
+    ///     .action("install", "Install now")
+    ///     .show()
+    ///     .unwrap();
+    /// if handle
+    ///     .wait_for_action_timeout(Duration::from_secs(30), |action| println!("{}", action))
+    ///     .is_err()
+    /// {
+    ///     handle.close().unwrap();
+    /// }
     /// ```
-    pub fn on_close<A>(self, handler: impl CloseHandler<A>) {
-        match self.inner {
+    pub fn wait_for_action_timeout<F>(
+        &self,
+        timeout: Duration,
+        invocation_closure: F,
+    ) -> std::result::Result<(), WaitTimedOut>
+    where
+        F: FnOnce(&str),
+    {
+        let handler = |action: &ActionResponse| match action {
+            ActionResponse::Custom(action) => invocation_closure(action),
+            ActionResponse::Closed(_reason) => invocation_closure("__closed"), // FIXME: remove backward compatibility with 5.0
+            ActionResponse::ServerGone => {}
+        };
+        let handler = |response: &ActionResponse| {
+            self.observe(response);
+            handler(response);
+        };
+        match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(inner) => {
-                inner.wait_for_action(|action: &ActionResponse| {
-                    if let ActionResponse::Closed(reason) = action {
-                        handler.call(*reason);
-                    }
-                });
+            NotificationHandleInner::Dbus(ref inner) => {
+                inner.wait_for_action_timeout(timeout, handler)
             }
+
             #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(inner) => {
-                block_on(inner.wait_for_action(|action: &ActionResponse| {
-                    if let ActionResponse::Closed(reason) = action {
-                        handler.call(*reason);
-                    }
-                }));
+            NotificationHandleInner::Zbus(ref inner) => {
+                block_on(inner.wait_for_action_timeout(timeout, handler))
             }
-        };
+        }
     }
 
-    /// Replace the original notification with an updated version
-    /// ## Example
-    /// ```no_run
-    /// # use notify_rust::Notification;
-    /// let mut notification = Notification::new().summary("Latest News")
-    ///                                           .body("Bayern Dortmund 3:2")
-    ///                                           .show()
-    ///                                           .unwrap();
-    ///
-    /// std::thread::sleep_ms(1_500);
+    /// Waits until the notification is closed, but no longer than `timeout`.
     ///
-    /// notification.summary("Latest News (Correction)")
-    ///             .body("Bayern Dortmund 3:3");
+    /// Invoked actions are skipped, they still reach every other listener of the notification.
+    /// If the server goes away there is nothing to wait for anymore, that ends the wait early with [`WaitError::ServerGone`].
     ///
-    /// notification.update();
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # use std::time::Duration;
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let handle = Notification::new().summary("Battery low").show()?;
+    /// match handle.wait_for_closed(Duration::from_secs(60)) {
+    ///     Ok(reason) => println!("closed: {:?}", reason),
+    ///     Err(_) => handle.close()?,
+    /// }
+    /// # Ok(())
+    /// # }
     /// ```
-    /// Watch out for different implementations of the
-    /// notification server! On plasma5 for instance, you should also change the appname, so the old
-    /// message is really replaced and not just amended. Xfce behaves well, all others have not
-    /// been tested by the developer.
-    pub fn update(&mut self) {
-        match self.inner {
+    pub fn wait_for_closed(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<CloseReason, WaitError> {
+        let reason = match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(ref mut inner) => inner.update(),
+            NotificationHandleInner::Dbus(ref inner) => inner.wait_for_closed(timeout),
+
             #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(ref mut inner) => inner.update(),
-        }
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.wait_for_closed(timeout)),
+        }?;
+        self.observe(&ActionResponse::Closed(reason));
+        Ok(reason)
     }
 
-    /// Returns the Handle's id.
-    pub fn id(&self) -> u32 {
-        match self.inner {
+    /// Resolves once the notification is closed, with the reason the server gave.
+    ///
+    /// This is the async counterpart of [`wait_for_closed()`](#method.wait_for_closed) without a timeout,
+    /// race it against a timer of your own if you need one.
+    /// Invoked actions are skipped on a subscription of its own, they still reach every other listener.
+    /// Dropping the future unsubscribes again, so cancelling it loses nothing.
+    /// Fails if the server went away before it closed the notification.
+    ///
+    /// ```no_run
+    /// # async fn _doc() -> notify_rust::error::Result<()> {
+    /// # use notify_rust::Notification;
+    /// let reason = Notification::new()
+    ///     .summary("Build finished")
+    ///     .show_async()
+    ///     .await?
+    ///     .wait_for_closed_async()
+    ///     .await?;
+    /// println!("closed: {:?}", reason);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn wait_for_closed_async(&self) -> Result<CloseReason> {
+        match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(ref inner) => inner.id,
-            #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(ref inner) => inner.id,
+            NotificationHandleInner::Dbus(_) => {
+                Err("wait_for_closed_async() is only supported with zbus".into())
+            }
+
+            NotificationHandleInner::Zbus(ref inner) => {
+                if let Some(reason) = inner.wait_for_close().await? {
+                    self.observe(&ActionResponse::Closed(reason));
+                    Ok(reason)
+                } else {
+                    self.observe(&ActionResponse::ServerGone);
+                    Err(ErrorKind::Msg(format!(
+                        "server went away before notification {} was closed",
+                        self.id()
+                    ))
+                    .into())
+                }
+            }
         }
     }
-}
-
-/// Required for `DerefMut`
-impl Deref for NotificationHandle {
-    type Target = Notification;
 
-    fn deref(&self) -> &Notification {
-        match self.inner {
+    /// Waits for whatever happens first to the notification: an invoked action, a reply or its closing.
+    ///
+    /// Only that one [`Event`] is consumed, the handle unsubscribes right after it,
+    /// so signals that follow are left for the next call.
+    ///
+    /// ```no_run
+    /// # use notify_rust::{Event, Notification};
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let handle = Notification::new()
+    ///     .summary("Incoming call")
+    ///     .action("accept", "Accept")
+    ///     .show()?;
+    /// match handle.wait()? {
+    ///     Event::ActionInvoked(action) => println!("{}", action),
+    ///     Event::Closed(reason) => println!("missed: {:?}", reason),
+    ///     _ => (),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait(&self) -> Result<Event> {
+        let event = match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(ref inner) => &inner.notification,
+            NotificationHandleInner::Dbus(ref inner) => inner.wait_for_event(),
+
             #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(ref inner) => &inner.notification,
-        }
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.wait_for_event()),
+        }?;
+        self.observe_event(&event);
+        Ok(event)
     }
-}
 
-/// Allow you to easily modify notification properties
-impl DerefMut for NotificationHandle {
-    fn deref_mut(&mut self) -> &mut Notification {
-        match self.inner {
+    /// Async version of [`wait()`](#method.wait).
+    ///
+    /// Dropping the future cancels the wait and unsubscribes from the signals again.
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn wait_async(&self) -> Result<Event> {
+        match *self.inner() {
             #[cfg(feature = "dbus")]
-            NotificationHandleInner::Dbus(ref mut inner) => &mut inner.notification,
-            #[cfg(feature = "zbus")]
-            NotificationHandleInner::Zbus(ref mut inner) => &mut inner.notification,
-        }
-    }
-}
+            NotificationHandleInner::Dbus(_) => {
+                Err("wait_async() is only supported with zbus".into())
+            }
 
-#[cfg(feature = "dbus")]
-impl From<dbus_rs::DbusNotificationHandle> for NotificationHandleInner {
-    fn from(handle: dbus_rs::DbusNotificationHandle) -> NotificationHandleInner {
-        NotificationHandleInner::Dbus(handle)
+            NotificationHandleInner::Zbus(ref inner) => {
+                let event = inner.wait_for_event().await?;
+                self.observe_event(&event);
+                Ok(event)
+            }
+        }
     }
-}
 
-#[cfg(feature = "zbus")]
-impl From<zbus_rs::ZbusNotificationHandle> for NotificationHandleInner {
-    fn from(handle: zbus_rs::ZbusNotificationHandle) -> NotificationHandleInner {
-        NotificationHandleInner::Zbus(handle)
+    /// Waits for the user to act on the notification without blocking the thread.
+    ///
+    /// Resolves to the next [`Event`] of this notification, an invoked action, a reply or its closing.
+    /// Dropping the future cancels the wait and unsubscribes from the signals again.
+    ///
+    /// ```no_run
+    /// # async fn _doc() -> notify_rust::error::Result<()> {
+    /// # use notify_rust::{Event, Notification};
+    /// let handle = Notification::new()
+    ///     .summary("Build finished")
+    ///     .action("open", "Open Log")
+    ///     .show_async()
+    ///     .await?;
+    /// if let Event::ActionInvoked(action) = handle.wait_for_action_async().await? {
+    ///     println!("{}", action);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn wait_for_action_async(&self) -> Result<Event> {
+        self.wait_async().await
     }
-}
 
-#[cfg(feature = "dbus")]
-impl From<dbus_rs::DbusNotificationHandle> for NotificationHandle {
-    fn from(handle: dbus_rs::DbusNotificationHandle) -> NotificationHandle {
-        NotificationHandle {
-            inner: handle.into(),
-        }
+    /// Like [`wait_for_action_async()`](#method.wait_for_action_async), but can be cancelled from elsewhere.
+    ///
+    /// Calling [`WaitCanceller::cancel`] resolves the wait to [`Event::Cancelled`].
+    /// Unlike dropping the future, this removes the match rules from the bus before the wait returns,
+    /// so nothing is left subscribed once it did.
+    ///
+    /// ```no_run
+    /// # async fn _doc() -> notify_rust::error::Result<()> {
+    /// # use notify_rust::{Event, Notification};
+    /// let handle = Notification::new()
+    ///     .summary("Download finished")
+    ///     .action("open", "Open")
+    ///     .show_async()
+    ///     .await?;
+    /// let (wait, canceller) = handle.wait_cancelable();
+    /// std::thread::spawn(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(5));
+    ///     canceller.cancel();
+    /// });
+    /// match wait.await? {
+    ///     Event::ActionInvoked(action) => println!("{}", action),
+    ///     Event::Cancelled => println!("nobody cared"),
+    ///     _ => (),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub fn wait_cancelable(
+        &self,
+    ) -> (
+        impl std::future::Future<Output = Result<Event>> + '_,
+        WaitCanceller,
+    ) {
+        let stop = listener::Stop::default();
+        let canceller = WaitCanceller(stop.clone());
+        let wait = async move {
+            match *self.inner() {
+                #[cfg(feature = "dbus")]
+                NotificationHandleInner::Dbus(_) => {
+                    Err("wait_cancelable() is only supported with zbus".into())
+                }
+
+                NotificationHandleInner::Zbus(ref inner) => {
+                    let event = inner.wait_for_event_until(&stop).await?;
+                    self.observe_event(&event);
+                    Ok(event)
+                }
+            }
+        };
+        (wait, canceller)
     }
-}
 
-#[cfg(feature = "zbus")]
-impl From<zbus_rs::ZbusNotificationHandle> for NotificationHandle {
-    fn from(handle: zbus_rs::ZbusNotificationHandle) -> NotificationHandle {
-        NotificationHandle {
-            inner: handle.into(),
-        }
-    }
+    /// Everything that happens to this notification from now on, until it is closed.
+    ///
+    /// The iterator blocks on each call to `next()` and ends after yielding [`Event::Closed`],
+    /// or [`Event::ServerGone`] should the server leave the bus first.
+    ///
+    /// ```no_run
+    /// # use notify_rust::{Event, Notification};
+    /// let handle = Notification::new()
+    ///     .summary("Build finished")
+    ///     .action("open", "Open Log")
+    ///     .show()
+    ///     .unwrap();
+    /// for event in handle.events().unwrap() {
+    ///     match event {
+    ///         Event::ActionInvoked(action) => println!("invoked {}", action),
+    ///         Event::Closed(reason) => println!("closed: {:?}", reason),
+    ///         _ => (),
+    ///     }
+    /// }
+    /// ```
+    pub fn events(&self) -> Result<Events<'_>> {
+        let inner = match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => {
+                EventsInner::Dbus(Box::new(inner.events()?))
+            }
+
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => {
+                EventsInner::Zbus(Box::new(block_on(inner.events())?), Default::default())
+            }
+        };
+        Ok(Events {
+            handle: self,
+            inner,
+        })
+    }
+
+    /// Returns the next pending [`Event`] of this notification without blocking.
+    ///
+    /// Meant for loops that can't wait, like games or UIs that redraw anyway.
+    /// The handle subscribes to the signals on the first call,
+    /// so make it right after showing the notification to not miss anything.
+    /// Once [`Event::Closed`] or [`Event::ServerGone`] was returned every further call returns `None`,
+    /// until the notification is shown again with [`reshow()`](#method.reshow).
+    ///
+    /// ```no_run
+    /// # use notify_rust::{Event, Notification};
+    /// let mut handle = Notification::new()
+    ///     .summary("Level complete")
+    ///     .action("next", "Next Level")
+    ///     .show()
+    ///     .unwrap();
+    /// loop {
+    ///     match handle.poll_event() {
+    ///         Some(Event::ActionInvoked(action)) => println!("{}", action),
+    ///         Some(Event::Closed(_)) => break,
+    ///         _ => { /* draw a frame */ }
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_event(&mut self) -> Option<Event> {
+        let event = match *self.inner_mut() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref mut inner) => inner.poll_event(),
+
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref mut inner) => block_on(inner.poll_event()),
+        }?;
+        self.observe_event(&event);
+        Some(event)
+    }
+
+    /// Async version of [`events()`](#method.events), a [`Stream`](futures_lite::Stream) that ends after [`Event::Closed`] or [`Event::ServerGone`].
+    ///
+    /// Dropping the stream unsubscribes from the signals again.
+    ///
+    /// (zbus only)
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    pub async fn events_async(&self) -> Result<impl futures_lite::Stream<Item = Event> + '_> {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(_) => {
+                Err("events_async() is only supported with zbus".into())
+            }
+
+            NotificationHandleInner::Zbus(ref inner) => {
+                let events = inner.events().await?;
+                Ok(futures_lite::StreamExt::inspect(events, move |event| {
+                    self.observe_event(event);
+                }))
+            }
+        }
+    }
+
+    /// Manually close the notification
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use notify_rust::*;
+    /// let handle: NotificationHandle = Notification::new()
+    ///     .summary("oh no")
+    ///     .hint(notify_rust::Hint::Transient(true))
+    ///     .body("I'll be here till you close me!")
+    ///     .hint(Hint::Resident(true)) // does not work on kde
+    ///     .timeout(Timeout::Never) // works on kde and gnome
+    ///     .show()
+    ///     .unwrap();
+    /// // ... and then later
+    /// handle.close().unwrap();
+    /// ```
+    ///
+    /// Fails if the server rejects the request, e.g. because the notification is already gone.
+    pub fn close(self) -> Result<()> {
+        self.close_on_drop.store(false, Ordering::Relaxed);
+        self.close_ref()
+    }
+
+    fn close_ref(&self) -> Result<()> {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.close(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.close()),
+        }
+    }
+
+    /// Closes the notification and waits for the server to confirm it.
+    ///
+    /// Returns the [`CloseReason`] the server reported, usually [`CloseReason::CloseAction`].
+    /// This blocks until the server emits `NotificationClosed`, which it is required to do.
+    pub fn close_and_wait(self) -> Result<CloseReason> {
+        self.close_on_drop.store(false, Ordering::Relaxed);
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.close_and_wait(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.close_and_wait()),
+        }
+    }
+
+    /// Closes the notification once this handle is dropped, e.g. while unwinding from a panic.
+    ///
+    /// Closing on drop is best-effort, errors are ignored.
+    /// Nothing is sent if the handle was closed explicitly or has already seen the notification close.
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// let mut handle = Notification::new().summary("Copying files").show().unwrap();
+    /// handle.close_on_drop(true);
+    /// // ... the notification goes away with `handle`
+    /// ```
+    pub fn close_on_drop(&mut self, close: bool) -> &mut NotificationHandle {
+        *self.close_on_drop.get_mut() = close;
+        self.wants_close_on_drop = close;
+        self
+    }
+
+    fn inner(&self) -> &NotificationHandleInner {
+        self.inner.as_ref().expect("handle already taken apart")
+    }
+
+    fn inner_mut(&mut self) -> &mut NotificationHandleInner {
+        self.inner.as_mut().expect("handle already taken apart")
+    }
+
+    /// Remembers a close reported by the server, there is nothing left to close on drop then.
+    fn observe(&self, response: &ActionResponse) {
+        if let ActionResponse::Closed(_) | ActionResponse::ServerGone = response {
+            self.close_on_drop.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn observe_event(&self, event: &Event) {
+        if let Some(response) = event.as_response() {
+            self.observe(&response);
+        }
+    }
+
+    /// Executes a closure after the notification has closed.
+    ///
+    /// ## Example 1: *I don't care about why it closed* (the good ole API)
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// Notification::new().summary("Time is running out")
+    ///                    .body("This will go away.")
+    ///                    .icon("clock")
+    ///                    .show()
+    ///                    .unwrap()
+    ///                    .on_close(|| println!("closed"));
+    /// ```
+    ///
+    /// ## Example 2: *I **do** care about why it closed* (added in v4.5.0)
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// Notification::new().summary("Time is running out")
+    ///                    .body("This will go away.")
+    ///                    .icon("clock")
+    ///                    .show()
+    ///                    .unwrap()
+    ///                    .on_close(|reason| println!("closed: {:?}", reason));
+    /// ```
+    ///
+    /// Only `NotificationClosed` is listened for, invoked actions don't end the wait.
+    /// The handler is not called if the server goes away or waiting fails, the error is only logged.
+    /// Use [`try_on_close()`](#method.try_on_close) to handle it yourself.
+    pub fn on_close<A>(self, handler: impl CloseHandler<A>) {
+        if let Err(error) = self.try_on_close(handler) {
+            log::warn!("stopped waiting for the notification to close: {}", error);
+        }
+    }
+
+    /// Like [`on_close()`](#method.on_close), but returns what the handler returned.
+    ///
+    /// `Ok(None)` if the server went away before it closed the notification, the handler is not called then.
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let reason = Notification::new()
+    ///     .summary("Time is running out")
+    ///     .show()?
+    ///     .try_on_close(|reason| reason)?;
+    /// println!("closed: {:?}", reason);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_on_close<A, R>(self, handler: impl CloseHandler<A, R>) -> Result<Option<R>> {
+        let closed = match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.wait_for_close(),
+
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => block_on(inner.wait_for_close()),
+        }?;
+        if let Some(reason) = closed {
+            self.observe(&ActionResponse::Closed(reason));
+            Ok(Some(handler.call(reason)))
+        } else {
+            self.observe(&ActionResponse::ServerGone);
+            Ok(None)
+        }
+    }
+
+    /// Replace the original notification with an updated version
+    /// ## Example
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// let mut notification = Notification::new().summary("Latest News")
+    ///                                           .body("Bayern Dortmund 3:2")
+    ///                                           .show()
+    ///                                           .unwrap();
+    ///
+    /// std::thread::sleep_ms(1_500);
+    ///
+    /// notification.summary("Latest News (Correction)")
+    ///             .body("Bayern Dortmund 3:3");
+    ///
+    /// notification.update().unwrap();
+    /// ```
+    /// Watch out for different implementations of the
+    /// notification server! On plasma5 for instance, you should also change the appname, so the old
+    /// message is really replaced and not just amended. Xfce behaves well, all others have not
+    /// been tested by the developer.
+    ///
+    /// Returns the id the server assigned, which is also stored in the handle.
+    /// Servers may hand out a new id, e.g. if the original notification expired in the meantime.
+    pub fn update(&mut self) -> Result<u32> {
+        match *self.inner_mut() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref mut inner) => inner.update(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref mut inner) => block_on(inner.update()),
+        }
+    }
+
+    /// Shows the notification again after it was closed, e.g. because the battery is still low.
+    ///
+    /// [`update()`](#method.update) is meant for notifications that are still visible,
+    /// this resends the stored notification as well, replacing the id the handle had last,
+    /// but also starts over with everything the handle noticed about the close:
+    /// [`poll_event()`](#method.poll_event) reports events again and [`close_on_drop`](#method.close_on_drop) is armed again if it was set.
+    ///
+    /// Returns the id the server assigned, servers may hand out a new one for a notification that is gone.
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # use std::time::Duration;
+    /// # fn battery_low() -> bool { true }
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let mut handle = Notification::new().summary("Battery low").show()?;
+    /// while handle.wait_for_closed(Duration::from_secs(300)).is_ok() && battery_low() {
+    ///     handle.reshow()?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reshow(&mut self) -> Result<u32> {
+        let id = match *self.inner_mut() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref mut inner) => inner.reshow(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref mut inner) => block_on(inner.reshow()),
+        }?;
+        *self.close_on_drop.get_mut() = self.wants_close_on_drop;
+        Ok(id)
+    }
+
+    /// The activation token the server sent along with the last invoked action.
+    ///
+    /// Newer servers emit `ActivationToken` right before `ActionInvoked`,
+    /// pass it to `xdg_activation_v1` (or set `XDG_ACTIVATION_TOKEN`) to raise your window under Wayland.
+    /// Any other event clears it again.
+    ///
+    /// Should the token arrive after the action, the action is delivered once the token is there,
+    /// an action without any token is delayed by a few milliseconds.
+    /// Action callbacks get the token through [`ActionListener::on_action_with_token`].
+    ///
+    /// ```no_run
+    /// # use notify_rust::{Event, Notification};
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let handle = Notification::new()
+    ///     .summary("New message")
+    ///     .action("default", "Open")
+    ///     .show()?;
+    /// if let Event::ActionInvoked(_) = handle.wait()? {
+    ///     if let Some(token) = handle.last_activation_token() {
+    ///         std::env::set_var("XDG_ACTIVATION_TOKEN", token);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn last_activation_token(&self) -> Option<String> {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.activation_token.get(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => inner.activation_token.get(),
+        }
+    }
+
+    /// Changes the notification in place and sends it again, without replaying its sound.
+    ///
+    /// [`update()`](#method.update) resends everything, including [`Hint::SoundName`](crate::Hint::SoundName)
+    /// and [`Hint::SoundFile`](crate::Hint::SoundFile), so the sound plays on every progress step.
+    /// These hints are left out here unless you opt in with [`replay_sounds(true)`](#method.replay_sounds),
+    /// the handle keeps them for later calls to `update()` though.
+    ///
+    /// ```no_run
+    /// # use notify_rust::Notification;
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let mut handle = Notification::new()
+    ///     .summary("Downloading")
+    ///     .sound_name("message-new-instant")
+    ///     .show()?;
+    /// // ... later, quietly
+    /// handle.update_with(|notification| {
+    ///     notification.summary("Download finished").body("ubuntu.iso");
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_with(&mut self, change: impl FnOnce(&mut Notification)) -> Result<u32> {
+        change(self);
+        if self.replay_sounds {
+            return self.update();
+        }
+        let kept = Notification::clone(self);
+        self.remove_one_shot_hints();
+        let result = self.update();
+        **self = kept;
+        result
+    }
+
+    /// Sends the sound hints again on [`update_with()`](#method.update_with) and its shortcuts.
+    pub fn replay_sounds(&mut self, replay: bool) -> &mut NotificationHandle {
+        self.replay_sounds = replay;
+        self
+    }
+
+    /// Changes only the summary, see [`update_with()`](#method.update_with).
+    pub fn set_summary(&mut self, summary: &str) -> Result<u32> {
+        self.update_with(|notification| {
+            notification.summary(summary);
+        })
+    }
+
+    /// Changes only the body, see [`update_with()`](#method.update_with).
+    pub fn set_body(&mut self, body: &str) -> Result<u32> {
+        self.update_with(|notification| {
+            notification.body(body);
+        })
+    }
+
+    /// Changes only the `value` hint, which many servers render as a progress bar,
+    /// see [`update_with()`](#method.update_with).
+    pub fn set_value(&mut self, value: i32) -> Result<u32> {
+        self.update_with(|notification| {
+            notification.hint(crate::Hint::CustomInt("value".into(), value));
+        })
+    }
+
+    /// The connection this handle talks to the server through.
+    ///
+    /// ```no_run
+    /// # use notify_rust::{get_capabilities_on, Notification};
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let handle = Notification::new().summary("Hello").show()?;
+    /// let capabilities = get_capabilities_on(handle.connection())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connection(&self) -> ConnectionRef<'_> {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => ConnectionRef::Dbus(&inner.connection),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => ConnectionRef::Zbus(&inner.connection),
+        }
+    }
+
+    /// Returns the Handle's id.
+    pub fn id(&self) -> u32 {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.id,
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => inner.id,
+        }
+    }
+
+    /// The last send replaced a notification and the server kept its id.
+    ///
+    /// `false` if nothing was to be replaced, or the server showed a new notification with a new id instead,
+    /// which [`id()`](#method.id) follows.
+    /// See [`Notification::require_stable_id()`] to fail in that case.
+    pub fn was_replaced(&self) -> bool {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => inner.replaced,
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => inner.replaced,
+        }
+    }
+}
+
+/// Required for `DerefMut`
+impl Deref for NotificationHandle {
+    type Target = Notification;
+
+    fn deref(&self) -> &Notification {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => &inner.notification,
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => &inner.notification,
+        }
+    }
+}
+
+/// Allow you to easily modify notification properties
+impl DerefMut for NotificationHandle {
+    fn deref_mut(&mut self) -> &mut Notification {
+        match *self.inner_mut() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref mut inner) => inner.notification_mut(),
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref mut inner) => &mut inner.notification,
+        }
+    }
+}
+
+impl PartialEq for NotificationHandle {
+    fn eq(&self, other: &NotificationHandle) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for NotificationHandle {}
+
+/// Hashes like the bare id, as `Borrow<u32>` requires.
+impl Hash for NotificationHandle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+impl Borrow<u32> for NotificationHandle {
+    fn borrow(&self) -> &u32 {
+        match *self.inner() {
+            #[cfg(feature = "dbus")]
+            NotificationHandleInner::Dbus(ref inner) => &inner.id,
+            #[cfg(feature = "zbus")]
+            NotificationHandleInner::Zbus(ref inner) => &inner.id,
+        }
+    }
+}
+
+#[cfg(feature = "dbus")]
+impl From<dbus_rs::DbusNotificationHandle> for NotificationHandleInner {
+    fn from(handle: dbus_rs::DbusNotificationHandle) -> NotificationHandleInner {
+        NotificationHandleInner::Dbus(handle)
+    }
+}
+
+#[cfg(feature = "zbus")]
+impl From<zbus_rs::ZbusNotificationHandle> for NotificationHandleInner {
+    fn from(handle: zbus_rs::ZbusNotificationHandle) -> NotificationHandleInner {
+        NotificationHandleInner::Zbus(handle)
+    }
+}
+
+impl Drop for NotificationHandle {
+    fn drop(&mut self) {
+        if *self.close_on_drop.get_mut() && self.inner.is_some() {
+            let _ = self.close_ref();
+        }
+        #[cfg(feature = "dbus")]
+        if let Some(NotificationHandleInner::Dbus(inner)) = self.inner.take() {
+            inner.recycle();
+        }
+    }
+}
+
+#[cfg(feature = "dbus")]
+impl From<dbus_rs::DbusNotificationHandle> for NotificationHandle {
+    fn from(handle: dbus_rs::DbusNotificationHandle) -> NotificationHandle {
+        NotificationHandle {
+            inner: Some(handle.into()),
+            close_on_drop: AtomicBool::new(false),
+            wants_close_on_drop: false,
+            replay_sounds: false,
+        }
+    }
+}
+
+#[cfg(feature = "zbus")]
+impl From<zbus_rs::ZbusNotificationHandle> for NotificationHandle {
+    fn from(handle: zbus_rs::ZbusNotificationHandle) -> NotificationHandle {
+        NotificationHandle {
+            inner: Some(handle.into()),
+            close_on_drop: AtomicBool::new(false),
+            wants_close_on_drop: false,
+            replay_sounds: false,
+        }
+    }
 }
 
 // here be public functions
@@ -310,108 +1289,513 @@ pub enum DbusStack {
     Zbus,
 }
 
+/// Environment variable that switches to dbus-rs when both backends are compiled in, zbus is used unless it is set.
+///
+/// Only whether it is set counts, not its value.
 #[cfg(all(feature = "dbus", feature = "zbus"))]
-const DBUS_SWITCH_VAR: &str = "DBUSRS";
+pub const DBUS_SWITCH_VAR: &str = "DBUSRS";
 
 #[cfg(all(feature = "zbus", not(feature = "dbus")))]
 pub(crate) fn show_notification(notification: &Notification) -> Result<NotificationHandle> {
     block_on(zbus_rs::connect_and_send_notification(notification)).map(Into::into)
 }
 
-#[cfg(all(feature = "async", feature = "zbus"))]
-pub(crate) async fn show_notification_async(
-    notification: &Notification,
-) -> Result<NotificationHandle> {
-    zbus_rs::connect_and_send_notification(notification)
-        .await
-        .map(Into::into)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub(crate) async fn show_notification_async(
+    notification: &Notification,
+) -> Result<NotificationHandle> {
+    zbus_rs::connect_shared_and_send_notification(notification)
+        .await
+        .map(Into::into)
+}
+
+#[cfg(feature = "zbus")]
+pub(crate) async fn show_notification_on(
+    notification: &Notification,
+    connection: &zbus::Connection,
+) -> Result<NotificationHandle> {
+    let bus = notification.bus.clone();
+    zbus_rs::send_notification_at_bus(notification, connection.clone(), bus)
+        .await
+        .map(Into::into)
+}
+
+/// Sends all `notifications` at once and returns their ids in the same order.
+///
+/// Unlike calling [`Notification::show_async()`] for each of them, this doesn't wait for one reply
+/// before sending the next notification, a burst of them costs about one round trip instead of one each.
+/// A notification the server rejects only fails its own entry, the others are shown regardless.
+/// The outer error means the batch could not be sent at all, some of the notifications may still have been shown.
+///
+/// The notifications are sent to the bus each of them picks, see [`Notification::on_bus()`], one batch per bus.
+/// Those for the session bus go through the connection shared with `show_async()`.
+/// There are no handles for them, use [`subscribe_all_async`] to follow what happens to them.
+///
+/// ```no_run
+/// # async fn _doc() -> notify_rust::error::Result<()> {
+/// # use notify_rust::Notification;
+/// let mails = ["Lunch?", "Re: Lunch?", "Invoice #42"]
+///     .iter()
+///     .map(|subject| Notification::new().summary("New mail").body(subject).finalize())
+///     .collect::<Vec<_>>();
+/// for (mail, id) in mails.iter().zip(notify_rust::send_all(&mails).await?) {
+///     match id {
+///         Ok(id) => println!("{} shown as {}", mail.body, id),
+///         Err(error) => println!("{} rejected: {}", mail.body, error),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn send_all(notifications: &[Notification]) -> Result<Vec<Result<u32>>> {
+    zbus_rs::send_all_shared(notifications).await
+}
+
+/// Drops the session connection that [`Notification::show_async()`] shares between notifications.
+///
+/// The next call connects again. Handles keep the connection they were created with,
+/// so it only goes away once they are dropped as well.
+/// There is no need to call this once a connection breaks, `show_async()` reconnects by itself.
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub fn reset_connection() {
+    zbus_rs::reset_connection();
+}
+
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub(crate) async fn show_notification_async_at_bus(
+    notification: &Notification,
+    bus: NotificationBus,
+) -> Result<NotificationHandle> {
+    zbus_rs::connect_and_send_notification_at_bus(notification, bus)
+        .await
+        .map(Into::into)
+}
+
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+pub(crate) fn show_notification(notification: &Notification) -> Result<NotificationHandle> {
+    dbus_rs::connect_and_send_notification(notification).map(Into::into)
+}
+
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+pub(crate) fn show_notification(notification: &Notification) -> Result<NotificationHandle> {
+    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        dbus_rs::connect_and_send_notification(notification).map(Into::into)
+    } else {
+        block_on(zbus_rs::connect_and_send_notification(notification)).map(Into::into)
+    }
+}
+
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+pub(crate) fn show_notification_nowait(notification: &Notification) -> Result<()> {
+    block_on(zbus_rs::send_notification_nowait(notification))
+}
+
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+pub(crate) fn show_notification_nowait(notification: &Notification) -> Result<()> {
+    dbus_rs::send_notification_nowait(notification)
+}
+
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+pub(crate) fn show_notification_nowait(notification: &Notification) -> Result<()> {
+    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        dbus_rs::send_notification_nowait(notification)
+    } else {
+        block_on(zbus_rs::send_notification_nowait(notification))
+    }
+}
+
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub(crate) async fn show_notification_nowait_async(notification: &Notification) -> Result<()> {
+    zbus_rs::send_notification_nowait(notification).await
+}
+
+/// Get the currently used [`DbusStack`]
+///
+/// (zbus only)
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+pub fn dbus_stack() -> Option<DbusStack> {
+    Some(DbusStack::Zbus)
+}
+
+/// Get the currently used [`DbusStack`]
+///
+/// (dbus-rs only)
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+pub fn dbus_stack() -> Option<DbusStack> {
+    Some(DbusStack::Dbus)
+}
+
+/// Get the currently used [`DbusStack`]
+///
+/// both dbus-rs and zbus, switch via [`DBUS_SWITCH_VAR`]
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+pub fn dbus_stack() -> Option<DbusStack> {
+    Some(if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        DbusStack::Dbus
+    } else {
+        DbusStack::Zbus
+    })
+}
+
+/// Get the currently used [`DbusStack`]
+///
+/// neither zbus nor dbus-rs are configured
+#[cfg(all(not(feature = "dbus"), not(feature = "zbus")))]
+pub fn dbus_stack() -> Option<DbusStack> {
+    None
+}
+
+/// Calls `handler` with the id and [`Event`] of every notification on the bus, no matter who sent it.
+///
+/// Runs in a background thread until [`Subscription::stop`] is called or the subscription is dropped.
+///
+/// ```no_run
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// let subscription = notify_rust::subscribe_all(|id, event| println!("{}: {:?}", id, event))?;
+/// // ... later
+/// subscription.stop();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (zbus only)
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+pub fn subscribe_all(handler: impl FnMut(u32, Event) + Send + 'static) -> Result<Subscription> {
+    Subscription::zbus(handler)
+}
+
+/// Calls `handler` with the id and [`Event`] of every notification on the bus, no matter who sent it.
+///
+/// (dbus-rs only)
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+pub fn subscribe_all(handler: impl FnMut(u32, Event) + Send + 'static) -> Result<Subscription> {
+    Subscription::dbus(handler)
+}
+
+/// Calls `handler` with the id and [`Event`] of every notification on the bus, no matter who sent it.
+///
+/// both dbus-rs and zbus, switch via [`DBUS_SWITCH_VAR`]
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+pub fn subscribe_all(handler: impl FnMut(u32, Event) + Send + 'static) -> Result<Subscription> {
+    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        Subscription::dbus(handler)
+    } else {
+        Subscription::zbus(handler)
+    }
+}
+
+/// Async version of [`subscribe_all()`], a [`Stream`](futures_lite::Stream) of `(id, event)` pairs.
+///
+/// Dropping the stream unsubscribes again.
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn subscribe_all_async() -> Result<impl futures_lite::Stream<Item = (u32, Event)>> {
+    let connection = zbus_rs::session_connection().await?;
+    zbus_rs::all_events(&connection).await
+}
+
+/// Closes the notification with this `id`, e.g. one shown by an earlier run.
+///
+/// Unlike [`NotificationHandle::close`] this needs no handle, just the id.
+/// Fails with [`ErrorKind::NotFound`] if the server has no such notification (anymore),
+/// which is also the case once it was closed already.
+///
+/// ```no_run
+/// # use notify_rust::error::ErrorKind;
+/// # fn _doc(saved_id: u32) -> notify_rust::error::Result<()> {
+/// match notify_rust::close_notification(saved_id) {
+///     Err(error) if matches!(error.kind(), ErrorKind::NotFound(_)) => {} // gone already
+///     closed => closed?,
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn close_notification(id: u32) -> Result<()> {
+    close_notification_at(id, Default::default())
+}
+
+/// Like [`close_notification()`], but at the bus a notification built with `Notification::at_bus(sub_bus)` was sent to.
+///
+/// This is for testing purposes only and will not work with actual implementations.
+pub fn close_notification_at_bus(id: u32, sub_bus: &str) -> Result<()> {
+    close_notification_at(id, custom_bus(sub_bus)?)
+}
+
+/// Like [`close_notification()`], but through an existing connection, e.g. [`NotificationHandle::connection`].
+///
+/// Some servers answer an unknown id with success, this can only report [`ErrorKind::NotFound`] for those that don't.
+pub fn close_notification_on<'a>(connection: impl Into<ConnectionRef<'a>>, id: u32) -> Result<()> {
+    match connection.into() {
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => {
+            dbus_rs::close_notification_on(connection, Default::default(), id)
+        }
+        #[cfg(feature = "zbus")]
+        ConnectionRef::Zbus(connection) => block_on(zbus_rs::close_notification_on(
+            connection,
+            Default::default(),
+            id,
+        )),
+    }
+    .map_err(|error| not_found(error, id))
+}
+
+/// Async version of [`close_notification()`].
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn close_notification_async(id: u32) -> Result<()> {
+    zbus_rs::close_notification(id, Default::default())
+        .await
+        .map_err(|error| not_found(error, id))
+}
+
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+fn close_notification_at(id: u32, bus: NotificationBus) -> Result<()> {
+    block_on(zbus_rs::close_notification(id, bus)).map_err(|error| not_found(error, id))
+}
+
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+fn close_notification_at(id: u32, bus: NotificationBus) -> Result<()> {
+    dbus_rs::close_notification(id, bus).map_err(|error| not_found(error, id))
+}
+
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+fn close_notification_at(id: u32, bus: NotificationBus) -> Result<()> {
+    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        dbus_rs::close_notification(id, bus)
+    } else {
+        block_on(zbus_rs::close_notification(id, bus))
+    }
+    .map_err(|error| not_found(error, id))
+}
+
+/// The server answers `CloseNotification` with an error of its own if it doesn't know the notification,
+/// any other error is passed on as it is.
+fn not_found(error: Error, id: u32) -> Error {
+    // the bus said no, or the connection broke, neither means the id is unknown
+    if matches!(error.kind(), ErrorKind::PermissionDenied(_)) || error.is_server_missing() {
+        return error;
+    }
+    #[cfg(feature = "zbus")]
+    if error.is_disconnected() {
+        return error;
+    }
+    if error.is_unknown_id() {
+        ErrorKind::NotFound(id).into()
+    } else {
+        error
+    }
+}
+
+/// Calls a method the specification doesn't know, e.g. dunst's `org.dunstproject.cmd0` interface.
+///
+/// This is an escape hatch: `member` of `interface` is called on the object of the notification server
+/// with `args` as they are, through the connection the other calls of this thread use.
+/// Nothing checks that the server implements it.
+///
+/// ```no_run
+/// # use dbus::arg::messageitem::MessageItem;
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// let history = notify_rust::call_raw_dbus("org.dunstproject.cmd0", "NotificationListHistory", &[])?;
+/// notify_rust::call_raw_dbus(
+///     "org.freedesktop.DBus.Properties",
+///     "Set",
+///     &[
+///         MessageItem::Str("org.dunstproject.cmd0".into()),
+///         MessageItem::Str("paused".into()),
+///         MessageItem::Variant(Box::new(MessageItem::Bool(true))),
+///     ],
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (dbus-rs only)
+#[cfg(feature = "dbus")]
+pub fn call_raw_dbus(
+    interface: &str,
+    member: &str,
+    args: &[dbus::arg::messageitem::MessageItem],
+) -> Result<Vec<dbus::arg::messageitem::MessageItem>> {
+    dbus_rs::call_raw(interface, member, args)
+}
+
+/// Like `call_raw_dbus()`, but through zbus, with `args` as a tuple and one value per argument of the reply.
+///
+/// ```no_run
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// notify_rust::call_raw_zbus(
+///     "org.freedesktop.DBus.Properties",
+///     "Set",
+///     &("org.dunstproject.cmd0", "paused", zbus::zvariant::Value::from(true)),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (zbus only)
+#[cfg(feature = "zbus")]
+pub fn call_raw_zbus<B>(
+    interface: &str,
+    member: &str,
+    args: &B,
+) -> Result<Vec<zvariant::OwnedValue>>
+where
+    B: serde::Serialize + zvariant::DynamicType,
+{
+    block_on(zbus_rs::call_raw(interface, member, args))
+}
+
+/// Get list of all capabilities of the running notification server.
+pub fn get_capabilities() -> Result<Vec<String>> {
+    capabilities_at(&BusSelection::Session, Default::default())
+}
+
+/// Like [`get_capabilities()`], but with the strings parsed into [`Capability`]s.
+///
+/// Collect them into a [`CapabilitySet`](crate::CapabilitySet) to ask for single ones.
+pub fn server_capabilities() -> Result<Vec<Capability>> {
+    Ok(get_capabilities()?
+        .into_iter()
+        .map(Capability::from)
+        .collect())
+}
+
+/// Like [`get_capabilities()`], but asks the server at `de.hoodie.Notification.<sub_bus>`.
+///
+/// This is for testing purposes only and will not work with actual implementations.
+pub fn get_capabilities_at_bus(sub_bus: &str) -> Result<Vec<String>> {
+    capabilities_at(&BusSelection::Session, custom_bus(sub_bus)?)
+}
+
+/// Like [`get_capabilities()`], but asks the server on `bus`, e.g. the one [`Notification::on_bus()`] sends to.
+pub fn get_capabilities_via(bus: &BusSelection) -> Result<Vec<String>> {
+    capabilities_at(bus, Default::default())
 }
 
-#[cfg(all(feature = "async", feature = "zbus"))]
-pub(crate) async fn show_notification_async_at_bus(
-    notification: &Notification,
-    bus: NotificationBus,
-) -> Result<NotificationHandle> {
-    zbus_rs::connect_and_send_notification_at_bus(notification, bus)
-        .await
-        .map(Into::into)
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+fn capabilities_at(selection: &BusSelection, bus: NotificationBus) -> Result<Vec<String>> {
+    block_on(zbus_rs::get_capabilities_at_bus(selection, bus))
 }
 
 #[cfg(all(feature = "dbus", not(feature = "zbus")))]
-pub(crate) fn show_notification(notification: &Notification) -> Result<NotificationHandle> {
-    dbus_rs::connect_and_send_notification(notification).map(Into::into)
+fn capabilities_at(selection: &BusSelection, bus: NotificationBus) -> Result<Vec<String>> {
+    dbus_rs::get_capabilities_at_bus(selection, bus)
 }
 
 #[cfg(all(feature = "dbus", feature = "zbus"))]
-pub(crate) fn show_notification(notification: &Notification) -> Result<NotificationHandle> {
+fn capabilities_at(selection: &BusSelection, bus: NotificationBus) -> Result<Vec<String>> {
     if std::env::var(DBUS_SWITCH_VAR).is_ok() {
-        dbus_rs::connect_and_send_notification(notification).map(Into::into)
+        dbus_rs::get_capabilities_at_bus(selection, bus)
     } else {
-        block_on(zbus_rs::connect_and_send_notification(notification)).map(Into::into)
+        block_on(zbus_rs::get_capabilities_at_bus(selection, bus))
     }
 }
 
-/// Get the currently used [`DbusStack`]
+/// Async version of [`get_capabilities()`], asks through the connection [`Notification::show_async()`] uses.
+///
+/// This always asks the server, see [`capabilities_cached()`] to ask only once.
 ///
 /// (zbus only)
-#[cfg(all(feature = "zbus", not(feature = "dbus")))]
-pub fn dbus_stack() -> Option<DbusStack> {
-    Some(DbusStack::Zbus)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn get_capabilities_async() -> Result<Vec<String>> {
+    zbus_rs::get_capabilities_shared().await
 }
 
-/// Get the currently used [`DbusStack`]
+/// Like [`get_capabilities_async()`], but returns what the same server answered before.
 ///
-/// (dbus-rs only)
-#[cfg(all(feature = "dbus", not(feature = "zbus")))]
-pub fn dbus_stack() -> Option<DbusStack> {
-    Some(DbusStack::Dbus)
-}
-
-/// Get the currently used [`DbusStack`]
+/// The cache belongs to the unique name of the server that answered,
+/// once another server takes over the bus (e.g. after a restart) the next call asks again.
+/// Anything else, like a server changing its capabilities at runtime, is not noticed,
+/// call [`get_capabilities_async()`] if you need to be sure.
 ///
-/// both dbus-rs and zbus, switch via `$ZBUS_NOTIFICATION`
-#[cfg(all(feature = "dbus", feature = "zbus"))]
-pub fn dbus_stack() -> Option<DbusStack> {
-    Some(if std::env::var(DBUS_SWITCH_VAR).is_ok() {
-        DbusStack::Dbus
-    } else {
-        DbusStack::Zbus
-    })
+/// ```no_run
+/// # async fn _doc() -> notify_rust::error::Result<()> {
+/// # use notify_rust::Notification;
+/// let mut notification = Notification::new();
+/// notification.summary("Build finished").body("<b>all</b> tests passed");
+/// // cheap enough to do before every notification
+/// notification.adapt_to(&notify_rust::capabilities_cached().await?);
+/// notification.show_async().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn capabilities_cached() -> Result<Vec<String>> {
+    zbus_rs::capabilities_cached().await
 }
 
-/// Get the currently used [`DbusStack`]
-///
-/// neither zbus nor dbus-rs are configured
-#[cfg(all(not(feature = "dbus"), not(feature = "zbus")))]
-pub fn dbus_stack() -> Option<DbusStack> {
-    None
+/// Like [`get_capabilities()`], but asks through an existing connection, e.g. [`NotificationHandle::connection`].
+pub fn get_capabilities_on<'a>(connection: impl Into<ConnectionRef<'a>>) -> Result<Vec<String>> {
+    match connection.into() {
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => dbus_rs::get_capabilities_on(connection),
+        #[cfg(feature = "zbus")]
+        ConnectionRef::Zbus(connection) => {
+            block_on(zbus_rs::get_capabilities_on(connection, Default::default()))
+        }
+    }
 }
 
-/// Get list of all capabilities of the running notification server.
-///
-/// (zbus only)
-#[cfg(all(feature = "zbus", not(feature = "dbus")))]
-pub fn get_capabilities() -> Result<Vec<String>> {
-    block_on(zbus_rs::get_capabilities())
+/// Like [`get_server_information()`], but asks through an existing connection, e.g. [`NotificationHandle::connection`].
+pub fn get_server_information_on<'a>(
+    connection: impl Into<ConnectionRef<'a>>,
+) -> Result<ServerInformation> {
+    match connection.into() {
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => dbus_rs::get_server_information_on(connection),
+        #[cfg(feature = "zbus")]
+        ConnectionRef::Zbus(connection) => block_on(zbus_rs::get_server_information_on(
+            connection,
+            Default::default(),
+        )),
+    }
 }
 
-/// Get list of all capabilities of the running notification server.
+/// Like [`get_capabilities_on()`], but fails with [`ErrorKind::ReplyTimeout`] unless the server answers within `timeout`.
 ///
-/// (dbus-rs only)
-#[cfg(all(feature = "dbus", not(feature = "zbus")))]
-pub fn get_capabilities() -> Result<Vec<String>> {
-    dbus_rs::get_capabilities()
+/// The other queries give the server 2 seconds on dbus-rs, zbus waits as long as its connection does.
+pub fn get_capabilities_within<'a>(
+    connection: impl Into<ConnectionRef<'a>>,
+    timeout: Duration,
+) -> Result<Vec<String>> {
+    match connection.into() {
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => {
+            dbus_rs::get_capabilities_within(connection, Default::default(), timeout)
+        }
+        #[cfg(feature = "zbus")]
+        ConnectionRef::Zbus(connection) => block_on(zbus_rs::reply_within(
+            zbus_rs::get_capabilities_on(connection, Default::default()),
+            timeout,
+        )),
+    }
 }
 
-/// Get list of all capabilities of the running notification server.
-///
-/// both dbus-rs and zbus, switch via `$ZBUS_NOTIFICATION`
-#[cfg(all(feature = "dbus", feature = "zbus"))]
-pub fn get_capabilities() -> Result<Vec<String>> {
-    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
-        dbus_rs::get_capabilities()
-    } else {
-        block_on(zbus_rs::get_capabilities())
+/// Like [`get_server_information_on()`], but fails with [`ErrorKind::ReplyTimeout`] unless the server answers within `timeout`.
+pub fn get_server_information_within<'a>(
+    connection: impl Into<ConnectionRef<'a>>,
+    timeout: Duration,
+) -> Result<ServerInformation> {
+    match connection.into() {
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => {
+            dbus_rs::get_server_information_within(connection, Default::default(), timeout)
+        }
+        #[cfg(feature = "zbus")]
+        ConnectionRef::Zbus(connection) => block_on(zbus_rs::reply_within(
+            zbus_rs::get_server_information_on(connection, Default::default()),
+            timeout,
+        )),
     }
 }
 
@@ -419,39 +1803,58 @@ pub fn get_capabilities() -> Result<Vec<String>> {
 ///
 /// This struct contains `name`, `vendor`, `version` and `spec_version` of the notification server
 /// running.
-///
-/// (zbus only)
-#[cfg(all(feature = "zbus", not(feature = "dbus")))]
 pub fn get_server_information() -> Result<ServerInformation> {
-    block_on(zbus_rs::get_server_information())
+    server_information_at(&BusSelection::Session, Default::default())
 }
 
-/// Returns a struct containing `ServerInformation`.
+/// Like [`get_server_information()`], but asks the server at `de.hoodie.Notification.<sub_bus>`.
 ///
-/// This struct contains `name`, `vendor`, `version` and `spec_version` of the notification server
-/// running.
-///
-/// (dbus-rs only)
+/// This is for testing purposes only and will not work with actual implementations.
+pub fn get_server_information_at_bus(sub_bus: &str) -> Result<ServerInformation> {
+    server_information_at(&BusSelection::Session, custom_bus(sub_bus)?)
+}
+
+/// Like [`get_server_information()`], but asks the server on `bus`, e.g. the one [`Notification::on_bus()`] sends to.
+pub fn get_server_information_via(bus: &BusSelection) -> Result<ServerInformation> {
+    server_information_at(bus, Default::default())
+}
+
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+fn server_information_at(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<ServerInformation> {
+    block_on(zbus_rs::get_server_information_at_bus(selection, bus))
+}
+
 #[cfg(all(feature = "dbus", not(feature = "zbus")))]
-pub fn get_server_information() -> Result<ServerInformation> {
-    dbus_rs::get_server_information()
+fn server_information_at(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<ServerInformation> {
+    dbus_rs::get_server_information_at_bus(selection, bus)
 }
 
-/// Returns a struct containing `ServerInformation`.
-///
-/// This struct contains `name`, `vendor`, `version` and `spec_version` of the notification server
-/// running.
-///
-/// both dbus-rs and zbus, switch via `$ZBUS_NOTIFICATION`
 #[cfg(all(feature = "dbus", feature = "zbus"))]
-pub fn get_server_information() -> Result<ServerInformation> {
+fn server_information_at(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<ServerInformation> {
     if std::env::var(DBUS_SWITCH_VAR).is_ok() {
-        dbus_rs::get_server_information()
+        dbus_rs::get_server_information_at_bus(selection, bus)
     } else {
-        block_on(zbus_rs::get_server_information())
+        block_on(zbus_rs::get_server_information_at_bus(selection, bus))
     }
 }
 
+/// Async version of [`get_server_information()`], asks through the connection [`Notification::show_async()`] uses.
+///
+/// (zbus only)
+#[cfg(all(feature = "async", feature = "zbus"))]
+pub async fn get_server_information_async() -> Result<ServerInformation> {
+    zbus_rs::get_server_information_shared().await
+}
+
 /// Return value of `get_server_information()`.
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize))]
@@ -467,6 +1870,29 @@ pub struct ServerInformation {
     pub spec_version: String,
 }
 
+impl ServerInformation {
+    /// The `spec_version` as `(major, minor)`, to compare versions as numbers rather than strings.
+    ///
+    /// Returns `None` if the server reports something other than `major.minor`.
+    ///
+    /// ```no_run
+    /// # fn _doc() -> notify_rust::error::Result<()> {
+    /// let info = notify_rust::get_server_information()?;
+    /// if info.spec_version_parsed() >= Some((1, 2)) {
+    ///     // e.g. use the `image-data` hint of 1.2
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spec_version_parsed(&self) -> Option<(u16, u16)> {
+        let version = self.spec_version.parse::<Version>().ok()?;
+        Some((
+            u16::try_from(version.major).ok()?,
+            u16::try_from(version.minor).ok()?,
+        ))
+    }
+}
+
 // /// Strictly internal.
 // /// The NotificationServer implemented here exposes a "Stop" function.
 // /// stops the notification server
@@ -506,7 +1932,8 @@ where
 /// Listens for the `ActionInvoked(UInt32, String)` Signal.
 ///
 /// No need to use this, check out [`NotificationHandle::wait_for_action`]
-/// both dbus-rs and zbus, switch via `$ZBUS_NOTIFICATION`
+///
+/// both dbus-rs and zbus, switch via [`DBUS_SWITCH_VAR`]
 #[cfg(all(feature = "dbus", feature = "zbus"))]
 // #[deprecated(note="please use `NotificationHandle::wait_for_action`")]
 pub fn handle_action<F>(id: u32, func: F)
@@ -520,11 +1947,69 @@ where
     }
 }
 
+/// Like [`handle_action`], but returns without calling `func` once `stop` is stopped.
+///
+/// Fails if the session bus can't be reached, e.g. with [`ErrorKind::NoSessionBus`].
+///
+/// (xdg only)
+pub fn handle_action_until<F>(id: u32, stop: &StopHandle, func: F) -> Result<()>
+where
+    F: FnOnce(&ActionResponse),
+{
+    action_until(id, &BusSelection::Session, &stop.0, func)
+}
+
+/// Like [`handle_action`], but listens on `bus`, e.g. the one [`Notification::on_bus()`] sends to.
+///
+/// Fails if `bus` can't be reached, e.g. an address nobody listens on.
+///
+/// (xdg only)
+pub fn handle_action_via<F>(id: u32, bus: &BusSelection, func: F) -> Result<()>
+where
+    F: FnOnce(&ActionResponse),
+{
+    action_until(id, bus, &listener::Stop::default(), func)
+}
+
+#[cfg(all(feature = "zbus", not(feature = "dbus")))]
+fn action_until(
+    id: u32,
+    selection: &BusSelection,
+    stop: &listener::Stop,
+    func: impl ActionResponseHandler,
+) -> Result<()> {
+    block_on(zbus_rs::handle_action_until(id, selection, stop, func))
+}
+
+#[cfg(all(feature = "dbus", not(feature = "zbus")))]
+fn action_until(
+    id: u32,
+    selection: &BusSelection,
+    stop: &listener::Stop,
+    func: impl ActionResponseHandler,
+) -> Result<()> {
+    dbus_rs::handle_action_until(id, selection, stop, func)
+}
+
+#[cfg(all(feature = "dbus", feature = "zbus"))]
+fn action_until(
+    id: u32,
+    selection: &BusSelection,
+    stop: &listener::Stop,
+    func: impl ActionResponseHandler,
+) -> Result<()> {
+    if std::env::var(DBUS_SWITCH_VAR).is_ok() {
+        dbus_rs::handle_action_until(id, selection, stop, func)
+    } else {
+        block_on(zbus_rs::handle_action_until(id, selection, stop, func))
+    }
+}
+
 /// Reason passed to `NotificationClosed` Signal
 ///
 /// ## Specification
 /// As listed under [Table 8. `NotificationClosed` Parameters](https://specifications.freedesktop.org/notification-spec/latest/ar01s09.html#idm46350804042704)
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CloseReason {
     /// The notification expired
     Expired,
@@ -532,7 +2017,7 @@ pub enum CloseReason {
     Dismissed,
     /// The notification was closed by a call to `CloseNotification`
     CloseAction,
-    /// Undefined/Reserved reason
+    /// Undefined/Reserved reason, also any code outside the specification as sent by some servers
     Other(u32),
 }
 
@@ -547,7 +2032,57 @@ impl From<u32> for CloseReason {
     }
 }
 
-/// Helper Trait implemented by `Fn()`
+impl From<CloseReason> for u32 {
+    fn from(reason: CloseReason) -> Self {
+        match reason {
+            CloseReason::Expired => 1,
+            CloseReason::Dismissed => 2,
+            CloseReason::CloseAction => 3,
+            CloseReason::Other(other) => other,
+        }
+    }
+}
+
+/// Returned by [`NotificationHandle::wait_for_action_timeout`] if neither an action was invoked
+/// nor the notification was closed in time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WaitTimedOut;
+
+impl fmt::Display for WaitTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out waiting for an action")
+    }
+}
+
+impl std::error::Error for WaitTimedOut {}
+
+/// Returned by [`NotificationHandle::wait_for_closed`] if it ends without the notification being closed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WaitError {
+    /// The notification was not closed in time.
+    Elapsed,
+    /// The server left the bus, or the connection to it broke, nobody is left to close the notification.
+    ServerGone,
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaitError::Elapsed => write!(f, "timed out waiting for the notification to close"),
+            WaitError::ServerGone => {
+                write!(f, "the server went away before closing the notification")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
+
+/// Helper Trait implemented by every `FnOnce(&ActionResponse)`
+///
+/// That includes `FnMut` closures, `&mut` references to them and boxed closures like
+/// `Box<dyn FnMut(&ActionResponse)>`, so a handler can keep state without a `RefCell`.
+/// Pass `&mut handler` to keep using the same one afterwards.
 pub trait ActionResponseHandler {
     fn call(self, response: &ActionResponse);
 }
@@ -569,6 +2104,182 @@ pub enum ActionResponse<'a> {
 
     /// The Notification was closed.
     Closed(CloseReason),
+
+    /// The notification server left the bus, nothing is going to happen anymore.
+    ServerGone,
+}
+
+impl<'a> ActionResponse<'a> {
+    /// The key of the invoked action, borrowed from the response.
+    ///
+    /// `None` for [`ActionResponse::Closed`] and [`ActionResponse::ServerGone`], no action was invoked then.
+    /// Compare it to an [`ActionKey`] directly or use [`ActionKey::from`] to keep it.
+    pub fn key(&self) -> Option<&'a str> {
+        match *self {
+            ActionResponse::Custom(key) => Some(key),
+            ActionResponse::Closed(_) | ActionResponse::ServerGone => None,
+        }
+    }
+
+    /// The default action was invoked, see [`ActionKey::DEFAULT`].
+    pub fn is_default(&self) -> bool {
+        matches!(self, ActionResponse::Custom(key) if *key == ActionKey::DEFAULT)
+    }
+}
+
+/// Something that happened to a shown notification.
+///
+/// Unlike [`ActionResponse`] this owns its data, so it can be returned from futures and sent across threads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The user invoked the action with this identifier.
+    ActionInvoked(String),
+
+    /// The user replied inline with this text (KDE and GNOME extension).
+    Replied(String),
+
+    /// The notification was closed.
+    Closed(CloseReason),
+
+    /// The notification server left the bus, e.g. because it crashed,
+    /// or a restarted one took over its name and knows nothing of the notification.
+    ServerGone,
+
+    /// The wait was cancelled through a [`WaitCanceller`], the notification may still be shown.
+    #[cfg(all(feature = "async", feature = "zbus"))]
+    Cancelled,
+}
+
+impl Event {
+    /// Borrows this as the [`ActionResponse`] passed to handlers, replies have none.
+    pub fn as_response(&self) -> Option<ActionResponse<'_>> {
+        match self {
+            Event::ActionInvoked(action) => Some(ActionResponse::Custom(action)),
+            Event::Replied(_) => None,
+            Event::Closed(reason) => Some(ActionResponse::Closed(*reason)),
+            Event::ServerGone => Some(ActionResponse::ServerGone),
+            #[cfg(all(feature = "async", feature = "zbus"))]
+            Event::Cancelled => None,
+        }
+    }
+
+    /// Nothing follows this event.
+    pub(crate) fn is_final(&self) -> bool {
+        matches!(self, Event::Closed(_) | Event::ServerGone)
+    }
+}
+
+/// The `ActivationToken` sent along with the last event, shared by a handle and its subscriptions.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ActivationToken(Arc<Mutex<Option<String>>>);
+
+impl ActivationToken {
+    pub(crate) fn get(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Keeps the `pending` token if `event` is an invoked action, clears it otherwise.
+    pub(crate) fn record(&self, event: &Event, pending: Option<String>) {
+        let token = pending.filter(|_| matches!(event, Event::ActionInvoked(_)));
+        *self.0.lock().unwrap() = token;
+    }
+}
+
+/// How long an invoked action without token waits for an `ActivationToken` that arrives late.
+const LATE_TOKEN_WINDOW: Duration = Duration::from_millis(50);
+
+/// Pairs each invoked action with its activation token, in whichever order the two signals arrive.
+///
+/// Servers send the token first, an action that arrives alone is held back for [`LATE_TOKEN_WINDOW`].
+#[derive(Debug, Default)]
+pub(crate) struct TokenPairing {
+    token: Option<String>,
+    held: Option<(Event, Instant)>,
+    ready: std::collections::VecDeque<(Event, Option<String>)>,
+}
+
+impl TokenPairing {
+    /// An `ActivationToken` for the notification arrived.
+    pub(crate) fn token(&mut self, token: String) {
+        match self.held.take() {
+            Some((action, _)) => self.ready.push_back((action, Some(token))),
+            None => self.token = Some(token),
+        }
+    }
+
+    /// Any other event of the notification arrived.
+    pub(crate) fn event(&mut self, event: Event) {
+        if let Event::ActionInvoked(_) = event {
+            self.release();
+            match self.token.take() {
+                Some(token) => self.ready.push_back((event, Some(token))),
+                None => self.held = Some((event, Instant::now())),
+            }
+        } else {
+            self.release();
+            self.ready.push_back((event, None));
+        }
+    }
+
+    /// Stops waiting for the token of the held action, e.g. because nothing is going to arrive anymore.
+    pub(crate) fn release(&mut self) {
+        if let Some((action, _)) = self.held.take() {
+            self.ready.push_back((action, None));
+        }
+    }
+
+    /// The next event with its token, once it is no longer waiting for one.
+    pub(crate) fn next(&mut self) -> Option<(Event, Option<String>)> {
+        if self
+            .deadline()
+            .map_or(false, |deadline| deadline <= Instant::now())
+        {
+            self.release();
+        }
+        self.ready.pop_front()
+    }
+
+    /// When the held action gives up on its token.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.held
+            .as_ref()
+            .map(|&(_, since)| since + LATE_TOKEN_WINDOW)
+    }
+}
+
+/// Blocking iterator over the [`Event`]s of a notification, ends after [`Event::Closed`] or [`Event::ServerGone`].
+///
+/// Created by [`NotificationHandle::events`].
+#[derive(Debug)]
+pub struct Events<'a> {
+    handle: &'a NotificationHandle,
+    inner: EventsInner<'a>,
+}
+
+#[derive(Debug)]
+enum EventsInner<'a> {
+    #[cfg(feature = "dbus")]
+    Dbus(Box<dbus_rs::DbusEvents<'a>>),
+
+    #[cfg(feature = "zbus")]
+    Zbus(Box<zbus_rs::EventStream>, std::marker::PhantomData<&'a ()>),
+}
+
+impl Iterator for Events<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        let event = match self.inner {
+            #[cfg(feature = "dbus")]
+            EventsInner::Dbus(ref mut events) => events.next(),
+
+            #[cfg(feature = "zbus")]
+            EventsInner::Zbus(ref mut events, _) => block_on(futures_lite::StreamExt::next(events)),
+        }?;
+        self.handle.observe_event(&event);
+        Some(event)
+    }
 }
 
 impl<'a> From<&'a str> for ActionResponse<'a> {
@@ -581,25 +2292,222 @@ impl<'a> From<&'a str> for ActionResponse<'a> {
 ///
 /// This is implemented by `Fn()` and `Fn(CloseReason)`, so there is probably no good reason for you to manually implement this trait.
 /// Should you find one anyway, please notify me and I'll gladly remove this obviously redundant comment.
-pub trait CloseHandler<T> {
+///
+/// `R` is what the closure returns, [`ActionListener::on_close`] also takes closures returning a `Result`.
+pub trait CloseHandler<T, R = ()> {
     /// This is called with the [`CloseReason`].
-    fn call(&self, reason: CloseReason);
+    fn call(&self, reason: CloseReason) -> R;
 }
 
-impl<F> CloseHandler<CloseReason> for F
+impl<F, R> CloseHandler<CloseReason, R> for F
 where
-    F: Fn(CloseReason),
+    F: Fn(CloseReason) -> R,
 {
-    fn call(&self, reason: CloseReason) {
-        self(reason);
+    fn call(&self, reason: CloseReason) -> R {
+        self(reason)
     }
 }
 
-impl<F> CloseHandler<()> for F
+impl<F, R> CloseHandler<(), R> for F
 where
-    F: Fn(),
+    F: Fn() -> R,
 {
-    fn call(&self, _: CloseReason) {
-        self();
+    fn call(&self, _: CloseReason) -> R {
+        self()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_bus_name, ActionResponse, ActionResponseHandler, CloseReason, Event, NotificationBus,
+        ServerInformation, TokenPairing, LATE_TOKEN_WINDOW, NOTIFICATION_DEFAULT_BUS,
+    };
+    use crate::{ActionKey, Notification};
+
+    fn respond(handler: impl ActionResponseHandler, response: ActionResponse) {
+        handler.call(&response);
+    }
+
+    #[test]
+    fn tokens_pair_with_actions_in_either_order() {
+        let open = || Event::ActionInvoked("open".into());
+        let mut pairing = TokenPairing::default();
+        pairing.token("first".into());
+        pairing.event(open());
+        assert_eq!(pairing.next(), Some((open(), Some("first".into()))));
+
+        pairing.event(open());
+        assert_eq!(pairing.next(), None); // waits for the token
+        pairing.token("second".into());
+        assert_eq!(pairing.next(), Some((open(), Some("second".into()))));
+
+        // anything else ends the wait, the order is kept
+        pairing.event(open());
+        pairing.event(Event::Closed(CloseReason::Dismissed));
+        assert_eq!(pairing.next(), Some((open(), None)));
+        assert_eq!(
+            pairing.next(),
+            Some((Event::Closed(CloseReason::Dismissed), None))
+        );
+        assert_eq!(pairing.next(), None);
+    }
+
+    #[test]
+    fn a_missing_token_is_waited_for_briefly() {
+        let mut pairing = TokenPairing::default();
+        pairing.event(Event::ActionInvoked("open".into()));
+        assert!(pairing.held.is_some());
+        std::thread::sleep(LATE_TOKEN_WINDOW);
+        assert_eq!(
+            pairing.next(),
+            Some((Event::ActionInvoked("open".into()), None))
+        );
+    }
+
+    #[test]
+    fn bus_names_follow_the_naming_rules() {
+        let long = format!("a.{}", "b".repeat(253));
+        let too_long = format!("a.{}", "b".repeat(254));
+        for (name, valid) in [
+            (NOTIFICATION_DEFAULT_BUS, true),
+            ("org.freedesktop.Notifications", true),
+            ("de.hoodie.Notifications", true),
+            ("a.b", true),
+            ("_a.b-c.d9", true),
+            (long.as_str(), true),
+            (too_long.as_str(), false),
+            ("", false),
+            ("single", false),
+            ("a..b", false),
+            (".a.b", false),
+            ("a.b.", false),
+            ("a.1b", false),
+            ("1a.b", false),
+            ("a.b c", false),
+            ("a.ä", false),
+            ("a/b.c", false),
+            (":1.42", false),
+        ] {
+            assert_eq!(check_bus_name(name).is_ok(), valid, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn custom_buses_are_checked_when_built() {
+        for (sub_bus, name) in [
+            ("debug", Some("de.hoodie.Notification.debug")),
+            ("a/b", Some("de.hoodie.Notification.a.b")),
+            (
+                "with_underscore",
+                Some("de.hoodie.Notification.with_underscore"),
+            ),
+            ("", None),
+            ("not valid", None),
+            ("1st", None),
+            ("a..b", None),
+            ("trailing.", None),
+            ("/", None),
+        ] {
+            let bus = NotificationBus::custom(sub_bus);
+            assert_eq!(
+                bus.as_ref().ok().map(NotificationBus::as_str),
+                name,
+                "{:?}",
+                sub_bus
+            );
+            if let Ok(bus) = bus {
+                assert_eq!(bus.to_string(), name.unwrap());
+            }
+        }
+        let error = NotificationBus::custom("1st").unwrap_err();
+        assert_eq!(error.name(), "de.hoodie.Notification.1st");
+        assert!(
+            error.to_string().contains("starts with a digit"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn spec_versions_parse_as_numbers() {
+        let parsed = |spec_version: &str| {
+            ServerInformation {
+                name: String::new(),
+                vendor: String::new(),
+                version: String::new(),
+                spec_version: spec_version.into(),
+            }
+            .spec_version_parsed()
+        };
+        for (spec_version, expected) in [
+            ("1.2", Some((1, 2))),
+            ("1.3", Some((1, 3))),
+            ("1.10", Some((1, 10))),
+            ("2.0", Some((2, 0))),
+            ("1.2.1", Some((1, 2))),
+            ("1", None),
+            ("", None),
+            ("1.", None),
+            ("v1.2", None),
+            ("1.2-beta", None),
+            ("-1.2", None),
+            ("65536.0", None),
+            ("unknown", None),
+        ] {
+            assert_eq!(parsed(spec_version), expected, "{:?}", spec_version);
+        }
+        assert!(parsed("1.10") > parsed("1.2"));
+    }
+
+    #[test]
+    fn fn_mut_handlers_keep_state() {
+        let mut seen = Vec::new();
+        let mut record = |response: &ActionResponse| match response {
+            ActionResponse::Custom(action) => seen.push(action.to_string()),
+            ActionResponse::Closed(_) => seen.push("closed".into()),
+            ActionResponse::ServerGone => {}
+        };
+        respond(&mut record, ActionResponse::Custom("open"));
+        respond(&mut record, ActionResponse::Closed(CloseReason::Dismissed));
+
+        let mut count = 0;
+        let boxed: Box<dyn FnMut(&ActionResponse) + '_> = Box::new(|_| count += 1);
+        respond(boxed, ActionResponse::ServerGone);
+
+        assert_eq!(seen, ["open", "closed"]);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn default_action_is_detected_by_key_not_label() {
+        let notification = Notification::new()
+            .action("open", "default")
+            .action(ActionKey::DEFAULT, "Open")
+            .finalize();
+        assert_eq!(notification.actions, ["open", "default", "default", "Open"]);
+
+        let labelled_default = ActionResponse::Custom("open");
+        assert!(!labelled_default.is_default());
+        assert_eq!(labelled_default.key(), Some("open"));
+
+        let default = ActionResponse::Custom("default");
+        assert!(default.is_default());
+        assert_eq!(default.key(), Some(ActionKey::DEFAULT.as_str()));
+
+        assert!(!ActionResponse::Closed(CloseReason::Dismissed).is_default());
+        assert_eq!(ActionResponse::ServerGone.key(), None);
+    }
+
+    #[test]
+    fn close_reason_roundtrips_raw_codes() {
+        let sampled = (0..=16)
+            .chain((0..=u32::MAX).step_by(65_537))
+            .chain([u32::MAX]);
+        for raw in sampled {
+            assert_eq!(u32::from(CloseReason::from(raw)), raw);
+        }
+        assert_eq!(CloseReason::from(4), CloseReason::Other(4));
+        assert_eq!(CloseReason::from(1000), CloseReason::Other(1000));
     }
 }
@@ -0,0 +1,79 @@
+//! Finds the session bus, for both backends alike.
+//!
+//! Services started by `systemd --user` don't always get `DBUS_SESSION_BUS_ADDRESS`,
+//! but the bus still listens at `$XDG_RUNTIME_DIR/bus`.
+
+use std::{env, path::Path};
+
+use crate::error::{ErrorKind, Result};
+
+/// The address of the session bus.
+///
+/// `DBUS_SESSION_BUS_ADDRESS` if it is set, otherwise the socket in `XDG_RUNTIME_DIR` if there is one.
+/// Fails with [`ErrorKind::NoSessionBus`] listing the addresses that were tried.
+pub(crate) fn address() -> Result<String> {
+    discover(
+        env::var("DBUS_SESSION_BUS_ADDRESS").ok(),
+        env::var_os("XDG_RUNTIME_DIR").as_deref().map(Path::new),
+    )
+}
+
+fn discover(session_address: Option<String>, runtime_dir: Option<&Path>) -> Result<String> {
+    if let Some(address) = session_address.filter(|address| !address.is_empty()) {
+        return Ok(address);
+    }
+    let socket = runtime_dir
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.join("bus"));
+    let tried = socket
+        .iter()
+        .map(|socket| format!("unix:path={}", socket.display()))
+        .collect::<Vec<_>>();
+    match socket {
+        Some(ref socket) if socket.exists() => Ok(tried[0].clone()),
+        _ => Err(ErrorKind::NoSessionBus { tried }.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::discover;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn the_address_variable_wins() {
+        let address = discover(
+            Some("unix:path=/tmp/bus".into()),
+            Some(Path::new("/nowhere")),
+        );
+        assert_eq!(address.unwrap(), "unix:path=/tmp/bus");
+    }
+
+    #[test]
+    fn falls_back_to_the_runtime_dir() {
+        // anything called `bus` will do, the connection fails later if it is no socket
+        let runtime_dir =
+            std::env::temp_dir().join(format!("notify-rust-runtime-{}", std::process::id()));
+        std::fs::create_dir_all(runtime_dir.join("bus")).unwrap();
+        let address = discover(Some(String::new()), Some(&runtime_dir));
+        assert_eq!(
+            address.unwrap(),
+            format!("unix:path={}/bus", runtime_dir.display())
+        );
+    }
+
+    #[test]
+    fn no_bus_lists_what_was_tried() {
+        let error = discover(None, Some(Path::new("/nowhere"))).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::NoSessionBus { tried } if tried == &["unix:path=/nowhere/bus"]),
+            "{}",
+            error
+        );
+
+        let error = discover(None, None).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::NoSessionBus { tried } if tried.is_empty()));
+    }
+}
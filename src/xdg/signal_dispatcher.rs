@@ -0,0 +1,158 @@
+use dbus::Message;
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use super::{dbus_rs, listener::Stop, BusSelection, Event, NOTIFICATION_DEFAULT_BUS};
+use crate::{error::Result, notification::Notification};
+
+/// Where the events of a registered notification go.
+#[derive(Debug)]
+struct Route {
+    events: mpsc::Sender<Event>,
+    /// The unique name of the server that showed it, signals of anyone else are ignored.
+    server: Option<String>,
+}
+
+type Routes = Arc<Mutex<HashMap<u32, Route>>>;
+
+/// Receives the signals of many notifications on one connection and sends each its own [`Event`]s.
+///
+/// Waiting on [`NotificationHandle`](super::NotificationHandle)s means a connection and a pair of match rules per notification,
+/// keeping dozens of interactive notifications around that way may run into the connection limit of the bus.
+/// A dispatcher listens on a single connection to the session bus in a background thread instead,
+/// and forwards the events of every registered id to a channel.
+///
+/// An id is unregistered once its notification was closed or the receiver was dropped,
+/// all of them when the server leaves the bus, after sending them [`Event::ServerGone`].
+/// Ids can be registered at any time while the dispatcher runs.
+///
+/// ```no_run
+/// # use notify_rust::{Event, Notification, SignalDispatcher};
+/// # fn _doc() -> notify_rust::error::Result<()> {
+/// let dispatcher = SignalDispatcher::new()?;
+/// let mut downloads = Vec::new();
+/// for download in ["a.iso", "b.iso", "c.iso"] {
+///     downloads.push(dispatcher.show(
+///         Notification::new()
+///             .summary("Download finished")
+///             .body(download)
+///             .action("open", "Open"),
+///     )?);
+/// }
+/// for (id, events) in downloads {
+///     if let Ok(Event::ActionInvoked(action)) = events.recv() {
+///         println!("{} of {}", action, id);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Dropping the dispatcher stops it without waiting for the thread.
+///
+/// (dbus-rs only)
+#[derive(Debug)]
+pub struct SignalDispatcher {
+    routes: Routes,
+    thread: Option<thread::JoinHandle<()>>,
+    stop: Stop,
+}
+
+impl SignalDispatcher {
+    /// Connects to the session bus and starts listening.
+    pub fn new() -> Result<SignalDispatcher> {
+        let routes = Routes::default();
+        let stop = Stop::default();
+        let rules = dbus_rs::signal_rules(NOTIFICATION_DEFAULT_BUS, None).to_vec();
+        let thread = dbus_rs::spawn_listening(stop.clone(), rules, {
+            let routes = Arc::clone(&routes);
+            move |message| route(&routes, message)
+        })?;
+        Ok(SignalDispatcher {
+            routes,
+            thread: Some(thread),
+            stop,
+        })
+    }
+
+    /// Sends `notification` on the session bus and registers its id.
+    ///
+    /// No signal of the new notification can slip through before it is registered.
+    pub fn show(&self, notification: &Notification) -> Result<(u32, mpsc::Receiver<Event>)> {
+        // the dispatcher thread waits for the lock, signals arriving in the meantime are routed after
+        let mut routes = self.routes.lock().unwrap();
+        let (id, server) = dbus_rs::send_notification(notification, &BusSelection::Session)?;
+        let (events, receiver) = mpsc::channel();
+        routes.insert(id, Route { events, server });
+        Ok((id, receiver))
+    }
+
+    /// Sends the events of notification `id` to the returned receiver from now on, no matter which server emits them.
+    ///
+    /// Registering an id again replaces the earlier receiver, it gets no more events.
+    pub fn register(&self, id: u32) -> mpsc::Receiver<Event> {
+        let (events, receiver) = mpsc::channel();
+        let route = Route {
+            events,
+            server: None,
+        };
+        self.routes.lock().unwrap().insert(id, route);
+        receiver
+    }
+
+    /// Stops sending the events of notification `id`, `false` if it was not registered (anymore).
+    pub fn unregister(&self, id: u32) -> bool {
+        self.routes.lock().unwrap().remove(&id).is_some()
+    }
+
+    /// Notification `id` is registered and was not closed yet.
+    pub fn is_registered(&self, id: u32) -> bool {
+        self.routes.lock().unwrap().contains_key(&id)
+    }
+
+    /// Stops listening and waits for the thread to finish.
+    ///
+    /// The receivers of ids still registered are disconnected.
+    pub fn stop(mut self) {
+        self.stop.set();
+        if let Some(Err(panic)) = self.thread.take().map(thread::JoinHandle::join) {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+impl Drop for SignalDispatcher {
+    fn drop(&mut self) {
+        self.stop.set();
+    }
+}
+
+/// Sends the event in `message` to the notification it belongs to.
+fn route(routes: &Routes, message: &Message) {
+    let mut routes = routes.lock().unwrap();
+    if dbus_rs::server_gone(message, NOTIFICATION_DEFAULT_BUS) {
+        for (_, route) in routes.drain() {
+            let _ = route.events.send(Event::ServerGone);
+        }
+        return;
+    }
+    let (id, event) = match dbus_rs::parse_signal(message) {
+        Some(signal) => signal,
+        None => return,
+    };
+    let route = match routes.get(&id) {
+        Some(route) => route,
+        None => return,
+    };
+    if let Some(ref server) = route.server {
+        if message.sender().as_deref() != Some(server.as_str()) {
+            return;
+        }
+    }
+    let ended = event.is_final();
+    if route.events.send(event).is_err() || ended {
+        routes.remove(&id);
+    }
+}
@@ -0,0 +1,63 @@
+//! Timers on the runtime zbus runs on, tokio's with the `tokio` feature and async-io's otherwise.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Resolves after `duration`.
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(not(feature = "tokio"))]
+    async_io::Timer::after(duration).await;
+}
+
+/// Resolves at a deadline that can be moved while it is polled.
+#[derive(Debug)]
+pub(crate) struct Timer {
+    #[cfg(feature = "tokio")]
+    inner: Pin<Box<tokio::time::Sleep>>,
+
+    #[cfg(not(feature = "tokio"))]
+    inner: async_io::Timer,
+}
+
+impl Timer {
+    pub(crate) fn at(deadline: Instant) -> Timer {
+        Timer {
+            #[cfg(feature = "tokio")]
+            inner: Box::pin(tokio::time::sleep_until(deadline.into())),
+
+            #[cfg(not(feature = "tokio"))]
+            inner: async_io::Timer::at(deadline),
+        }
+    }
+
+    pub(crate) fn set_at(&mut self, deadline: Instant) {
+        #[cfg(feature = "tokio")]
+        self.inner.as_mut().reset(deadline.into());
+
+        #[cfg(not(feature = "tokio"))]
+        self.inner.set_at(deadline);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.inner).poll(cx).map(drop)
+    }
+}
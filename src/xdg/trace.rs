@@ -0,0 +1,86 @@
+//! Spans around `Notify` calls and events for received signals, nothing at all without the `tracing` feature.
+
+use super::Event;
+use crate::notification::Notification;
+
+#[cfg(all(feature = "tracing", feature = "zbus"))]
+pub(crate) use tracing::Instrument;
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::Span;
+
+/// Stands in for a span without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    #[cfg(feature = "dbus")]
+    pub(crate) fn entered(self) -> Span {
+        self
+    }
+}
+
+/// Leaves futures as they are without the `tracing` feature.
+#[cfg(all(not(feature = "tracing"), feature = "zbus"))]
+pub(crate) trait Instrument: Sized {
+    fn instrument(self, _span: Span) -> Self {
+        self
+    }
+}
+
+#[cfg(all(not(feature = "tracing"), feature = "zbus"))]
+impl<T> Instrument for T {}
+
+/// The span of the `Notify` call that sends `notification`, replacing `replaces_id` unless it is 0.
+///
+/// The `id` the server returns is filled in by [`record_id`].
+#[cfg(feature = "tracing")]
+pub(crate) fn notify_span(notification: &Notification, replaces_id: u32) -> Span {
+    tracing::debug_span!(
+        "notify",
+        appname = %notification.appname,
+        replaces_id,
+        id = tracing::field::Empty,
+        timeout = i32::from(notification.timeout),
+        hints = notification.effective_hints().count(),
+        image_bytes = image_bytes(notification),
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn notify_span(_notification: &Notification, _replaces_id: u32) -> Span {
+    Span
+}
+
+/// Fills in the id the server returned for the notification of `span`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_id(span: &Span, id: u32) {
+    #[cfg(feature = "tracing")]
+    span.record("id", id);
+}
+
+/// Reports a signal of the notification interface for notification `id`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn received(id: u32, event: &Event) {
+    #[cfg(feature = "tracing")]
+    match event {
+        Event::ActionInvoked(action) => tracing::debug!(id, action = %action, "ActionInvoked"),
+        Event::Closed(reason) => tracing::debug!(id, reason = ?reason, "NotificationClosed"),
+        Event::Replied(_) => tracing::debug!(id, "NotificationReplied"),
+        _ => {}
+    }
+}
+
+/// The size of the raw image data sent along, without the image keys repeated by `compat_image_hints()`.
+#[cfg(feature = "tracing")]
+fn image_bytes(notification: &Notification) -> usize {
+    notification
+        .effective_hints()
+        .map(|hint| match hint {
+            #[cfg(feature = "images")]
+            crate::Hint::ImageData(image) => image.byte_len(),
+            _ => 0,
+        })
+        .sum()
+}
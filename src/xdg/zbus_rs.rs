@@ -1,12 +1,123 @@
-use crate::{error::*, notification::Notification, xdg};
-use futures_lite::stream::StreamExt;
-use zbus::MatchRule;
+use crate::{error::*, hints::HintValue, notification::Notification, xdg};
+use futures_lite::stream::{Stream, StreamExt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    pin::Pin,
+    sync::{mpsc, Mutex},
+    task::{Context, Poll},
+    thread,
+};
+use zbus::{
+    zvariant::{self, OwnedValue},
+    MatchRule,
+};
 
-use super::{bus::NotificationBus, ActionResponse, ActionResponseHandler, CloseReason};
+use std::time::Duration;
+
+use super::{
+    bus::NotificationBus,
+    listener::Stop,
+    trace::{self, Instrument},
+    ActionResponseHandler, ActivationToken, BusSelection, CloseReason, Event, TokenPairing,
+    WaitError, WaitTimedOut,
+};
+
+pub use self::proxy::{NotificationsProxy, NotificationsProxyBlocking};
+
+// its own module, the generated signal types would clash with the crate's `ActivationToken`
+mod proxy {
+    use super::xdg;
+    use std::collections::BTreeMap;
+    use zbus::zvariant;
+
+    /// The `org.freedesktop.Notifications` interface, as described by the specification.
+    ///
+    /// The crate sends through this proxy as well, use it to call the server or receive its signals directly.
+    /// Built with [`NotificationsProxy::new`] it talks to the default `org.freedesktop.Notifications` bus.
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use futures_lite::StreamExt;
+    /// use notify_rust::{NotificationDispatcher, NotificationsProxy};
+    ///
+    /// // shares the connection with the notifications shown by `dispatcher`
+    /// let dispatcher = NotificationDispatcher::new()?;
+    /// let proxy = NotificationsProxy::new(dispatcher.connection()).await?;
+    /// println!("{:?}", proxy.get_capabilities().await?);
+    ///
+    /// let mut invoked = proxy.receive_action_invoked().await?;
+    /// let id = proxy
+    ///     .notify("app", 0, "", "summary", "body", &["default", "Open"], Default::default(), -1)
+    ///     .await?;
+    /// while let Some(signal) = invoked.next().await {
+    ///     let args = signal.args()?;
+    ///     if args.id == id {
+    ///         println!("invoked {}", args.action_key);
+    ///         break;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[zbus::proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    pub trait Notifications {
+        /// Shows a notification, replacing `replaces_id` unless it is 0, and returns its id.
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            replaces_id: u32,
+            app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: &[&str],
+            hints: BTreeMap<&str, zvariant::Value<'_>>,
+            expire_timeout: i32,
+        ) -> zbus::Result<u32>;
+
+        /// Closes notification `id`.
+        fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+        /// The optional features the server implements.
+        fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+        /// Name, vendor, version and spec version of the server.
+        fn get_server_information(&self) -> zbus::Result<xdg::ServerInformation>;
+
+        /// The action `action_key` of notification `id` was invoked.
+        #[zbus(signal)]
+        fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+        /// Notification `id` was closed, the reason is one of the specification's codes.
+        #[zbus(signal)]
+        fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+        /// Sent right before `ActionInvoked`, with a token to activate the invoking window.
+        #[zbus(signal)]
+        fn activation_token(&self, id: u32, activation_token: &str) -> zbus::Result<()>;
+    }
+}
+
+/// A proxy to `bus` that neither caches properties nor subscribes to anything.
+async fn notifications_proxy(
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+) -> zbus::Result<NotificationsProxy<'static>> {
+    NotificationsProxy::builder(connection)
+        .destination(bus.into_name())?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await
+}
 
 pub mod bus {
 
-    use crate::xdg::NOTIFICATION_DEFAULT_BUS;
+    use crate::error::InvalidBusName;
+    use crate::xdg::{check_bus_name, NOTIFICATION_DEFAULT_BUS};
 
     fn skip_first_slash(s: &str) -> &str {
         if let Some('/') = s.chars().next() {
@@ -16,6 +127,7 @@ pub mod bus {
         }
     }
 
+    use std::fmt;
     use std::path::PathBuf;
 
     type BusNameType = zbus::names::WellKnownName<'static>;
@@ -42,16 +154,29 @@ pub mod bus {
             .into()
         }
 
-        pub fn custom(custom_path: &str) -> Option<Self> {
-            let name =
-                zbus::names::WellKnownName::try_from(Self::namespaced_custom(custom_path)?).ok()?;
-            Some(Self(name))
+        pub fn custom(custom_path: &str) -> Result<Self, InvalidBusName> {
+            let name = Self::namespaced_custom(custom_path)
+                .ok_or_else(|| InvalidBusName::new(custom_path, "is no valid path"))?;
+            check_bus_name(&name)?;
+            zbus::names::WellKnownName::try_from(name.clone())
+                .map(Self)
+                .map_err(|error| InvalidBusName::new(&name, error.to_string()))
+        }
+
+        pub fn as_str(&self) -> &str {
+            self.0.as_str()
         }
 
         pub fn into_name(self) -> BusNameType {
             self.0
         }
     }
+
+    impl fmt::Display for NotificationBus {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
 }
 
 /// A handle to a shown notification.
@@ -62,6 +187,12 @@ pub struct ZbusNotificationHandle {
     pub(crate) id: u32,
     pub(crate) connection: zbus::Connection,
     pub(crate) notification: Notification,
+    pub(crate) activation_token: ActivationToken,
+    /// The unique name of the server that answered `Notify`, only its signals are listened to.
+    pub(crate) server: Option<String>,
+    /// The last send replaced a notification in place.
+    pub(crate) replaced: bool,
+    polled: Option<Box<EventStream>>,
 }
 
 impl ZbusNotificationHandle {
@@ -74,61 +205,264 @@ impl ZbusNotificationHandle {
             id,
             connection,
             notification,
+            activation_token: ActivationToken::default(),
+            server: None,
+            replaced: false,
+            polled: None,
         }
     }
 
-    pub async fn wait_for_action(self, invocation_closure: impl ActionResponseHandler) {
-        wait_for_action_signal(&self.connection, self.id, invocation_closure).await;
+    /// The bus name of the server the notification was sent to.
+    fn bus(&self) -> String {
+        self.notification.bus.clone().into_name().to_string()
+    }
+
+    pub fn into_parts(self) -> (u32, zbus::Connection, Notification) {
+        (self.id, self.connection, self.notification)
+    }
+
+    pub async fn wait_for_action(&self, invocation_closure: impl ActionResponseHandler) {
+        wait_for_action_signal(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+            invocation_closure,
+        )
+        .await;
     }
 
-    pub async fn close_fallible(self) -> Result<()> {
-        self.connection
-            .call_method(
-                Some(self.notification.bus.clone().into_name()),
-                xdg::NOTIFICATION_OBJECTPATH,
-                Some(xdg::NOTIFICATION_INTERFACE),
-                "CloseNotification",
-                &(self.id),
+    pub async fn wait_for_action_timeout(
+        &self,
+        timeout: Duration,
+        invocation_closure: impl ActionResponseHandler,
+    ) -> std::result::Result<(), WaitTimedOut> {
+        let action = async {
+            wait_for_action_signal(
+                &self.connection,
+                &self.bus(),
+                self.server.as_deref(),
+                self.id,
+                &self.activation_token,
+                invocation_closure,
             )
-            .await?;
-        Ok(())
+            .await;
+            Ok(())
+        };
+        let deadline = async {
+            xdg::timer::sleep(timeout).await;
+            Err(WaitTimedOut)
+        };
+        // dropping the action future also drops the closure, it can't be called after the deadline
+        futures_lite::future::or(action, deadline).await
+    }
+
+    pub async fn wait_for_event(&self) -> Result<Event> {
+        wait_for_event(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+        .await
     }
 
-    pub async fn close(self) {
-        self.close_fallible().await.unwrap();
+    /// Like `wait_for_event()`, but resolves to [`Event::Cancelled`] once `stop` is set.
+    ///
+    /// Either way the match rules are removed from the bus before this returns.
+    #[cfg(feature = "async")]
+    pub(crate) async fn wait_for_event_until(&self, stop: &Stop) -> Result<Event> {
+        let mut events = EventStream::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+        .await?;
+        let cancelled = async {
+            stop.stopped().await;
+            Some(Event::Cancelled)
+        };
+        let event = futures_lite::future::or(cancelled, events.next()).await;
+        events.unsubscribe().await;
+        event.ok_or_else(|| {
+            ErrorKind::Msg(format!(
+                "connection closed before notification {} was acted on",
+                self.id
+            ))
+            .into()
+        })
+    }
+
+    /// Skips all other events, `None` if the server went away.
+    pub async fn wait_for_close(&self) -> Result<Option<CloseReason>> {
+        wait_for_close_signal(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+        )
+        .await
+    }
+
+    pub async fn wait_for_closed(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<CloseReason, WaitError> {
+        let closed = async {
+            match self.wait_for_close().await {
+                Ok(Some(reason)) => Ok(reason),
+                // the server left, or the connection broke
+                Ok(None) | Err(_) => Err(WaitError::ServerGone),
+            }
+        };
+        let deadline = async {
+            xdg::timer::sleep(timeout).await;
+            Err(WaitError::Elapsed)
+        };
+        futures_lite::future::or(closed, deadline).await
+    }
+
+    pub async fn poll_event(&mut self) -> Option<Event> {
+        if self.polled.is_none() {
+            let events = EventStream::subscribe(
+                &self.connection,
+                &self.bus(),
+                self.server.as_deref(),
+                self.id,
+                &self.activation_token,
+            )
+            .await;
+            self.polled = Some(Box::new(events.ok()?));
+        }
+        let events = self.polled.as_mut()?;
+        match futures_lite::future::poll_once(events.next()).await {
+            Some(event) => event,
+            // polling takes what is there now, an action doesn't wait for its token
+            None => events.release_held(),
+        }
+    }
+
+    /// Listens in a new thread that shares this connection.
+    ///
+    /// Returns once the thread has subscribed, so no signal emitted afterwards is missed.
+    pub(crate) fn spawn_listener(
+        &self,
+        stop: Stop,
+        mut handler: impl FnMut(Event) + Send + 'static,
+    ) -> Result<thread::JoinHandle<()>> {
+        let (connection, bus, id) = (self.connection.clone(), self.bus(), self.id);
+        let (server, tokens) = (self.server.clone(), self.activation_token.clone());
+        let (subscribed_tx, subscribed_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            zbus::block_on(async {
+                let mut events =
+                    match EventStream::subscribe(&connection, &bus, server.as_deref(), id, &tokens)
+                        .await
+                    {
+                        Ok(events) => events,
+                        Err(error) => return subscribed_tx.send(Err(error)).unwrap_or(()),
+                    };
+                let _ = subscribed_tx.send(Ok(()));
+                loop {
+                    let next = events.next();
+                    let stopped = async {
+                        stop.stopped().await;
+                        None
+                    };
+                    match futures_lite::future::or(next, stopped).await {
+                        Some(event) => handler(event),
+                        None => break,
+                    }
+                }
+            });
+        });
+        subscribed_rx
+            .recv()
+            .unwrap_or_else(|_| Err("listener thread panicked".into()))?;
+        Ok(thread)
+    }
+
+    pub async fn events(&self) -> Result<EventStream> {
+        EventStream::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+        .await
+    }
+
+    pub async fn close(&self) -> Result<()> {
+        close_notification_on(&self.connection, self.notification.bus.clone(), self.id).await
+    }
+
+    pub async fn close_and_wait(&self) -> Result<CloseReason> {
+        let mut events = EventStream::subscribe(
+            &self.connection,
+            &self.bus(),
+            self.server.as_deref(),
+            self.id,
+            &self.activation_token,
+        )
+        .await?;
+        self.close().await?;
+        while let Some(event) = events.next().await {
+            if let Event::Closed(reason) = event {
+                return Ok(reason);
+            }
+        }
+        Err(ErrorKind::Msg(format!(
+            "connection closed before notification {} was closed",
+            self.id
+        ))
+        .into())
     }
 
     pub fn on_close<F>(self, closure: F)
     where
         F: FnOnce(CloseReason),
     {
-        zbus::block_on(self.wait_for_action(|action: &ActionResponse| {
-            if let ActionResponse::Closed(reason) = action {
-                closure(*reason);
-            }
-        }));
+        if let Ok(Some(reason)) = zbus::block_on(self.wait_for_close()) {
+            closure(reason);
+        }
     }
 
-    pub fn update_fallible(&mut self) -> Result<()> {
-        self.id = zbus::block_on(send_notification_via_connection(
-            &self.notification,
-            self.id,
-            &self.connection,
-        ))?;
-        Ok(())
+    pub async fn update(&mut self) -> Result<u32> {
+        let (id, server) =
+            send_notification_via_connection(&self.notification, self.id, &self.connection).await?;
+        if server != self.server || id != self.id {
+            // the polled stream only listens to the server that answered before, for the old id
+            self.polled = None;
+        }
+        let replaced = xdg::check_replaced(&self.notification, self.id, id);
+        self.replaced = matches!(replaced, Ok(true));
+        // the handle follows the new id either way, so it can still be closed
+        self.id = id;
+        self.server = server;
+        replaced.map(|_| id)
     }
 
-    pub fn update(&mut self) {
-        self.update_fallible().unwrap();
+    /// Like `update()`, but forgets that the notification was closed before.
+    pub async fn reshow(&mut self) -> Result<u32> {
+        // the polled stream has ended with the close, or follows an id that may be replaced now
+        self.polled = None;
+        self.update().await
     }
 }
 
+/// Sends `notification` to the bus it names.
 async fn send_notification_via_connection(
     notification: &Notification,
     id: u32,
     connection: &zbus::Connection,
-) -> Result<u32> {
-    send_notification_via_connection_at_bus(notification, id, connection, Default::default()).await
+) -> Result<(u32, Option<String>)> {
+    let bus = notification.bus.clone();
+    send_notification_via_connection_at_bus(notification, id, connection, bus).await
 }
 
 async fn send_notification_via_connection_at_bus(
@@ -136,28 +470,191 @@ async fn send_notification_via_connection_at_bus(
     id: u32,
     connection: &zbus::Connection,
     bus: NotificationBus,
-) -> Result<u32> {
-    let reply: u32 = connection
-        .call_method(
-            Some(bus.into_name()),
-            xdg::NOTIFICATION_OBJECTPATH,
-            Some(xdg::NOTIFICATION_INTERFACE),
-            "Notify",
-            &(
-                &notification.appname,
-                id,
-                &notification.icon,
-                &notification.summary,
-                &notification.body,
-                &notification.actions,
-                crate::hints::hints_to_map(notification),
-                i32::from(notification.timeout),
-            ),
-        )
-        .await?
-        .body()
-        .deserialize()?;
-    Ok(reply)
+) -> Result<(u32, Option<String>)> {
+    notification.check_actions()?;
+    learn_spec_version(notification, connection, bus.clone()).await;
+    let proxy = notifications_proxy(connection, bus).await?;
+    let args = notify_args(notification, id);
+    // through the untyped call, which keeps the reply and with it the sender
+    let span = trace::notify_span(notification, id);
+    let call = proxy
+        .inner()
+        .call_method("Notify", &args)
+        .instrument(span.clone());
+    let reply = match notification.send_timeout {
+        Some(timeout) => reply_within(call, timeout).await?,
+        None => call.await?,
+    };
+    let server = reply.header().sender().map(ToString::to_string);
+    let id = reply.body().deserialize()?;
+    trace::record_id(&span, id);
+    Ok((id, server))
+}
+
+/// Asks the server at `bus` for its spec version before the first image is sent, its key depends on it.
+///
+/// This runs inside the send, where blocking on the lazy [`SPEC_VERSION`](crate::SPEC_VERSION) would panic on tokio.
+/// If the server can't tell, the image goes out under the key of spec 1.1.
+#[allow(unused_variables)]
+async fn learn_spec_version(
+    notification: &Notification,
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+) {
+    #[cfg(feature = "images")]
+    if xdg::cached_spec_version().is_none()
+        && notification
+            .effective_hints()
+            .any(|hint| matches!(hint, crate::Hint::ImageData(_)))
+    {
+        if let Err(error) = get_server_information_on(connection, bus).await {
+            log::warn!("can't ask for the spec version ({}), assuming 1.1", error);
+        }
+    }
+}
+
+/// Fails with [`ErrorKind::ReplyTimeout`] if `call` takes longer than `timeout`.
+pub(crate) async fn reply_within<T, E: Into<Error>>(
+    call: impl std::future::Future<Output = std::result::Result<T, E>>,
+    timeout: Duration,
+) -> Result<T> {
+    let call = async { call.await.map_err(Into::into) };
+    let deadline = async {
+        xdg::timer::sleep(timeout).await;
+        Err(ErrorKind::ReplyTimeout(timeout).into())
+    };
+    futures_lite::future::or(call, deadline).await
+}
+
+/// The arguments of the `Notify` call for `notification`, replacing `id` unless it is 0.
+#[allow(clippy::type_complexity)]
+fn notify_args(
+    notification: &Notification,
+    id: u32,
+) -> (
+    &str,
+    u32,
+    &str,
+    &str,
+    &str,
+    &[String],
+    BTreeMap<&str, HintValue<'_>>,
+    i32,
+) {
+    (
+        &notification.appname,
+        id,
+        &notification.icon,
+        &notification.summary,
+        &notification.body,
+        &notification.actions,
+        crate::hints::hints_to_args(notification),
+        i32::from(notification.timeout),
+    )
+}
+
+/// The `Notify` call for `notification`, flagged to not expect a reply if `no_reply`.
+fn notify_message(notification: &Notification, no_reply: bool) -> Result<zbus::Message> {
+    notification.check_actions()?;
+    let mut call = zbus::Message::method_call(xdg::NOTIFICATION_OBJECTPATH, "Notify")?
+        .destination(notification.bus.clone().into_name())?
+        .interface(xdg::NOTIFICATION_INTERFACE)?;
+    if no_reply {
+        call = call.with_flags(zbus::message::Flags::NoReplyExpected)?;
+    }
+    Ok(call.build(&notify_args(notification, notification.id.unwrap_or(0)))?)
+}
+
+/// Sends all `notifications` through `connection` without waiting for a reply in between.
+///
+/// The results are in the order of `notifications`,
+/// only failing to send or losing the connection fails the whole batch.
+pub(crate) async fn send_all(
+    connection: &zbus::Connection,
+    notifications: &[&Notification],
+) -> Result<Vec<Result<u32>>> {
+    for notification in notifications {
+        learn_spec_version(notification, connection, notification.bus.clone()).await;
+    }
+    // subscribed before sending, so no reply can slip by
+    let mut incoming = zbus::MessageStream::from(connection);
+    let mut results = notifications.iter().map(|_| None).collect::<Vec<_>>();
+    let mut pending = HashMap::with_capacity(notifications.len());
+    let mut spans = Vec::with_capacity(notifications.len());
+    for (index, notification) in notifications.iter().enumerate() {
+        let span = trace::notify_span(notification, notification.id.unwrap_or(0));
+        spans.push(span.clone());
+        match notify_message(notification, false) {
+            Ok(call) => {
+                connection.send(&call).instrument(span).await?;
+                pending.insert(call.primary_header().serial_num(), index);
+            }
+            // e.g. invalid actions or a hint that can't be serialized, that one is left out
+            Err(error) => results[index] = Some(Err(error)),
+        }
+    }
+
+    while !pending.is_empty() {
+        let reply = match incoming.next().await {
+            Some(reply) => reply?,
+            None => return Err("connection closed before all notifications were answered".into()),
+        };
+        let header = reply.header();
+        let index = match header
+            .reply_serial()
+            .and_then(|serial| pending.remove(&serial))
+        {
+            Some(index) => index,
+            None => continue,
+        };
+        let result = match header.message_type() {
+            zbus::message::Type::Error => Err(zbus::Error::from(reply.clone()).into()),
+            _ => reply.body().deserialize::<u32>().map_err(Into::into),
+        };
+        if let Ok(id) = result {
+            trace::record_id(&spans[index], id);
+        }
+        results[index] = Some(result);
+    }
+    // every notification has its result by now
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// [`send_all`] in one batch per bus the notifications pick, the results are in the order of `notifications` regardless.
+///
+/// Those for the session bus go through the shared connection, a broken one is replaced by the next send,
+/// those for any other bus through a connection of their own.
+pub(crate) async fn send_all_shared(notifications: &[Notification]) -> Result<Vec<Result<u32>>> {
+    let mut batches: Vec<(&BusSelection, Vec<usize>)> = Vec::new();
+    for (index, notification) in notifications.iter().enumerate() {
+        let selection = &notification.bus_selection;
+        match batches.iter_mut().find(|(bus, _)| *bus == selection) {
+            Some((_, indices)) => indices.push(index),
+            None => batches.push((selection, vec![index])),
+        }
+    }
+
+    let mut results = notifications.iter().map(|_| None).collect::<Vec<_>>();
+    for (selection, indices) in batches {
+        let batch = indices
+            .iter()
+            .map(|&index| &notifications[index])
+            .collect::<Vec<_>>();
+        let sent = match selection {
+            BusSelection::Session => {
+                let sent = send_all(&shared_connection().await?, &batch).await;
+                if matches!(sent, Err(ref error) if error.is_disconnected()) {
+                    reset_connection();
+                }
+                sent
+            }
+            _ => send_all(&connect(selection).await?, &batch).await,
+        }?;
+        for (index, result) in indices.into_iter().zip(sent) {
+            results[index] = Some(result);
+        }
+    }
+    Ok(results.into_iter().flatten().collect())
 }
 
 pub async fn connect_and_send_notification(
@@ -171,116 +668,760 @@ pub(crate) async fn connect_and_send_notification_at_bus(
     notification: &Notification,
     bus: NotificationBus,
 ) -> Result<ZbusNotificationHandle> {
-    let connection = zbus::Connection::session().await?;
+    let connection = connect(&notification.bus_selection).await?;
+    send_notification_at_bus(notification, connection, bus).await
+}
+
+/// A new connection to the bus `selection` picks.
+async fn connect(selection: &BusSelection) -> Result<zbus::Connection> {
+    match selection {
+        BusSelection::Session => session_connection().await,
+        BusSelection::System => Ok(zbus::Connection::system().await?),
+        BusSelection::Address(address) => Ok(zbus::connection::Builder::address(address.as_str())?
+            .build()
+            .await?),
+    }
+}
+
+/// A new connection to the session bus at the address [`session_bus::address`](xdg::session_bus::address) finds.
+pub(crate) async fn session_connection() -> Result<zbus::Connection> {
+    let address = xdg::session_bus::address()?;
+    Ok(zbus::connection::Builder::address(address.as_str())?
+        .build()
+        .await?)
+}
+
+/// The connection [`connect_shared_and_send_notification`] reuses, opened on first use.
+static SHARED_CONNECTION: Mutex<Option<zbus::Connection>> = Mutex::new(None);
+
+async fn shared_connection() -> Result<zbus::Connection> {
+    if let Some(connection) = SHARED_CONNECTION.lock().unwrap().clone() {
+        return Ok(connection);
+    }
+    let connection = session_connection().await?;
+    // another send may have connected in the meantime, everybody keeps using the first connection
+    Ok(SHARED_CONNECTION
+        .lock()
+        .unwrap()
+        .get_or_insert(connection)
+        .clone())
+}
+
+/// Forgets the shared connection, the next send opens a new one.
+pub(crate) fn reset_connection() {
+    SHARED_CONNECTION.lock().unwrap().take();
+    // the cache listens on the old connection
+    CAPABILITIES.lock().unwrap().take();
+}
+
+/// Sends `notification` through the connection shared by all calls, reconnects once if it is gone.
+pub(crate) async fn connect_shared_and_send_notification(
+    notification: &Notification,
+) -> Result<ZbusNotificationHandle> {
+    let bus = notification.bus.clone();
+    if notification.bus_selection != BusSelection::Session {
+        // only the session connection is shared
+        return connect_and_send_notification_at_bus(notification, bus).await;
+    }
+    let connection = shared_connection().await?;
+    match send_notification_at_bus(notification, connection, bus.clone()).await {
+        Err(error) if error.is_disconnected() => {
+            log::warn!("shared connection is gone ({}), reconnecting", error);
+            reset_connection();
+            send_notification_at_bus(notification, shared_connection().await?, bus).await
+        }
+        sent => sent,
+    }
+}
+
+/// Sends `notification` through the shared connection and tells the server not to reply.
+pub(crate) async fn send_notification_nowait(notification: &Notification) -> Result<()> {
+    if notification.bus_selection != BusSelection::Session {
+        // only the session connection is shared
+        let connection = connect(&notification.bus_selection).await?;
+        return send_nowait_via_connection(notification, &connection).await;
+    }
+    match send_nowait_via_connection(notification, &shared_connection().await?).await {
+        Err(error) if error.is_disconnected() => {
+            log::warn!("shared connection is gone ({}), reconnecting", error);
+            reset_connection();
+            send_nowait_via_connection(notification, &shared_connection().await?).await
+        }
+        sent => sent,
+    }
+}
+
+async fn send_nowait_via_connection(
+    notification: &Notification,
+    connection: &zbus::Connection,
+) -> Result<()> {
+    learn_spec_version(notification, connection, notification.bus.clone()).await;
+    let span = trace::notify_span(notification, notification.id.unwrap_or(0));
+    connection
+        .send(&notify_message(notification, true)?)
+        .instrument(span)
+        .await?;
+    Ok(())
+}
+
+/// Closes notification `id` at `bus` through the shared connection.
+pub(crate) async fn close_notification(id: u32, bus: NotificationBus) -> Result<()> {
+    let closed = close_notification_on(&shared_connection().await?, bus, id).await;
+    if matches!(closed, Err(ref error) if error.is_disconnected()) {
+        reset_connection();
+    }
+    closed
+}
+
+pub(crate) async fn close_notification_on(
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+    id: u32,
+) -> Result<()> {
+    notifications_proxy(connection, bus)
+        .await?
+        .close_notification(id)
+        .await?;
+    Ok(())
+}
+
+/// Sends `notification` through an existing `connection`, the handle keeps the connection.
+pub(crate) async fn send_notification_at_bus(
+    notification: &Notification,
+    connection: zbus::Connection,
+    bus: NotificationBus,
+) -> Result<ZbusNotificationHandle> {
     let inner_id = notification.id.unwrap_or(0);
-    let id =
-        send_notification_via_connection_at_bus(notification, inner_id, &connection, bus).await?;
+    let (id, server) =
+        send_notification_via_connection_at_bus(notification, inner_id, &connection, bus.clone())
+            .await?;
 
-    Ok(ZbusNotificationHandle::new(
-        id,
-        connection,
-        notification.clone(),
-    ))
+    // the handle follows, updates and closes the notification at the same bus
+    let notification = Notification {
+        bus,
+        ..notification.clone()
+    };
+    let replaced = xdg::check_replaced(&notification, inner_id, id)?;
+    let mut handle = ZbusNotificationHandle::new(id, connection, notification);
+    handle.server = server;
+    handle.replaced = replaced;
+    Ok(handle)
 }
 
-pub async fn get_capabilities_at_bus(bus: NotificationBus) -> Result<Vec<String>> {
-    let connection = zbus::Connection::session().await?;
-    let info: Vec<String> = connection
-        .call_method(
-            Some(bus.into_name()),
-            xdg::NOTIFICATION_OBJECTPATH,
-            Some(xdg::NOTIFICATION_INTERFACE),
-            "GetCapabilities",
-            &(),
-        )
+pub async fn get_capabilities_at_bus(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<Vec<String>> {
+    let connection = connect(selection).await?;
+    get_capabilities_on(&connection, bus).await
+}
+
+pub async fn get_capabilities_on(
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+) -> Result<Vec<String>> {
+    capabilities_and_server(connection, bus)
+        .await
+        .map(|(capabilities, _)| capabilities)
+}
+
+/// The capabilities and the unique name of the server that answered.
+async fn capabilities_and_server(
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+) -> Result<(Vec<String>, Option<String>)> {
+    let reply = notifications_proxy(connection, bus)
         .await?
-        .body()
-        .deserialize()?;
-    Ok(info)
+        .inner()
+        .call_method("GetCapabilities", &())
+        .await?;
+    let server = reply.header().sender().map(ToString::to_string);
+    Ok((reply.body().deserialize()?, server))
 }
 
-pub async fn get_capabilities() -> Result<Vec<String>> {
-    get_capabilities_at_bus(Default::default()).await
+/// Capabilities of the server with the unique name `server`, valid until the default bus changes hands.
+struct CachedCapabilities {
+    capabilities: Vec<String>,
+    server: Option<String>,
+    owner_changes: Subscription,
+}
+
+impl CachedCapabilities {
+    /// Nobody else took over the bus since the capabilities were asked for.
+    fn is_current(&mut self) -> bool {
+        // only takes what has arrived already, never waits
+        while let Some(Some(Ok(message))) = futures_lite::future::block_on(
+            futures_lite::future::poll_once(self.owner_changes.next()),
+        ) {
+            // `NameOwnerChanged(name, old_owner, new_owner)`
+            let body = message.body();
+            match body.deserialize::<(&str, &str, &str)>() {
+                Ok((_, _, new_owner)) if Some(new_owner) == self.server.as_deref() => {}
+                _ => return false,
+            }
+        }
+        true
+    }
 }
 
-pub async fn get_server_information_at_bus(bus: NotificationBus) -> Result<xdg::ServerInformation> {
-    let connection = zbus::Connection::session().await?;
-    let info: xdg::ServerInformation = connection
+static CAPABILITIES: Mutex<Option<CachedCapabilities>> = Mutex::new(None);
+
+/// Like `get_capabilities()` through the shared connection, but only asks again once the server changed.
+pub(crate) async fn capabilities_cached() -> Result<Vec<String>> {
+    if let Some(cached) = CAPABILITIES.lock().unwrap().as_mut() {
+        if cached.is_current() {
+            return Ok(cached.capabilities.clone());
+        }
+    }
+    let connection = shared_connection().await?;
+    // subscribed before asking, a server starting in between invalidates the answer right away
+    let owner_changes = Subscription::new(owner_changes_rule(&bus_name())?, &connection).await?;
+    let (capabilities, server) = capabilities_and_server(&connection, Default::default()).await?;
+    *CAPABILITIES.lock().unwrap() = Some(CachedCapabilities {
+        capabilities: capabilities.clone(),
+        server,
+        owner_changes,
+    });
+    Ok(capabilities)
+}
+
+/// The default bus name of the notification server.
+fn bus_name() -> String {
+    NotificationBus::default().into_name().to_string()
+}
+
+/// Like `get_capabilities()`, but through the shared connection.
+pub(crate) async fn get_capabilities_shared() -> Result<Vec<String>> {
+    get_capabilities_on(&shared_connection().await?, Default::default()).await
+}
+
+/// Calls `member` of `interface` on the object of the notification server and returns the reply, one value per argument.
+pub async fn call_raw<B>(interface: &str, member: &str, args: &B) -> Result<Vec<OwnedValue>>
+where
+    B: serde::Serialize + zvariant::DynamicType,
+{
+    let connection = session_connection().await?;
+    let reply = connection
         .call_method(
-            Some(bus.into_name()),
+            Some(xdg::NOTIFICATION_DEFAULT_BUS),
             xdg::NOTIFICATION_OBJECTPATH,
-            Some(xdg::NOTIFICATION_INTERFACE),
-            "GetServerInformation",
-            &(),
+            Some(interface),
+            member,
+            args,
         )
+        .await?;
+    let body = reply.body();
+    if body.signature().to_string().is_empty() {
+        return Ok(Vec::new());
+    }
+    let fields = body.deserialize::<zvariant::Structure<'_>>()?.into_fields();
+    Ok(fields
+        .into_iter()
+        .map(OwnedValue::try_from)
+        .collect::<std::result::Result<_, _>>()
+        .map_err(zbus::Error::from)?)
+}
+
+pub async fn get_server_information_at_bus(
+    selection: &BusSelection,
+    bus: NotificationBus,
+) -> Result<xdg::ServerInformation> {
+    let connection = connect(selection).await?;
+    get_server_information_on(&connection, bus).await
+}
+
+pub async fn get_server_information_on(
+    connection: &zbus::Connection,
+    bus: NotificationBus,
+) -> Result<xdg::ServerInformation> {
+    let info = notifications_proxy(connection, bus)
         .await?
-        .body()
-        .deserialize()?;
+        .get_server_information()
+        .await?;
 
+    xdg::cache_spec_version(&info);
     Ok(info)
 }
 
-pub async fn get_server_information() -> Result<xdg::ServerInformation> {
-    get_server_information_at_bus(Default::default()).await
+/// Like `get_server_information()`, but through the shared connection.
+pub(crate) async fn get_server_information_shared() -> Result<xdg::ServerInformation> {
+    get_server_information_on(&shared_connection().await?, Default::default()).await
 }
 
 /// Listens for the `ActionInvoked(UInt32, String)` Signal.
 ///
 /// No need to use this, check out `Notification::show_and_wait_for_action(FnOnce(action:&str))`
 pub async fn handle_action(id: u32, func: impl ActionResponseHandler) {
-    let connection = zbus::Connection::session().await.unwrap();
-    wait_for_action_signal(&connection, id, func).await;
+    let connection = session_connection().await.unwrap();
+    let tokens = ActivationToken::default();
+    wait_for_action_signal(
+        &connection,
+        xdg::NOTIFICATION_DEFAULT_BUS,
+        None,
+        id,
+        &tokens,
+        func,
+    )
+    .await;
+}
+
+/// Like [`handle_action`], but on `selection` and returns without calling `func` once `stop` is set.
+///
+/// Fails if the bus can't be reached or the match rules can't be added.
+pub(crate) async fn handle_action_until(
+    id: u32,
+    selection: &BusSelection,
+    stop: &Stop,
+    func: impl ActionResponseHandler,
+) -> Result<()> {
+    let connection = connect(selection).await?;
+    let tokens = ActivationToken::default();
+    let mut events = EventStream::subscribe(
+        &connection,
+        xdg::NOTIFICATION_DEFAULT_BUS,
+        None,
+        id,
+        &tokens,
+    )
+    .await?;
+    let stopped = async {
+        stop.stopped().await;
+        None
+    };
+    let responded = async {
+        while let Some(event) = events.next().await {
+            if event.as_response().is_some() {
+                return Some(event);
+            }
+        }
+        None
+    };
+    let event = futures_lite::future::or(stopped, responded).await;
+    events.unsubscribe().await;
+    if let Some(response) = event.as_ref().and_then(Event::as_response) {
+        func.call(&response);
+    }
+    Ok(())
 }
 
 async fn wait_for_action_signal(
     connection: &zbus::Connection,
+    bus: &str,
+    server: Option<&str>,
     id: u32,
+    tokens: &ActivationToken,
     handler: impl ActionResponseHandler,
 ) {
-    let action_signal_rule = MatchRule::builder()
+    if let Ok(mut events) = EventStream::subscribe(connection, bus, server, id, tokens).await {
+        while let Some(event) = events.next().await {
+            if let Some(response) = event.as_response() {
+                handler.call(&response);
+                return;
+            }
+        }
+    }
+}
+
+/// Waits for the next [`Event`] of notification `id`.
+pub(crate) async fn wait_for_event(
+    connection: &zbus::Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+    tokens: &ActivationToken,
+) -> Result<Event> {
+    match EventStream::subscribe(connection, bus, server, id, tokens)
+        .await?
+        .next()
+        .await
+    {
+        Some(event) => Ok(event),
+        None => Err(ErrorKind::Msg(format!(
+            "connection closed before notification {} was acted on",
+            id
+        ))
+        .into()),
+    }
+}
+
+/// Matches all signals of the notification interface, only those sent by `server` if there is one.
+fn notification_signals_rule(server: Option<&str>) -> zbus::Result<MatchRule<'static>> {
+    let rule = MatchRule::builder()
         .msg_type(zbus::message::Type::Signal)
-        .interface(xdg::NOTIFICATION_INTERFACE)
-        .unwrap()
-        .member("ActionInvoked")
-        .unwrap()
-        .build();
+        .interface(xdg::NOTIFICATION_INTERFACE)?
+        .path(xdg::NOTIFICATION_OBJECTPATH)?;
+    Ok(match server {
+        Some(server) => rule.sender(server.to_owned())?.build(),
+        None => rule.build(),
+    })
+}
+
+/// Matches only the `NotificationClosed` signals of `server`, or of anyone without one.
+fn closed_signal_rule(server: Option<&str>) -> zbus::Result<MatchRule<'static>> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface(xdg::NOTIFICATION_INTERFACE)?
+        .member("NotificationClosed")?
+        .path(xdg::NOTIFICATION_OBJECTPATH)?;
+    Ok(match server {
+        Some(server) => rule.sender(server.to_owned())?.build(),
+        None => rule.build(),
+    })
+}
+
+/// Waits for `NotificationClosed` of notification `id`, `None` if the server at `bus` went away first.
+///
+/// Only that signal is subscribed to, invoked actions don't even reach the connection.
+async fn wait_for_close_signal(
+    connection: &zbus::Connection,
+    bus: &str,
+    server: Option<&str>,
+    id: u32,
+) -> Result<Option<CloseReason>> {
+    let mut closed = Subscription::new(closed_signal_rule(server)?, connection).await?;
+    let mut owner_changes = Subscription::new(owner_changes_rule(bus)?, connection).await?;
+    let reason = loop {
+        let event = futures_lite::future::or(
+            async { Some(parse_event(&closed.next().await?.ok()?, id)) },
+            async {
+                let message = owner_changes.next().await?.ok()?;
+                Some(owner_lost(&message).then_some(Event::ServerGone))
+            },
+        )
+        .await;
+        match event {
+            Some(Some(Event::Closed(reason))) => break Some(reason),
+            Some(Some(Event::ServerGone)) | None => break None,
+            Some(_) => {}
+        }
+    };
+    futures_lite::future::zip(closed.unsubscribe(), owner_changes.unsubscribe()).await;
+    Ok(reason)
+}
 
-    let proxy = zbus::fdo::DBusProxy::new(connection).await.unwrap();
-    proxy.add_match_rule(action_signal_rule).await.unwrap();
+/// The events of all notifications as `(id, event)`, the match rule is removed once the stream is dropped.
+pub(crate) async fn all_events(
+    connection: &zbus::Connection,
+) -> Result<impl Stream<Item = (u32, Event)>> {
+    let signals = Subscription::new(notification_signals_rule(None)?, connection).await?;
+    Ok(signals.filter_map(|message| parse_signal(&message.ok()?)))
+}
+
+/// Calls `handler` with the events of all notifications in a new thread until `stop` is set.
+///
+/// Returns once the thread has subscribed.
+pub(crate) fn spawn_subscription(
+    stop: Stop,
+    mut handler: impl FnMut(u32, Event) + Send + 'static,
+) -> Result<thread::JoinHandle<()>> {
+    let connection = zbus::block_on(session_connection())?;
+    let (subscribed_tx, subscribed_rx) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        zbus::block_on(async {
+            let events = match all_events(&connection).await {
+                Ok(events) => events,
+                Err(error) => return subscribed_tx.send(Err(error)).unwrap_or(()),
+            };
+            futures_lite::pin!(events);
+            let _ = subscribed_tx.send(Ok(()));
+            loop {
+                let next = events.next();
+                let stopped = async {
+                    stop.stopped().await;
+                    None
+                };
+                match futures_lite::future::or(next, stopped).await {
+                    Some((id, event)) => handler(id, event),
+                    None => break,
+                }
+            }
+        });
+    });
+    subscribed_rx
+        .recv()
+        .unwrap_or_else(|_| Err("subscription thread panicked".into()))?;
+    Ok(thread)
+}
 
-    let close_signal_rule = MatchRule::builder()
+/// Matches owner changes of the well-known name `bus`.
+fn owner_changes_rule(bus: &str) -> zbus::Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
         .msg_type(zbus::message::Type::Signal)
-        .interface(xdg::NOTIFICATION_INTERFACE)
-        .unwrap()
-        .member("NotificationClosed")
-        .unwrap()
-        .build();
-    proxy.add_match_rule(close_signal_rule).await.unwrap();
-
-    while let Ok(Some(msg)) = zbus::MessageStream::from(connection).try_next().await {
-        let header = msg.header();
-        if let zbus::message::Type::Signal = header.message_type() {
-            match header.member() {
-                Some(name) if name == "ActionInvoked" => {
-                    match msg.body().deserialize::<(u32, String)>() {
-                        Ok((nid, action)) if nid == id => {
-                            handler.call(&ActionResponse::Custom(&action));
-                            break;
-                        }
-                        _ => {}
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .add_arg(bus.to_owned())?
+        .build())
+}
+
+/// The previous owner of the name lost it, whether a successor took over right away or not.
+fn owner_lost(message: &zbus::Message) -> bool {
+    // `NameOwnerChanged(name, old_owner, new_owner)`
+    matches!(
+        message.body().deserialize::<(&str, &str, &str)>(),
+        Ok((_, old_owner, _)) if !old_owner.is_empty()
+    )
+}
+
+/// The messages matching a rule, the rule is removed from the bus once this is dropped.
+///
+/// zbus removes it in a task on the connection's executor,
+/// with tokio that needs a runtime which the blocking calls don't have, so they get one to drop this.
+#[derive(Debug)]
+struct Subscription(Option<zbus::MessageStream>);
+
+impl Subscription {
+    async fn new(rule: MatchRule<'static>, connection: &zbus::Connection) -> Result<Self> {
+        let messages = zbus::MessageStream::for_match_rule(rule, connection, None).await?;
+        Ok(Subscription(Some(messages)))
+    }
+
+    /// Removes the match rule from the bus and waits for it, dropping leaves that to a background task.
+    async fn unsubscribe(mut self) {
+        if let Some(messages) = self.0.take() {
+            zbus::AsyncDrop::async_drop(messages).await;
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = zbus::Result<zbus::Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.0 {
+            Some(ref mut messages) => messages.poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if tokio::runtime::Handle::try_current().is_err() {
+            let messages = self.0.take();
+            zbus::block_on(async move { drop(messages) });
+        }
+    }
+}
+
+/// The [`Event`]s of notification `id`, ends after it was closed or the server at `bus` went away.
+///
+/// The match rules are removed from the bus again once the stream is dropped.
+#[derive(Debug)]
+pub struct EventStream {
+    signals: Subscription,
+    owner_changes: Subscription,
+    id: u32,
+    tokens: ActivationToken,
+    pairing: TokenPairing,
+    late_token: Option<xdg::timer::Timer>,
+    ended: bool,
+}
+
+impl EventStream {
+    /// Subscribes to all signals of the notification interface and to owner changes of `bus`.
+    ///
+    /// With a `server`, only its signals are let through, those of other servers on the bus are ignored.
+    /// The activation token of every event is recorded in `tokens`.
+    pub(crate) async fn subscribe(
+        connection: &zbus::Connection,
+        bus: &str,
+        server: Option<&str>,
+        id: u32,
+        tokens: &ActivationToken,
+    ) -> Result<Self> {
+        let signals_rule = notification_signals_rule(server)?;
+        let signals = Subscription::new(signals_rule, connection).await?;
+        let owner_changes = Subscription::new(owner_changes_rule(bus)?, connection).await?;
+        Ok(EventStream {
+            signals,
+            owner_changes,
+            id,
+            tokens: tokens.clone(),
+            pairing: TokenPairing::default(),
+            late_token: None,
+            ended: false,
+        })
+    }
+
+    /// Removes both match rules from the bus before returning.
+    pub(crate) async fn unsubscribe(self) {
+        futures_lite::future::zip(self.signals.unsubscribe(), self.owner_changes.unsubscribe())
+            .await;
+    }
+
+    /// The action that is still waiting for its activation token, without the token.
+    fn release_held(&mut self) -> Option<Event> {
+        self.pairing.release();
+        let (event, token) = self.pairing.next()?;
+        self.ended = event.is_final();
+        self.tokens.record(&event, token);
+        Some(event)
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        while !self.ended {
+            if let Some((event, token)) = self.pairing.next() {
+                self.ended = event.is_final();
+                self.tokens.record(&event, token);
+                return Poll::Ready(Some(event));
+            }
+            match self.owner_changes.poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    if owner_lost(&message) {
+                        self.pairing.event(Event::ServerGone);
                     }
+                    continue;
                 }
-                Some(name) if name == "NotificationClosed" => {
-                    match msg.body().deserialize::<(u32, u32)>() {
-                        Ok((nid, reason)) if nid == id => {
-                            handler.call(&ActionResponse::Closed(reason.into()));
-                            break;
-                        }
-                        _ => {}
+                Poll::Ready(Some(Err(_)) | None) => break,
+                Poll::Pending => {}
+            }
+            match self.signals.poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    if let Some(token) = parse_activation_token(&message, self.id) {
+                        self.pairing.token(token);
+                    } else if let Some(event) = parse_event(&message, self.id) {
+                        self.pairing.event(event);
                     }
+                    continue;
                 }
-                _ => {}
+                Poll::Ready(Some(Err(_)) | None) => break,
+                Poll::Pending => {}
+            }
+            // an action waits for its token a little longer
+            let deadline = match self.pairing.deadline() {
+                Some(deadline) => deadline,
+                None => return Poll::Pending,
+            };
+            let timer = self
+                .late_token
+                .get_or_insert_with(|| xdg::timer::Timer::at(deadline));
+            timer.set_at(deadline);
+            if std::future::Future::poll(Pin::new(timer), cx).is_pending() {
+                return Poll::Pending;
             }
         }
+        // the streams ended, what was held back still goes out
+        self.pairing.release();
+        match self.pairing.next() {
+            Some((event, token)) if !self.ended => {
+                self.tokens.record(&event, token);
+                Poll::Ready(Some(event))
+            }
+            _ => Poll::Ready(None),
+        }
+    }
+}
+
+/// The token of an `ActivationToken` signal for notification `id`, it precedes `ActionInvoked`.
+fn parse_activation_token(message: &zbus::Message, id: u32) -> Option<String> {
+    if message.header().member()?.as_str() != "ActivationToken" {
+        return None;
+    }
+    match message.body().deserialize::<(u32, String)>() {
+        Ok((nid, token)) if nid == id => Some(token),
+        _ => None,
+    }
+}
+
+/// Turns a signal into an [`Event`] if it concerns notification `id`.
+fn parse_event(message: &zbus::Message, id: u32) -> Option<Event> {
+    parse_signal(message)
+        .filter(|&(nid, _)| nid == id)
+        .map(|(_, event)| event)
+}
+
+/// Turns a signal of the notification interface into the id of its notification and an [`Event`].
+fn parse_signal(message: &zbus::Message) -> Option<(u32, Event)> {
+    let header = message.header();
+    let body = message.body();
+    let (id, event) = match header.member()?.as_str() {
+        "ActionInvoked" => {
+            let (id, action) = body.deserialize::<(u32, String)>().ok()?;
+            (id, Event::ActionInvoked(action))
+        }
+        "NotificationClosed" => {
+            let (id, reason) = body.deserialize::<(u32, u32)>().ok()?;
+            (id, Event::Closed(reason.into()))
+        }
+        "NotificationReplied" => {
+            let (id, text) = body.deserialize::<(u32, String)>().ok()?;
+            (id, Event::Replied(text))
+        }
+        _ => return None,
+    };
+    trace::received(id, &event);
+    Some((id, event))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use zbus::zvariant::{self, OwnedValue};
+
+    use super::notify_message;
+    use crate::{Hint, Notification};
+
+    type NotifyBody = (
+        String,
+        u32,
+        String,
+        String,
+        String,
+        Vec<String>,
+        HashMap<String, OwnedValue>,
+        i32,
+    );
+
+    fn sent_hints(notification: &Notification) -> HashMap<String, OwnedValue> {
+        let message = notify_message(notification, false).unwrap();
+        message.body().deserialize::<NotifyBody>().unwrap().6
+    }
+
+    #[test]
+    fn nested_raw_hints_survive_into_the_message() {
+        let nested = HashMap::from([("volume".to_owned(), 40u32), ("max".to_owned(), 100u32)]);
+        let notification = Notification::new()
+            .raw_hints_extend([(
+                "x-vendor-levels".to_owned(),
+                OwnedValue::try_from(zvariant::Value::from(nested.clone())).unwrap(),
+            )])
+            .finalize();
+
+        let hints = sent_hints(&notification);
+        let sent = HashMap::<String, u32>::try_from(hints["x-vendor-levels"].try_clone().unwrap());
+        assert_eq!(sent.unwrap(), nested);
+    }
+
+    #[test]
+    fn raw_hints_replace_typed_ones_in_the_message() {
+        let notification = Notification::new()
+            .hint(Hint::Category("email".into()))
+            .raw_hints_extend([("category".to_owned(), OwnedValue::from(7u32))])
+            .finalize();
+
+        let hints = sent_hints(&notification);
+        assert_eq!(hints.len(), 1);
+        assert_eq!(u32::try_from(&hints["category"]).unwrap(), 7);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn image_data_arrives_as_it_was() {
+        let data = (0..=255).cycle().take(4 * 3 * 3).collect::<Vec<u8>>();
+        let image = crate::Image::from_rgb(4, 3, data.clone()).unwrap();
+        let notification = Notification::new().image_data(image).finalize();
+
+        let hints = sent_hints(&notification);
+        let key = crate::image::image_spec_str(crate::image::known_spec_version());
+        let sent =
+            <(i32, i32, i32, bool, i32, i32, Vec<u8>)>::try_from(hints[key].try_clone().unwrap());
+        assert_eq!(sent.unwrap(), (4, 3, 12, false, 8, 3, data));
     }
 }
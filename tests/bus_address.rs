@@ -0,0 +1,139 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+//! Starts a `dbus-daemon` of its own and talks to it only by address.
+
+mod common;
+
+use std::{
+    env, fs,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use common::TestServer;
+use notify_rust::{
+    get_capabilities_via, get_server_information_via, handle_action_via, ActionResponse,
+    BusSelection, Event, Notification,
+};
+
+/// A `dbus-daemon` listening on a unix socket, killed on drop.
+struct PrivateBus(Child);
+
+impl PrivateBus {
+    fn start() -> Option<(PrivateBus, String)> {
+        static STARTED: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "notify-rust-private-bus-{}-{}",
+            std::process::id(),
+            STARTED.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut daemon = Command::new("dbus-daemon")
+            .arg("--session")
+            .arg("--nofork")
+            .arg("--print-address")
+            .arg(format!("--address=unix:path={}", dir.join("bus").display()))
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        // printed once it listens
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some((PrivateBus(daemon), address.trim().to_owned()))
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn talks_to_a_bus_by_address() {
+    let (_daemon, address) = match PrivateBus::start() {
+        Some(started) => started,
+        // no dbus-daemon installed
+        None => return,
+    };
+    let server = TestServer::start_at_address(&address);
+    let bus = BusSelection::Address(address);
+
+    let info = get_server_information_via(&bus).unwrap();
+    assert_eq!(info.name, "test-server");
+    assert!(get_capabilities_via(&bus)
+        .unwrap()
+        .contains(&"actions".to_owned()));
+
+    let handle = Notification::new()
+        .summary("by address")
+        .action("open", "Open")
+        .on_bus(bus.clone())
+        .show()
+        .unwrap();
+    assert_eq!(server.received(handle.id())[0].summary, "by address");
+    server.invoke_later(handle.id(), "open");
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("open".into()));
+
+    let invoked = Arc::new(Mutex::new(None));
+    let id = handle.id();
+    server.invoke_later(id, "open");
+    handle_action_via(id, &bus, {
+        let invoked = Arc::clone(&invoked);
+        move |response: &ActionResponse| {
+            if let ActionResponse::Custom(action) = response {
+                *invoked.lock().unwrap() = Some(action.to_string());
+            }
+        }
+    })
+    .unwrap();
+    assert_eq!(invoked.lock().unwrap().as_deref(), Some("open"));
+}
+
+#[cfg(feature = "async")]
+#[async_std::test]
+async fn send_all_sends_each_to_its_own_bus() {
+    let (_daemon, address) = match PrivateBus::start() {
+        Some(started) => started,
+        None => return,
+    };
+    let private = TestServer::start_at_address(&address);
+    let shared = TestServer::shared();
+    let bus = BusSelection::Address(address);
+    let notifications = [
+        Notification::new().summary("batch session").finalize(),
+        Notification::new()
+            .summary("batch private")
+            .on_bus(bus.clone())
+            .finalize(),
+        Notification::new().summary("").on_bus(bus).finalize(),
+        Notification::new().summary("batch session too").finalize(),
+    ];
+
+    let results = notify_rust::send_all(&notifications).await.unwrap();
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok() && results[3].is_ok());
+    assert_eq!(
+        private.received(*results[1].as_ref().unwrap())[0].summary,
+        "batch private"
+    );
+    assert!(results[2].is_err());
+    assert_eq!(shared.received_with_summary("batch session").len(), 1);
+    assert_eq!(shared.received_with_summary("batch session too").len(), 1);
+    assert!(shared.received_with_summary("batch private").is_empty());
+    assert!(private.received_with_summary("batch session").is_empty());
+}
+
+#[test]
+fn unreachable_addresses_fail_without_panicking() {
+    let dir = env::temp_dir().join(format!("notify-rust-no-bus-{}", std::process::id()));
+    let bus = BusSelection::Address(format!("unix:path={}", dir.join("bus").display()));
+    assert!(handle_action_via(1, &bus, |_| panic!("nobody listens, but handled")).is_err());
+}
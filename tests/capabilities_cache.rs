@@ -0,0 +1,58 @@
+#![cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::thread;
+
+use common::{TestServer, SIGNAL_DELAY};
+use notify_rust::{ConnectionRef, Notification};
+
+/// The unique name of the connection `show_async()` and the cache share.
+async fn shared_sender() -> String {
+    let handle = Notification::new()
+        .summary("probe")
+        .show_async()
+        .await
+        .unwrap();
+    match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(_) => unreachable!("show_async() always uses zbus"),
+    }
+}
+
+fn capability_calls(server: &TestServer, sender: &str) -> usize {
+    server
+        .methods_called_by(sender)
+        .into_iter()
+        .filter(|&method| method == "GetCapabilities")
+        .count()
+}
+
+#[async_std::test]
+async fn cached_until_the_server_changes() {
+    let first = TestServer::start();
+    let sender = shared_sender().await;
+
+    assert_eq!(
+        notify_rust::capabilities_cached().await.unwrap(),
+        ["actions", "body"]
+    );
+    assert_eq!(
+        notify_rust::capabilities_cached().await.unwrap(),
+        ["actions", "body"]
+    );
+    assert_eq!(capability_calls(&first, &sender), 1);
+
+    // bypassing the cache always asks
+    notify_rust::get_capabilities_async().await.unwrap();
+    assert_eq!(capability_calls(&first, &sender), 2);
+
+    first.stop();
+    let second = TestServer::start();
+    // the owner change is a signal, give it time to arrive
+    thread::sleep(SIGNAL_DELAY);
+    notify_rust::capabilities_cached().await.unwrap();
+    notify_rust::capabilities_cached().await.unwrap();
+    assert_eq!(capability_calls(&second, &sender), 1);
+}
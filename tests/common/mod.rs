@@ -0,0 +1,529 @@
+//! A minimal notification server to run the handle tests against.
+//!
+//! These need a session bus without a notification server, e.g. `dbus-run-session cargo test`.
+#![allow(dead_code)]
+
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use zbus::{fdo, message::Header, object_server::SignalEmitter, zvariant::OwnedValue};
+
+pub const BUS: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+
+/// Custom hint that makes the server invoke an action on its own, for calls that block right away.
+pub const INVOKE_HINT: &str = "x-test-invoke";
+
+/// Custom hint with the milliseconds the server takes to answer `Notify`.
+pub const DELAY_HINT: &str = "x-test-delay";
+
+/// Long enough for the client to subscribe to signals before they are emitted.
+pub const SIGNAL_DELAY: Duration = Duration::from_millis(300);
+
+struct Notifications {
+    calls: Arc<AtomicU32>,
+    live: Live,
+    never_replace: Arc<AtomicBool>,
+    deny_closing: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<Received>>>,
+    close_requests: Arc<Mutex<Vec<u32>>>,
+    methods: Methods,
+    query_delay: QueryDelay,
+}
+
+/// How long `GetCapabilities` and `GetServerInformation` take to answer.
+type QueryDelay = Arc<Mutex<Duration>>;
+
+/// Every method call as `(sender, member)`.
+type Methods = Arc<Mutex<Vec<(String, &'static str)>>>;
+
+fn record(methods: &Methods, header: &Header<'_>, member: &'static str) {
+    let sender = header.sender().map(ToString::to_string).unwrap_or_default();
+    methods.lock().unwrap().push((sender, member));
+}
+
+/// A `Notify` call as the server saw it.
+#[derive(Debug)]
+pub struct Received {
+    pub id: u32,
+    pub replaces_id: u32,
+    pub summary: String,
+    pub hints: HashMap<String, OwnedValue>,
+}
+
+impl Clone for Received {
+    fn clone(&self) -> Self {
+        Received {
+            summary: self.summary.clone(),
+            hints: self
+                .hints
+                .iter()
+                .map(|(key, value)| (key.clone(), value.try_clone().unwrap()))
+                .collect(),
+            ..*self
+        }
+    }
+}
+
+/// Ids of the notifications that were neither closed nor expired yet.
+type Live = Arc<Mutex<HashSet<u32>>>;
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl Notifications {
+    /// Rejects notifications without summary, replacing an id that is gone yields a new one,
+    /// as does any replacing after [`TestServer::never_replace()`].
+    ///
+    /// Invokes the action named by the hint [`INVOKE_HINT`] after [`SIGNAL_DELAY`].
+    /// Notifications with a positive timeout expire after it.
+    /// Answers only after the milliseconds of the hint [`DELAY_HINT`].
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        _app_name: &str,
+        replaces_id: u32,
+        _app_icon: &str,
+        summary: &str,
+        _body: &str,
+        _actions: Vec<&str>,
+        hints: HashMap<&str, OwnedValue>,
+        expire_timeout: i32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<u32> {
+        record(&self.methods, &header, "Notify");
+        if let Some(delay) = hints.get(DELAY_HINT) {
+            let delay = i32::try_from(delay.try_clone().unwrap()).unwrap();
+            async_io::Timer::after(Duration::from_millis(delay as u64)).await;
+        }
+        let id = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if summary.is_empty() {
+            return Err(fdo::Error::InvalidArgs("summary is empty".into()));
+        }
+        let mut live = self.live.lock().unwrap();
+        let replaces = !self.never_replace.load(Ordering::SeqCst) && live.contains(&replaces_id);
+        let id = if replaces { replaces_id } else { id };
+        live.insert(id);
+        if let Some(action) = hints.get(INVOKE_HINT) {
+            let action = String::try_from(action.try_clone().unwrap()).unwrap();
+            let emitter = emitter.to_owned();
+            thread::spawn(move || {
+                thread::sleep(SIGNAL_DELAY);
+                zbus::block_on(Self::action_invoked(&emitter, id, &action)).unwrap();
+            });
+        }
+        if let Ok(timeout @ 1..) = u64::try_from(expire_timeout) {
+            let (live, emitter) = (self.live.clone(), emitter.to_owned());
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(timeout));
+                if live.lock().unwrap().remove(&id) {
+                    zbus::block_on(Self::notification_closed(&emitter, id, 1)).unwrap();
+                }
+            });
+        }
+        self.received.lock().unwrap().push(Received {
+            id,
+            replaces_id,
+            summary: summary.to_owned(),
+            hints: hints
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        });
+        Ok(id)
+    }
+
+    /// Rejects ids that are not shown anymore, and all of them after [`TestServer::deny_closing()`].
+    async fn close_notification(
+        &self,
+        id: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        self.close_requests.lock().unwrap().push(id);
+        if self.deny_closing.load(Ordering::SeqCst) {
+            return Err(fdo::Error::AccessDenied("closing is not allowed".into()));
+        }
+        if !self.live.lock().unwrap().remove(&id) {
+            return Err(fdo::Error::InvalidArgs(format!("no notification {}", id)));
+        }
+        Self::notification_closed(&emitter, id, 3).await?;
+        Ok(())
+    }
+
+    async fn get_capabilities(&self, #[zbus(header)] header: Header<'_>) -> Vec<&str> {
+        record(&self.methods, &header, "GetCapabilities");
+        self.query_delayed().await;
+        vec!["actions", "body"]
+    }
+
+    async fn get_server_information(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+    ) -> (&str, &str, &str, &str) {
+        record(&self.methods, &header, "GetServerInformation");
+        self.query_delayed().await;
+        ("test-server", "notify-rust", "0.1", "1.2")
+    }
+
+    /// Not in the specification, leaves the bus name it was called at like `notify-rust`'s own server does.
+    async fn stop(
+        &self,
+        #[zbus(connection)] connection: &zbus::Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        record(&self.methods, &header, "Stop");
+        if let Some(zbus::names::BusName::WellKnown(name)) = header.destination() {
+            connection.release_name(name.to_owned()).await?;
+        }
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(emitter: &SignalEmitter<'_>, id: u32, action: &str)
+        -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+}
+
+impl Notifications {
+    async fn query_delayed(&self) {
+        let delay = *self.query_delay.lock().unwrap();
+        if !delay.is_zero() {
+            async_io::Timer::after(delay).await;
+        }
+    }
+}
+
+pub struct TestServer {
+    connection: zbus::blocking::Connection,
+    bus: String,
+    calls: Arc<AtomicU32>,
+    live: Live,
+    never_replace: Arc<AtomicBool>,
+    deny_closing: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<Received>>>,
+    close_requests: Arc<Mutex<Vec<u32>>>,
+    methods: Methods,
+    query_delay: QueryDelay,
+}
+
+impl TestServer {
+    pub fn start() -> TestServer {
+        TestServer::start_at(BUS)
+    }
+
+    /// Serves at `bus` instead of the usual name, e.g. the one of `Notification::at_bus()`.
+    pub fn start_at(bus: &str) -> TestServer {
+        TestServer::serve(
+            zbus::blocking::connection::Builder::session().unwrap(),
+            Some(bus),
+        )
+    }
+
+    /// Serves at `bus` on the system bus, which needs a policy that allows owning it.
+    pub fn start_on_system_bus_at(bus: &str) -> TestServer {
+        TestServer::serve(
+            zbus::blocking::connection::Builder::system().unwrap(),
+            Some(bus),
+        )
+    }
+
+    /// Serves at the usual name on the bus at `address`, e.g. a `dbus-daemon` of the test's own.
+    pub fn start_at_address(address: &str) -> TestServer {
+        TestServer::serve(
+            zbus::blocking::connection::Builder::address(address).unwrap(),
+            Some(BUS),
+        )
+    }
+
+    /// Serves the one client at the other end of `socket`, peer to peer without a bus or a name.
+    ///
+    /// Returns once the client connected, so connect it from another thread.
+    pub fn start_on_socket(socket: UnixStream) -> TestServer {
+        let builder = zbus::blocking::connection::Builder::async_io_unix_stream(socket)
+            .server(zbus::Guid::generate())
+            .unwrap()
+            .p2p();
+        TestServer::serve(builder, None)
+    }
+
+    fn serve(builder: zbus::blocking::connection::Builder<'_>, bus: Option<&str>) -> TestServer {
+        let calls = Arc::new(AtomicU32::new(0));
+        let live = Live::default();
+        let never_replace = Arc::default();
+        let deny_closing = Arc::default();
+        let received = Arc::default();
+        let close_requests = Arc::default();
+        let methods = Methods::default();
+        let query_delay = QueryDelay::default();
+        let builder = match bus {
+            Some(bus) => builder.name(bus).unwrap(),
+            None => builder,
+        };
+        let connection = builder
+            .serve_at(
+                PATH,
+                Notifications {
+                    calls: calls.clone(),
+                    live: live.clone(),
+                    never_replace: Arc::clone(&never_replace),
+                    deny_closing: Arc::clone(&deny_closing),
+                    received: Arc::clone(&received),
+                    close_requests: Arc::clone(&close_requests),
+                    methods: Arc::clone(&methods),
+                    query_delay: Arc::clone(&query_delay),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        TestServer {
+            connection,
+            bus: bus.unwrap_or(BUS).to_owned(),
+            calls,
+            live,
+            never_replace,
+            deny_closing,
+            received,
+            close_requests,
+            methods,
+            query_delay,
+        }
+    }
+
+    /// Shows every notification anew, like servers that don't support `replaces_id`.
+    pub fn never_replace(&self) {
+        self.never_replace.store(true, Ordering::SeqCst);
+    }
+
+    /// Answers every `CloseNotification` with `AccessDenied`, known id or not.
+    pub fn deny_closing(&self) {
+        self.deny_closing.store(true, Ordering::SeqCst);
+    }
+
+    /// Makes `GetCapabilities` and `GetServerInformation` answer only after `delay`.
+    pub fn delay_queries(&self, delay: Duration) {
+        *self.query_delay.lock().unwrap() = delay;
+    }
+
+    /// One server per test binary, started on first use.
+    pub fn shared() -> &'static TestServer {
+        static SHARED: Mutex<Option<&'static TestServer>> = Mutex::new(None);
+        let mut shared = SHARED.lock().unwrap();
+        shared.get_or_insert_with(|| Box::leak(Box::new(TestServer::start())))
+    }
+
+    /// Leaves the bus as if the server crashed.
+    pub fn stop(self) {
+        self.connection.release_name(self.bus.as_str()).unwrap();
+    }
+
+    /// Closes the connection to the bus, calls still waiting for an answer never get one.
+    pub fn disconnect(self) {
+        self.connection.close().unwrap();
+    }
+
+    /// Number of `Notify` calls so far.
+    pub fn calls(&self) -> u32 {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// All `Notify` calls that showed or replaced notification `id`.
+    pub fn received(&self, id: u32) -> Vec<Received> {
+        let received = self.received.lock().unwrap();
+        received.iter().filter(|r| r.id == id).cloned().collect()
+    }
+
+    /// All `Notify` calls with `summary`, for sends that never learned their id.
+    pub fn received_with_summary(&self, summary: &str) -> Vec<Received> {
+        let received = self.received.lock().unwrap();
+        received
+            .iter()
+            .filter(|r| r.summary == summary)
+            .cloned()
+            .collect()
+    }
+
+    /// How often `CloseNotification` was called for `id`.
+    pub fn close_requests(&self, id: u32) -> usize {
+        let close_requests = self.close_requests.lock().unwrap();
+        close_requests
+            .iter()
+            .filter(|&&requested| requested == id)
+            .count()
+    }
+
+    /// The methods called through the connection with the unique name `sender`, in order.
+    pub fn methods_called_by(&self, sender: &str) -> Vec<&'static str> {
+        let methods = self.methods.lock().unwrap();
+        methods
+            .iter()
+            .filter(|(from, _)| from == sender)
+            .map(|&(_, member)| member)
+            .collect()
+    }
+
+    /// Emits `ActionInvoked` after [`SIGNAL_DELAY`].
+    pub fn invoke_later(&self, id: u32, action: &str) {
+        self.invoke_after(SIGNAL_DELAY, id, action);
+    }
+
+    /// Emits `NotificationClosed` after [`SIGNAL_DELAY`].
+    pub fn close_later(&self, id: u32, reason: u32) {
+        self.close_after(SIGNAL_DELAY, id, reason);
+    }
+
+    /// Emits `ActionInvoked` after `delay`.
+    pub fn invoke_after(&self, delay: Duration, id: u32, action: &str) {
+        self.emit_after(delay, "ActionInvoked", (id, action.to_owned()), || ());
+    }
+
+    /// Emits `ActivationToken` and right behind it `ActionInvoked` after [`SIGNAL_DELAY`].
+    pub fn invoke_with_token_later(&self, id: u32, action: &str, token: &str) {
+        self.invoke_and_send_token(id, action, token, true);
+    }
+
+    /// Like [`invoke_with_token_later`](Self::invoke_with_token_later), but the token comes late.
+    pub fn invoke_then_token_later(&self, id: u32, action: &str, token: &str) {
+        self.invoke_and_send_token(id, action, token, false);
+    }
+
+    fn invoke_and_send_token(&self, id: u32, action: &str, token: &str, token_first: bool) {
+        let connection = self.connection.clone();
+        let (action, token) = (action.to_owned(), token.to_owned());
+        thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY);
+            let send_token = || {
+                connection
+                    .emit_signal(None::<&str>, PATH, BUS, "ActivationToken", &(id, &token))
+                    .unwrap()
+            };
+            if token_first {
+                send_token();
+            }
+            connection
+                .emit_signal(None::<&str>, PATH, BUS, "ActionInvoked", &(id, action))
+                .unwrap();
+            if !token_first {
+                send_token();
+            }
+        });
+    }
+
+    /// Emits `ActionInvoked` and right behind it `NotificationClosed` after [`SIGNAL_DELAY`],
+    /// as servers do when an action of a notification that is not resident is invoked.
+    pub fn invoke_then_close_later(&self, id: u32, action: &str, reason: u32) {
+        let (connection, live) = (self.connection.clone(), self.live.clone());
+        let action = action.to_owned();
+        thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY);
+            connection
+                .emit_signal(None::<&str>, PATH, BUS, "ActionInvoked", &(id, action))
+                .unwrap();
+            live.lock().unwrap().remove(&id);
+            connection
+                .emit_signal(None::<&str>, PATH, BUS, "NotificationClosed", &(id, reason))
+                .unwrap();
+        });
+    }
+
+    /// Emits `NotificationReplied` after `delay`.
+    pub fn reply_after(&self, delay: Duration, id: u32, text: &str) {
+        self.emit_after(delay, "NotificationReplied", (id, text.to_owned()), || ());
+    }
+
+    /// Forgets notification `id` and emits `NotificationClosed` after `delay`.
+    pub fn close_after(&self, delay: Duration, id: u32, reason: u32) {
+        let live = self.live.clone();
+        self.emit_after(delay, "NotificationClosed", (id, reason), move || {
+            live.lock().unwrap().remove(&id);
+        });
+    }
+
+    fn emit_after<B>(
+        &self,
+        delay: Duration,
+        signal: &'static str,
+        body: B,
+        before: impl FnOnce() + Send + 'static,
+    ) where
+        B: serde::Serialize + zbus::zvariant::DynamicType + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            before();
+            connection
+                .emit_signal(None::<&str>, PATH, BUS, signal, &body)
+                .unwrap();
+        });
+    }
+}
+
+/// Emits `ActionInvoked` and `NotificationClosed` for `id` after [`SIGNAL_DELAY`],
+/// from a connection of its own that is not the server the notification was sent to.
+pub fn impersonate_later(id: u32, action: &str) {
+    let impostor = zbus::blocking::Connection::session().unwrap();
+    let action = action.to_owned();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        impostor
+            .emit_signal(None::<&str>, PATH, BUS, "ActionInvoked", &(id, action))
+            .unwrap();
+        impostor
+            .emit_signal(None::<&str>, PATH, BUS, "NotificationClosed", &(id, 2u32))
+            .unwrap();
+    });
+}
+
+/// Number of match rules currently registered on the bus.
+///
+/// Needs a bus with `org.freedesktop.DBus.Debug.Stats`, as the `dbus-daemon` of `dbus-run-session` has.
+pub fn bus_match_rules() -> Option<u32> {
+    let connection = zbus::blocking::Connection::session().ok()?;
+    let stats: HashMap<String, OwnedValue> = connection
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus.Debug.Stats"),
+            "GetStats",
+            &(),
+        )
+        .ok()?
+        .body()
+        .deserialize()
+        .ok()?;
+    u32::try_from(stats.get("MatchRules")?).ok()
+}
+
+/// The match rules `connection` registered on the bus, by its unique name.
+///
+/// Needs `org.freedesktop.DBus.Debug.Stats` just like [`bus_match_rules()`].
+pub fn match_rules_of(connection: &str) -> Option<Vec<String>> {
+    let bus = zbus::blocking::Connection::session().ok()?;
+    let mut rules: HashMap<String, Vec<String>> = bus
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus.Debug.Stats"),
+            "GetAllMatchRules",
+            &(),
+        )
+        .ok()?
+        .body()
+        .deserialize()
+        .ok()?;
+    Some(rules.remove(connection).unwrap_or_default())
+}
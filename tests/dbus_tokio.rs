@@ -0,0 +1,72 @@
+#![cfg(all(
+    feature = "dbus_tokio",
+    feature = "zbus",
+    unix,
+    not(target_os = "macos")
+))]
+
+mod common;
+
+use std::time::Duration;
+
+use common::TestServer;
+use notify_rust::{dbus_tokio, CloseReason, Event, Notification};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shows_and_waits_without_blocking() {
+    let server = TestServer::start();
+
+    let capabilities = dbus_tokio::get_capabilities_async().await.unwrap();
+    assert!(capabilities.contains(&"actions".to_owned()));
+    let info = dbus_tokio::get_server_information_async().await.unwrap();
+    assert_eq!(info.name, "test-server");
+
+    let handle = dbus_tokio::show_async(
+        Notification::new()
+            .summary("on tokio")
+            .action("open", "Open"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(server.received(handle.id())[0].summary, "on tokio");
+
+    server.invoke_later(handle.id(), "open");
+    assert_eq!(
+        handle.wait_for_action_async().await.unwrap(),
+        Event::ActionInvoked("open".into())
+    );
+
+    // the action is skipped, only the close ends the wait
+    server.invoke_then_close_later(handle.id(), "open", 2);
+    assert_eq!(
+        handle.wait_for_closed_async().await.unwrap(),
+        CloseReason::Dismissed
+    );
+
+    let other = dbus_tokio::show_async(Notification::new().summary("closed by us"))
+        .await
+        .unwrap();
+    let id = other.id();
+    other.close_async().await.unwrap();
+    assert_eq!(server.close_requests(id), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_dropped_wait_lets_the_runtime_go_on() {
+    let server = TestServer::start();
+    let handle = dbus_tokio::show_async(Notification::new().summary("ignored"))
+        .await
+        .unwrap();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), handle.wait_async())
+            .await
+            .is_err(),
+        "nothing happened to the notification"
+    );
+
+    server.close_later(handle.id(), 3);
+    assert_eq!(
+        handle.wait_async().await.unwrap(),
+        Event::Closed(CloseReason::CloseAction)
+    );
+}
@@ -0,0 +1,1656 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{TestServer, DELAY_HINT, INVOKE_HINT, SIGNAL_DELAY};
+use notify_rust::{
+    error::ErrorKind, CloseReason, Event, Hint, Notification, WaitError, WaitTimedOut,
+};
+
+fn close_reason_for(raw_reason: u32) -> CloseReason {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("close me").show().unwrap();
+    server.close_later(handle.id(), raw_reason);
+
+    let (reason_tx, reason_rx) = mpsc::channel();
+    handle.on_close(move |reason| reason_tx.send(reason).unwrap());
+    reason_rx.try_recv().unwrap()
+}
+
+#[test]
+fn on_close_reports_reason() {
+    assert_eq!(close_reason_for(1), CloseReason::Expired);
+    assert_eq!(close_reason_for(2), CloseReason::Dismissed);
+    assert_eq!(close_reason_for(3), CloseReason::CloseAction);
+    assert_eq!(close_reason_for(42), CloseReason::Other(42));
+}
+
+#[test]
+fn try_on_close_returns_what_the_handler_returned() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("close me").show().unwrap();
+    server.close_later(handle.id(), 2);
+
+    let closed = handle.try_on_close(|reason| format!("{:?}", reason));
+    assert_eq!(closed.unwrap().as_deref(), Some("Dismissed"));
+}
+
+#[test]
+fn try_on_close_skips_the_handler_when_the_server_leaves() {
+    let server = TestServer::start_at("de.hoodie.Notification.debug_close_gone");
+    let handle = Notification::new()
+        .summary("orphaned")
+        .show_at_bus("debug_close_gone")
+        .unwrap();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        server.stop();
+    });
+
+    let closed = handle.try_on_close(|| panic!("nothing was closed"));
+    assert_eq!(closed.unwrap(), None);
+}
+
+#[test]
+fn close_is_reported_as_close_action() {
+    let _server = TestServer::shared();
+    let handle = Notification::new().summary("closing").show().unwrap();
+    let id = handle.id();
+
+    let closer = Notification::new().summary("closer").id(id).finalize();
+    let (reason_tx, reason_rx) = mpsc::channel();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        closer.show().unwrap().close().unwrap();
+    });
+    handle.on_close(move |reason| reason_tx.send(reason).unwrap());
+    assert_eq!(reason_rx.try_recv().unwrap(), CloseReason::CloseAction);
+}
+
+#[test]
+fn on_close_waits_past_invoked_actions() {
+    use notify_rust::ConnectionRef;
+
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("acted on").show().unwrap();
+    let own = match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => connection.unique_name(),
+    };
+    server.invoke_then_close_later(handle.id(), "open", 2);
+    let sampled = thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY / 2);
+        common::match_rules_of(&own)
+    });
+
+    let (reason_tx, reason_rx) = mpsc::channel();
+    handle.on_close(move |reason| reason_tx.send(reason).unwrap());
+    assert_eq!(reason_rx.try_recv().unwrap(), CloseReason::Dismissed);
+
+    if let Some(rules) = sampled.join().unwrap() {
+        // the owner changes of the bus and nothing but closes of the notification interface
+        assert_eq!(rules.len(), 2, "{:?}", rules);
+        assert!(rules
+            .iter()
+            .any(|rule| rule.contains("member='NotificationClosed'")));
+        assert!(rules
+            .iter()
+            .all(|rule| rule.contains("member='NotificationClosed'")
+                || rule.contains("member='NameOwnerChanged'")));
+    }
+}
+
+#[test]
+fn wait_for_action_times_out() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("ignored").show().unwrap();
+    server.invoke_later(handle.id(), "too late");
+
+    let (action_tx, action_rx) = mpsc::channel::<String>();
+    let result = handle.wait_for_action_timeout(SIGNAL_DELAY / 3, move |action| {
+        action_tx.send(action.to_owned()).unwrap();
+    });
+    assert_eq!(result, Err(WaitTimedOut));
+
+    // the closure was dropped along with the wait, the late action is not delivered
+    thread::sleep(SIGNAL_DELAY);
+    assert_eq!(action_rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+#[test]
+fn wait_for_action_completes_before_timeout() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("click me").show().unwrap();
+    server.invoke_later(handle.id(), "clicked");
+
+    let (action_tx, action_rx) = mpsc::channel();
+    let result =
+        handle.wait_for_action_timeout(SIGNAL_DELAY + Duration::from_millis(300), |action| {
+            action_tx.send(action.to_owned()).unwrap();
+        });
+    assert_eq!(result, Ok(()));
+    assert_eq!(action_rx.try_recv().unwrap(), "clicked");
+}
+
+#[test]
+fn wait_for_closed_skips_actions() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("expiring").show().unwrap();
+    server.invoke_later(handle.id(), "ignored");
+    server.close_after(SIGNAL_DELAY * 2, handle.id(), 1);
+
+    assert_eq!(
+        handle.wait_for_closed(Duration::from_secs(5)),
+        Ok(CloseReason::Expired)
+    );
+}
+
+#[test]
+fn wait_for_closed_gives_up_at_the_deadline() {
+    let _server = TestServer::shared();
+    let handle = Notification::new().summary("resident").show().unwrap();
+
+    let started = Instant::now();
+    assert_eq!(
+        handle.wait_for_closed(Duration::from_millis(150)),
+        Err(WaitError::Elapsed)
+    );
+    let waited = started.elapsed();
+    assert!(waited >= Duration::from_millis(150), "{:?}", waited);
+    assert!(waited < Duration::from_millis(900), "{:?}", waited);
+}
+
+#[test]
+fn wait_for_closed_ends_when_the_server_leaves() {
+    let server = TestServer::start_at("de.hoodie.Notification.debug_wait_gone");
+    let handle = Notification::new()
+        .summary("orphaned")
+        .show_at_bus("debug_wait_gone")
+        .unwrap();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        server.stop();
+    });
+
+    let started = Instant::now();
+    assert_eq!(
+        handle.wait_for_closed(Duration::from_secs(5)),
+        Err(WaitError::ServerGone)
+    );
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn send_timeout_gives_up_on_a_slow_server() {
+    let _server = TestServer::shared();
+    let mut notification = Notification::new();
+    notification
+        .summary("slow")
+        .hint(Hint::CustomInt(DELAY_HINT.into(), 1000))
+        .send_timeout(Duration::from_millis(200));
+
+    let started = Instant::now();
+    let error = notification.show().unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::ReplyTimeout(_)),
+        "{}",
+        error
+    );
+    assert!(started.elapsed() < Duration::from_millis(1000));
+
+    // patient enough
+    notification.send_timeout(Duration::from_secs(5));
+    notification.show().unwrap();
+}
+
+#[test]
+fn a_server_leaving_mid_call_is_no_timeout() {
+    let server = TestServer::start_at("de.hoodie.Notification.debug_leaving");
+    let leaving = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        server.disconnect();
+    });
+    let started = Instant::now();
+    let error = Notification::new()
+        .summary("never answered")
+        .hint(Hint::CustomInt(DELAY_HINT.into(), 2000))
+        .send_timeout(Duration::from_secs(5))
+        .show_at_bus("debug_leaving")
+        .unwrap_err();
+    assert!(
+        !matches!(error.kind(), ErrorKind::ReplyTimeout(_)),
+        "{}",
+        error
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+    leaving.join().unwrap();
+}
+
+/// Waits up to 5 seconds for the server to have received `summary`.
+fn eventually_received(server: &TestServer, summary: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while server.received_with_summary(summary).is_empty() {
+        if Instant::now() > deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+#[test]
+fn show_nowait_returns_before_the_server_answers() {
+    let server = TestServer::shared();
+    let started = Instant::now();
+    Notification::new()
+        .summary("fire and forget")
+        .hint(Hint::CustomInt(DELAY_HINT.into(), 1000))
+        .show_nowait()
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_millis(1000));
+    assert!(eventually_received(server, "fire and forget"));
+
+    // nobody hears about the rejection
+    Notification::new().summary("").show_nowait().unwrap();
+}
+
+#[async_std::test]
+async fn show_nowait_async_sends() {
+    let server = TestServer::shared();
+    Notification::new()
+        .summary("forgotten async")
+        .show_nowait_async()
+        .await
+        .unwrap();
+    assert!(eventually_received(server, "forgotten async"));
+}
+
+#[test]
+fn signals_of_other_servers_are_ignored() {
+    let server = TestServer::shared();
+    let mut handle = Notification::new().summary("cross-talk").show().unwrap();
+    common::impersonate_later(handle.id(), "spoofed");
+    server.invoke_after(SIGNAL_DELAY * 2, handle.id(), "genuine");
+
+    assert_eq!(
+        handle.wait().unwrap(),
+        Event::ActionInvoked("genuine".into())
+    );
+
+    // the polling path filters just the same
+    assert_eq!(handle.poll_event(), None);
+    common::impersonate_later(handle.id(), "spoofed");
+    server.close_after(SIGNAL_DELAY * 2, handle.id(), 1);
+    thread::sleep(SIGNAL_DELAY * 3);
+    assert_eq!(
+        handle.poll_event(),
+        Some(Event::Closed(CloseReason::Expired))
+    );
+    assert_eq!(handle.poll_event(), None);
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn wait_for_action_async_resolves() {
+    let server = TestServer::shared();
+    let handle = Notification::new()
+        .summary("async")
+        .show_async()
+        .await
+        .unwrap();
+
+    server.invoke_later(handle.id(), "open");
+    assert_eq!(
+        handle.wait_for_action_async().await.unwrap(),
+        Event::ActionInvoked("open".into())
+    );
+
+    server.close_later(handle.id(), 2);
+    assert_eq!(
+        handle.wait_for_action_async().await.unwrap(),
+        Event::Closed(CloseReason::Dismissed)
+    );
+}
+
+#[async_std::test]
+async fn cancelling_a_wait_unsubscribes_right_away() {
+    let _server = TestServer::shared();
+    // a connection of its own, so no other test's rules are on it
+    let connection = zbus::Connection::session().await.unwrap();
+    let own = connection.unique_name().unwrap().to_string();
+    let handle = Notification::new()
+        .summary("cancel me")
+        .finalize()
+        .show_async_on(&connection)
+        .await
+        .unwrap();
+
+    let (wait, canceller) = handle.wait_cancelable();
+    let cancelled = {
+        let own = own.clone();
+        thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY);
+            let during = common::match_rules_of(&own);
+            canceller.cancel();
+            (during, Instant::now())
+        })
+    };
+    assert_eq!(wait.await.unwrap(), Event::Cancelled);
+    let (during, cancelled_at) = cancelled.join().unwrap();
+    assert!(cancelled_at.elapsed() < Duration::from_millis(100));
+
+    if let (Some(during), Some(after)) = (during, common::match_rules_of(&own)) {
+        assert_eq!(during.len(), 2, "{:?}", during);
+        assert_eq!(after, Vec::<String>::new());
+    }
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn wait_for_closed_async_resolves_with_the_reason() {
+    let server = TestServer::shared();
+    let handle = Notification::new()
+        .summary("expires by itself")
+        .timeout(SIGNAL_DELAY * 2)
+        .show_async()
+        .await
+        .unwrap();
+    server.invoke_later(handle.id(), "ignored");
+
+    // the action is skipped, but still reaches the other waiter
+    let (action, reason) = futures_lite::future::zip(
+        handle.wait_for_action_async(),
+        handle.wait_for_closed_async(),
+    )
+    .await;
+    assert_eq!(action.unwrap(), Event::ActionInvoked("ignored".into()));
+    assert_eq!(reason.unwrap(), CloseReason::Expired);
+}
+
+#[test]
+fn show_and_wait_returns_invoked_action() {
+    let _server = TestServer::shared();
+    let event = Notification::new()
+        .summary("pick one")
+        .action("install", "Install")
+        .action("later", "Later")
+        .hint(Hint::Custom(INVOKE_HINT.into(), "install".into()))
+        .show_and_wait()
+        .unwrap();
+    assert_eq!(event, Event::ActionInvoked("install".into()));
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn show_and_wait_async_returns_invoked_action() {
+    let _server = TestServer::shared();
+    let event = Notification::new()
+        .summary("pick one")
+        .action("later", "Later")
+        .hint(Hint::Custom(INVOKE_HINT.into(), "later".into()))
+        .show_and_wait_async()
+        .await
+        .unwrap();
+    assert_eq!(event, Event::ActionInvoked("later".into()));
+}
+
+#[test]
+fn wait_returns_action_before_close() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("race").show().unwrap();
+    server.invoke_after(SIGNAL_DELAY, handle.id(), "open");
+    server.close_after(SIGNAL_DELAY * 3, handle.id(), 2);
+
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("open".into()));
+    // the close was not consumed along with the action
+    assert_eq!(
+        handle.wait().unwrap(),
+        Event::Closed(CloseReason::Dismissed)
+    );
+}
+
+#[test]
+fn wait_returns_close_before_action() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("race").show().unwrap();
+    server.close_after(SIGNAL_DELAY, handle.id(), 1);
+    server.invoke_after(SIGNAL_DELAY * 2, handle.id(), "too late");
+
+    assert_eq!(handle.wait().unwrap(), Event::Closed(CloseReason::Expired));
+}
+
+#[async_std::test]
+async fn wait_async_returns_first_event() {
+    let server = TestServer::shared();
+    let handle = Notification::new()
+        .summary("race")
+        .show_async()
+        .await
+        .unwrap();
+    server.reply_after(SIGNAL_DELAY, handle.id(), "on my way");
+    server.close_after(SIGNAL_DELAY * 3, handle.id(), 2);
+
+    assert_eq!(
+        handle.wait_async().await.unwrap(),
+        Event::Replied("on my way".into())
+    );
+    assert_eq!(
+        handle.wait_async().await.unwrap(),
+        Event::Closed(CloseReason::Dismissed)
+    );
+}
+
+#[test]
+fn activation_token_comes_with_the_action() {
+    let server = TestServer::shared();
+    let mut handle = Notification::new().summary("raise me").show().unwrap();
+    let other = Notification::new().summary("someone else").show().unwrap();
+    assert_eq!(handle.last_activation_token(), None);
+
+    server.invoke_with_token_later(other.id(), "open", "not mine");
+    server.invoke_with_token_later(handle.id(), "open", "token-1");
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("open".into()));
+    assert_eq!(handle.last_activation_token().as_deref(), Some("token-1"));
+
+    server.reply_after(SIGNAL_DELAY, handle.id(), "hello");
+    assert_eq!(handle.wait().unwrap(), Event::Replied("hello".into()));
+    assert_eq!(handle.last_activation_token(), None);
+
+    assert_eq!(handle.poll_event(), None); // subscribes
+    server.invoke_with_token_later(handle.id(), "again", "token-2");
+    thread::sleep(SIGNAL_DELAY * 2);
+    assert_eq!(
+        handle.poll_event(),
+        Some(Event::ActionInvoked("again".into()))
+    );
+    assert_eq!(handle.last_activation_token().as_deref(), Some("token-2"));
+}
+
+#[test]
+fn a_late_token_still_belongs_to_its_action() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("raise me late").show().unwrap();
+
+    server.invoke_then_token_later(handle.id(), "open", "late");
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("open".into()));
+    assert_eq!(handle.last_activation_token().as_deref(), Some("late"));
+
+    // without any token the action still arrives
+    server.invoke_later(handle.id(), "alone");
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("alone".into()));
+    assert_eq!(handle.last_activation_token(), None);
+}
+
+#[test]
+fn action_callbacks_get_the_token() {
+    let server = TestServer::shared();
+    for token_first in [true, false] {
+        let handle = Notification::new().summary("callback").show().unwrap();
+        if token_first {
+            server.invoke_with_token_later(handle.id(), "open", "for-callback");
+        } else {
+            server.invoke_then_token_later(handle.id(), "open", "for-callback");
+        }
+        let mut received = None;
+        handle
+            .on_action_with_token("open", |token| received = token)
+            .listen()
+            .unwrap();
+        assert_eq!(received.as_deref(), Some("for-callback"));
+    }
+}
+
+#[test]
+fn one_fn_mut_handler_for_several_notifications() {
+    let server = TestServer::shared();
+    let mut seen = Vec::new();
+    let mut record = |action: &str| seen.push(action.to_owned());
+
+    for action in ["archive", "delete"] {
+        let handle = Notification::new().summary("mail").show().unwrap();
+        server.invoke_later(handle.id(), action);
+        handle.wait_for_action(&mut record);
+    }
+    assert_eq!(seen, ["archive", "delete"]);
+}
+
+#[test]
+fn on_action_dispatches_by_key() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    for key in ["archive", "delete", "snooze"] {
+        let handle = Notification::new()
+            .summary("New mail")
+            .action("archive", "Archive")
+            .action("delete", "Delete")
+            .show()
+            .unwrap();
+        server.invoke_then_close_later(handle.id(), key, 3);
+
+        handle
+            .on_action("archive", || {
+                called.borrow_mut().push("replaced".to_owned())
+            })
+            .on_action("archive", || called.borrow_mut().push("archive".to_owned()))
+            .on_action("delete", || called.borrow_mut().push("delete".to_owned()))
+            .on_other_action(|other| called.borrow_mut().push(format!("other {}", other)))
+            .on_close(|| called.borrow_mut().push("closed".to_owned()))
+            .listen()
+            .unwrap();
+    }
+
+    assert_eq!(
+        *called.borrow(),
+        [
+            "archive",
+            "closed",
+            "delete",
+            "closed",
+            "other snooze",
+            "closed"
+        ]
+    );
+}
+
+#[test]
+fn close_right_behind_the_action_is_not_missed() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    let handle = Notification::new().summary("clicked").show().unwrap();
+    server.invoke_then_close_later(handle.id(), "open", 2);
+    handle
+        .on_action("open", || called.borrow_mut().push("open".to_owned()))
+        .on_close(|reason: CloseReason| called.borrow_mut().push(format!("{:?}", reason)))
+        .listen()
+        .unwrap();
+    assert_eq!(*called.borrow(), ["open", "Dismissed"]);
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn close_right_behind_the_action_is_not_missed_async() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    let handle = Notification::new()
+        .summary("clicked")
+        .show_async()
+        .await
+        .unwrap();
+    server.invoke_then_close_later(handle.id(), "open", 2);
+    handle
+        .on_action("open", || called.borrow_mut().push("open".to_owned()))
+        .on_close(|reason: CloseReason| called.borrow_mut().push(format!("{:?}", reason)))
+        .listen_async()
+        .await
+        .unwrap();
+    assert_eq!(*called.borrow(), ["open", "Dismissed"]);
+}
+
+#[test]
+fn all_close_handlers_run_in_order() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    let handle = Notification::new().summary("closing").show().unwrap();
+    server.close_later(handle.id(), 2);
+    handle
+        .on_action("open", || called.borrow_mut().push("open".to_owned()))
+        .on_close(|| called.borrow_mut().push("first".to_owned()))
+        .on_close(|reason: CloseReason| called.borrow_mut().push(format!("second {:?}", reason)))
+        .on_close(|| called.borrow_mut().push("third".to_owned()))
+        .listen()
+        .unwrap();
+
+    assert_eq!(*called.borrow(), ["first", "second Dismissed", "third"]);
+}
+
+#[test]
+fn failing_callbacks_end_the_listener() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    let handle = Notification::new().summary("failing").show().unwrap();
+    server.close_later(handle.id(), 1);
+    let error = handle
+        .on_action("open", || called.borrow_mut().push("open"))
+        .on_close(|| called.borrow_mut().push("first"))
+        .on_close(|reason: CloseReason| {
+            called.borrow_mut().push("second");
+            Err(format!("can't handle {:?}", reason))
+        })
+        .on_close(|| called.borrow_mut().push("third"))
+        .listen()
+        .unwrap_err();
+    assert_eq!(error.to_string(), "can't handle Expired");
+    assert_eq!(*called.borrow(), ["first", "second"]);
+
+    let handle = Notification::new().summary("failing").show().unwrap();
+    server.invoke_later(handle.id(), "open");
+    let error = handle
+        .on_action("open", || "not a number".parse::<u32>().map(|_| ()))
+        .listen()
+        .unwrap_err();
+    assert!(std::error::Error::source(&error).is_some());
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn on_action_async_awaits_the_callback() {
+    let server = TestServer::shared();
+    let called = std::cell::RefCell::new(Vec::new());
+
+    for key in ["save", "discard"] {
+        let handle = Notification::new()
+            .summary("unsaved changes")
+            .action("save", "Save")
+            .action("discard", "Discard")
+            .show_async()
+            .await
+            .unwrap();
+        server.invoke_later(handle.id(), key);
+
+        handle
+            .on_action("save", || called.borrow_mut().push("replaced"))
+            .on_action_async("save", || async {
+                async_std::task::sleep(Duration::from_millis(50)).await;
+                called.borrow_mut().push("saved");
+            })
+            .on_action_async("discard", || async { called.borrow_mut().push("replaced") })
+            .on_action("discard", || called.borrow_mut().push("discarded"))
+            .listen_async()
+            .await
+            .unwrap();
+    }
+    assert_eq!(*called.borrow(), ["saved", "discarded"]);
+
+    // the blocking listener runs the future to completion as well
+    let handle = Notification::new().summary("blocking").show().unwrap();
+    server.invoke_later(handle.id(), "save");
+    handle
+        .on_action_async("save", || async {
+            async_std::task::sleep(Duration::from_millis(50)).await;
+            called.borrow_mut().push("saved again");
+        })
+        .listen()
+        .unwrap();
+    assert_eq!(called.borrow().last(), Some(&"saved again"));
+}
+
+#[test]
+fn events_end_after_close() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("events").show().unwrap();
+    server.invoke_after(SIGNAL_DELAY, handle.id(), "open");
+    server.reply_after(SIGNAL_DELAY * 2, handle.id(), "on my way");
+    server.close_after(SIGNAL_DELAY * 3, handle.id(), 2);
+    server.invoke_after(SIGNAL_DELAY * 4, handle.id(), "too late");
+
+    let events = handle.events().unwrap().collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            Event::ActionInvoked("open".into()),
+            Event::Replied("on my way".into()),
+            Event::Closed(CloseReason::Dismissed),
+        ]
+    );
+}
+
+#[async_std::test]
+#[cfg_attr(feature = "tokio", ignore = "its timeouts need a tokio runtime")]
+async fn events_async_end_after_close() {
+    use futures_lite::StreamExt;
+
+    let server = TestServer::shared();
+    let handle = Notification::new()
+        .summary("async events")
+        .show_async()
+        .await
+        .unwrap();
+    server.invoke_after(SIGNAL_DELAY, handle.id(), "open");
+    server.close_after(SIGNAL_DELAY * 2, handle.id(), 1);
+
+    let events = handle
+        .events_async()
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(
+        events,
+        [
+            Event::ActionInvoked("open".into()),
+            Event::Closed(CloseReason::Expired),
+        ]
+    );
+}
+
+#[test]
+fn poll_event_does_not_block() {
+    fn poll_for(handle: &mut notify_rust::NotificationHandle, duration: Duration) -> Option<Event> {
+        let deadline = std::time::Instant::now() + duration;
+        while std::time::Instant::now() < deadline {
+            if let Some(event) = handle.poll_event() {
+                return Some(event);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    let server = TestServer::shared();
+    let mut handle = Notification::new().summary("polled").show().unwrap();
+    assert_eq!(handle.poll_event(), None);
+
+    server.invoke_later(handle.id(), "next");
+    assert_eq!(
+        poll_for(&mut handle, SIGNAL_DELAY * 3),
+        Some(Event::ActionInvoked("next".into()))
+    );
+    assert_eq!(handle.poll_event(), None);
+
+    server.close_later(handle.id(), 1);
+    assert_eq!(
+        poll_for(&mut handle, SIGNAL_DELAY * 3),
+        Some(Event::Closed(CloseReason::Expired))
+    );
+
+    server.invoke_later(handle.id(), "after close");
+    assert_eq!(poll_for(&mut handle, SIGNAL_DELAY * 2), None);
+}
+
+#[test]
+fn close_and_wait_reports_close_action() {
+    let _server = TestServer::shared();
+    let handle = Notification::new().summary("closing").show().unwrap();
+    assert_eq!(handle.close_and_wait().unwrap(), CloseReason::CloseAction);
+}
+
+#[test]
+fn closing_an_expired_notification_fails() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("expiring").show().unwrap();
+    server.close_after(Duration::ZERO, handle.id(), 1);
+    thread::sleep(SIGNAL_DELAY);
+
+    let error = handle.close().unwrap_err();
+    assert!(error.to_string().contains("no notification"), "{}", error);
+}
+
+#[test]
+fn unpaired_actions_are_not_sent() {
+    let server = TestServer::shared();
+    let mut notification = Notification::new();
+    notification
+        .summary("unpaired actions")
+        .action("open", "Open");
+    notification.actions.push("dangling".into());
+
+    let error = notification.show().unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::InvalidActions(_)),
+        "{}",
+        error
+    );
+    assert!(server.received_with_summary("unpaired actions").is_empty());
+}
+
+#[cfg(feature = "images")]
+#[test]
+fn image_data_arrives_as_the_spec_struct() {
+    let server = TestServer::shared();
+    let data = (0..=255).cycle().take(3 * 2 * 4).collect::<Vec<u8>>();
+    let image = notify_rust::Image::from_rgba(3, 2, data.clone()).unwrap();
+    let handle = Notification::new()
+        .summary("pictured")
+        .image_data(image)
+        .show()
+        .unwrap();
+
+    let received = server.received(handle.id());
+    let value = received[0].hints["image-data"].try_clone().unwrap();
+    assert_eq!(value.value_signature(), "(iiibiiay)");
+    let sent = <(i32, i32, i32, bool, i32, i32, Vec<u8>)>::try_from(value).unwrap();
+    assert_eq!(sent, (3, 2, 12, true, 8, 4, data));
+}
+
+#[test]
+fn close_notification_by_id() {
+    let server = TestServer::shared();
+    let id = Notification::new()
+        .summary("persisted")
+        .show()
+        .unwrap()
+        .id();
+
+    notify_rust::close_notification(id).unwrap();
+    assert_eq!(server.close_requests(id), 1);
+
+    let error = notify_rust::close_notification(id).unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::NotFound(gone) if *gone == id),
+        "{}",
+        error
+    );
+    assert_eq!(
+        error.to_string(),
+        format!("there is no notification {}", id)
+    );
+}
+
+#[test]
+fn close_notification_on_an_existing_connection() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("connected").show().unwrap();
+    let id = Notification::new()
+        .summary("persisted elsewhere")
+        .show()
+        .unwrap()
+        .id();
+
+    notify_rust::close_notification_on(handle.connection(), id).unwrap();
+    assert_eq!(server.close_requests(id), 1);
+
+    let error = notify_rust::close_notification_on(handle.connection(), id).unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::NotFound(gone) if *gone == id),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn close_notification_passes_other_errors_on() {
+    let server = TestServer::start_at("de.hoodie.Notification.debug_no_closing");
+    server.deny_closing();
+    let id = Notification::new()
+        .summary("stays")
+        .show_at_bus("debug_no_closing")
+        .unwrap()
+        .id();
+
+    let error = notify_rust::close_notification_at_bus(id, "debug_no_closing").unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::PermissionDenied(ref e) if e == "closing is not allowed"),
+        "{}",
+        error
+    );
+    assert_eq!(server.close_requests(id), 1);
+}
+
+#[async_std::test]
+async fn close_notification_async_by_id() {
+    let _server = TestServer::shared();
+    let id = Notification::new()
+        .summary("persisted")
+        .show_async()
+        .await
+        .unwrap()
+        .id();
+
+    notify_rust::close_notification_async(id).await.unwrap();
+    let error = notify_rust::close_notification_async(id).await.unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::NotFound(_)), "{}", error);
+}
+
+#[test]
+fn show_at_bus_sticks_to_that_server() {
+    let shared = TestServer::shared();
+    let debug = TestServer::start_at("de.hoodie.Notification.debug_server");
+    let mut handle = Notification::new()
+        .summary("debugging")
+        .show_at_bus("debug_server")
+        .unwrap();
+    assert_eq!(debug.received(handle.id())[0].summary, "debugging");
+    assert!(shared
+        .received(handle.id())
+        .iter()
+        .all(|received| received.summary != "debugging"));
+
+    handle.update().unwrap();
+    assert_eq!(debug.calls(), 2);
+
+    // the shared server closing a notification with the same id is none of its business
+    shared.close_later(handle.id(), 1);
+    debug.close_after(SIGNAL_DELAY * 2, handle.id(), 2);
+    assert_eq!(
+        handle.wait_for_closed(Duration::from_secs(5)),
+        Ok(CloseReason::Dismissed)
+    );
+
+    let error = Notification::new()
+        .summary("nowhere")
+        .show_at_bus("not valid")
+        .unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::InvalidBusName(e) if e.name() == "de.hoodie.Notification.not valid"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn server_capabilities_are_typed() {
+    use notify_rust::{Capability, CapabilitySet};
+
+    let _server = TestServer::shared();
+    let capabilities = notify_rust::server_capabilities().unwrap();
+    assert_eq!(capabilities, [Capability::Actions, Capability::Body]);
+    let set = capabilities.into_iter().collect::<CapabilitySet>();
+    assert!(set.supports(Capability::Actions));
+    assert!(!set.supports(Capability::BodyMarkup));
+}
+
+#[test]
+fn queries_at_bus_ask_that_server() {
+    let _debug = TestServer::start_at("de.hoodie.Notification.debug_queries");
+    assert_eq!(
+        notify_rust::get_capabilities_at_bus("debug_queries").unwrap(),
+        ["actions", "body"]
+    );
+    assert_eq!(
+        notify_rust::get_server_information_at_bus("debug_queries")
+            .unwrap()
+            .name,
+        "test-server"
+    );
+
+    // nobody owns this one
+    assert!(notify_rust::get_capabilities_at_bus("debug_nobody").is_err());
+    let error = notify_rust::get_server_information_at_bus("not valid").unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::InvalidBusName(_)),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn odd_bus_names_and_hints_fail_without_panicking() {
+    use notify_rust::Hint;
+
+    let server = TestServer::shared();
+    let long = "x".repeat(300);
+    for sub_bus in [
+        "",
+        "not valid",
+        "1st",
+        "a..b",
+        "ä",
+        "/",
+        "trailing.",
+        "-",
+        &long,
+    ] {
+        let notification = Notification::new().summary("nowhere").finalize();
+        assert!(notification.show_at_bus(sub_bus).is_err(), "{:?}", sub_bus);
+        assert!(
+            notify_rust::get_capabilities_at_bus(sub_bus).is_err(),
+            "{:?}",
+            sub_bus
+        );
+        assert!(
+            notify_rust::close_notification_at_bus(1, sub_bus).is_err(),
+            "{:?}",
+            sub_bus
+        );
+    }
+
+    let handle = Notification::new()
+        .summary("odd")
+        .hint(Hint::Custom(String::new(), String::new()))
+        .hint(Hint::CustomInt("ünïcode".into(), i32::MIN))
+        .hint(Hint::Category(String::new()))
+        .hint(Hint::SoundName("\0".into()))
+        .hint(Hint::Transient(false))
+        .action("", "")
+        .show();
+    // an empty action key is refused before anything is sent
+    assert!(handle.is_err());
+    let handle = Notification::new()
+        .summary("odd")
+        .hint(Hint::Custom(String::new(), String::new()))
+        .hint(Hint::CustomInt("ünïcode".into(), i32::MIN))
+        .hint(Hint::Category(String::new()))
+        .show()
+        .unwrap();
+    assert_eq!(server.received(handle.id())[0].summary, "odd");
+}
+
+#[test]
+fn update_replaces_and_refreshes_id() {
+    use notify_rust::{Hint, Urgency};
+
+    let server = TestServer::shared();
+    let mut handle = Notification::new()
+        .summary("Downloading")
+        .hint(Hint::Urgency(Urgency::Low))
+        .show()
+        .unwrap();
+    let id = handle.id();
+    assert!(!handle.was_replaced());
+
+    handle
+        .summary("Download finished")
+        .hint(Hint::Urgency(Urgency::Critical));
+    assert_eq!(handle.update().unwrap(), id);
+    assert!(handle.was_replaced());
+
+    let received = server.received(id);
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[1].replaces_id, id);
+    assert_eq!(received[1].summary, "Download finished");
+    assert_eq!(u8::try_from(&received[1].hints["urgency"]).unwrap(), 2);
+
+    // the server hands out a new id once the original one expired
+    server.close_after(Duration::ZERO, id, 1);
+    thread::sleep(SIGNAL_DELAY);
+    let new_id = handle.update().unwrap();
+    assert_ne!(new_id, id);
+    assert_eq!(handle.id(), new_id);
+    assert!(!handle.was_replaced());
+    assert_eq!(server.received(new_id)[0].replaces_id, id);
+}
+
+#[test]
+fn update_follows_a_server_that_never_replaces() {
+    let server = TestServer::start_at("de.hoodie.Notification.debug_new_ids");
+    server.never_replace();
+    let mut handle = Notification::new()
+        .summary("first")
+        .show_at_bus("debug_new_ids")
+        .unwrap();
+    let first = handle.id();
+
+    handle.summary("second");
+    let second = handle.update().unwrap();
+    assert_ne!(second, first);
+    assert_eq!(handle.id(), second);
+    assert!(!handle.was_replaced());
+    let received = server.received(second);
+    assert_eq!(received[0].replaces_id, first);
+    assert_eq!(received[0].summary, "second");
+
+    // signals of the first notification are none of the handle's business anymore
+    server.close_later(first, 1);
+    server.close_after(SIGNAL_DELAY * 2, second, 2);
+    assert_eq!(
+        handle.wait_for_closed(Duration::from_secs(5)),
+        Ok(CloseReason::Dismissed)
+    );
+
+    // unchanged hints are sent again as they were
+    handle.hint(Hint::Category("transfer".into()));
+    let third = handle.update().unwrap();
+    let fourth = handle.update().unwrap();
+    assert_ne!(third, fourth);
+    assert_eq!(
+        <&str>::try_from(&server.received(fourth)[0].hints["category"]).unwrap(),
+        "transfer"
+    );
+
+    handle.close().unwrap();
+    assert_eq!(server.close_requests(fourth), 1);
+    assert_eq!(server.close_requests(third), 0);
+
+    let mut handle = Notification::new()
+        .summary("strict")
+        .require_stable_id(true)
+        .show_at_bus("debug_new_ids")
+        .unwrap();
+    let shown = handle.id();
+    let error = handle.update().unwrap_err();
+    assert!(
+        matches!(*error.kind(), ErrorKind::IdChanged { requested, returned } if requested == shown && returned == handle.id()),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn require_stable_id_fails_on_a_new_id() {
+    let server = TestServer::shared();
+    let mut handle = Notification::new()
+        .summary("Pinned")
+        .require_stable_id(true)
+        .show()
+        .unwrap();
+    let id = handle.id();
+    assert_eq!(handle.update().unwrap(), id);
+    assert!(handle.was_replaced());
+
+    server.close_after(Duration::ZERO, id, 1);
+    thread::sleep(SIGNAL_DELAY);
+    let error = handle.update().unwrap_err();
+    let returned = match *error.kind() {
+        ErrorKind::IdChanged {
+            requested,
+            returned,
+        } => {
+            assert_eq!(requested, id);
+            returned
+        }
+        ref other => panic!("unexpected error {:?}", other),
+    };
+    // the new notification is still shown and the handle follows it
+    assert_ne!(returned, id);
+    assert_eq!(handle.id(), returned);
+    assert!(!handle.was_replaced());
+
+    // sending with a stale id ahead of time fails the same way
+    let error = Notification::new()
+        .summary("Pinned again")
+        .id(id)
+        .require_stable_id(true)
+        .show()
+        .unwrap_err();
+    assert!(
+        matches!(*error.kind(), ErrorKind::IdChanged { requested, .. } if requested == id),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn reshow_brings_back_an_expired_notification() {
+    let server = TestServer::shared();
+    let mut handle = Notification::new().summary("Battery low").show().unwrap();
+    handle.close_on_drop(true);
+    let mut last_id = handle.id();
+
+    for _ in 0..2 {
+        server.close_later(handle.id(), 1);
+        assert_eq!(
+            handle.wait_for_closed(Duration::from_secs(5)),
+            Ok(CloseReason::Expired)
+        );
+
+        let id = handle.reshow().unwrap();
+        assert_eq!(handle.id(), id);
+        let received = server.received(id);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].replaces_id, last_id);
+        assert_eq!(received[0].summary, "Battery low");
+        last_id = id;
+    }
+
+    let mut polled = Notification::new().summary("polled").show().unwrap();
+    assert_eq!(polled.poll_event(), None);
+    server.close_after(Duration::ZERO, polled.id(), 1);
+    thread::sleep(SIGNAL_DELAY);
+    assert_eq!(
+        polled.poll_event(),
+        Some(Event::Closed(CloseReason::Expired))
+    );
+    assert_eq!(polled.poll_event(), None);
+    polled.reshow().unwrap();
+    assert_eq!(polled.poll_event(), None);
+    server.close_later(polled.id(), 2);
+    thread::sleep(SIGNAL_DELAY * 2);
+    assert_eq!(
+        polled.poll_event(),
+        Some(Event::Closed(CloseReason::Dismissed))
+    );
+
+    // shown again, so dropping the handle closes it like before the first expiry
+    drop(handle);
+    assert_eq!(server.close_requests(last_id), 1);
+}
+
+#[test]
+// the hash only covers the id, not the connection clippy is worried about
+#[allow(clippy::mutable_key_type)]
+fn handles_are_keyed_by_id() {
+    use std::collections::{HashMap, HashSet};
+
+    let _server = TestServer::shared();
+    let first = Notification::new().summary("first").show().unwrap();
+    let second = Notification::new().summary("second").show().unwrap();
+    let (first_id, second_id) = (first.id(), second.id());
+    assert_ne!(first, second);
+
+    let mut handles = HashSet::new();
+    assert!(handles.insert(first));
+    assert!(handles.insert(second));
+    assert_eq!(handles.get(&first_id).unwrap().summary, "first");
+    assert!(handles.contains(&second_id));
+
+    // a second handle for the same id is a duplicate
+    let connection = zbus::block_on(zbus::Connection::session()).unwrap();
+    let again =
+        notify_rust::NotificationHandle::from_parts(first_id, connection, Notification::new());
+    assert!(!handles.insert(again));
+    assert_eq!(handles.len(), 2);
+    assert!(handles.remove(&second_id));
+
+    let mut by_id = HashMap::new();
+    for handle in handles {
+        by_id.insert(handle.id(), handle);
+    }
+    assert_eq!(by_id[&first_id].summary, "first");
+}
+
+#[test]
+fn partial_updates_leave_out_sounds() {
+    let server = TestServer::shared();
+    let mut handle = Notification::new()
+        .summary("Downloading")
+        .sound_name("message-new-instant")
+        .hint(Hint::SoundFile("/tmp/ding.oga".into()))
+        .show()
+        .unwrap();
+    let id = handle.id();
+
+    handle.set_summary("Downloading 50%").unwrap();
+    handle.set_value(50).unwrap();
+    let received = server.received(id);
+    assert_eq!(received.len(), 3);
+    assert!(received[0].hints.contains_key("sound-name"));
+    assert_eq!(received[1].summary, "Downloading 50%");
+    for update in &received[1..] {
+        assert!(!update.hints.contains_key("sound-name"), "{:?}", update);
+        assert!(!update.hints.contains_key("sound-file"), "{:?}", update);
+    }
+    assert_eq!(i32::try_from(&received[2].hints["value"]).unwrap(), 50);
+
+    // the handle still has them
+    assert!(handle
+        .hints
+        .contains(&Hint::SoundName("message-new-instant".into())));
+    handle.replay_sounds(true).set_body("done").unwrap();
+    let replayed = server.received(id).pop().unwrap();
+    assert!(replayed.hints.contains_key("sound-name"));
+    assert!(replayed.hints.contains_key("sound-file"));
+}
+
+#[test]
+fn spawned_listener_stops_promptly() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("background").show().unwrap();
+    let id = handle.id();
+    let (event_tx, event_rx) = mpsc::channel();
+    let listener = handle
+        .spawn_listener(move |event| event_tx.send(event).unwrap())
+        .unwrap();
+
+    server.invoke_later(id, "open");
+    assert_eq!(
+        event_rx.recv_timeout(SIGNAL_DELAY * 3).unwrap(),
+        Event::ActionInvoked("open".into())
+    );
+
+    let stopping = Instant::now();
+    let handle = listener.stop();
+    assert!(stopping.elapsed() < Duration::from_millis(50));
+    assert_eq!(handle.id(), id);
+    // the handler went away with the thread
+    assert_eq!(event_rx.recv(), Err(mpsc::RecvError));
+}
+
+#[test]
+fn spawned_listener_ends_with_the_notification() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("background").show().unwrap();
+    let id = handle.id();
+    let (event_tx, event_rx) = mpsc::channel();
+    let listener = handle
+        .spawn_listener(move |event| event_tx.send(event).unwrap())
+        .unwrap();
+
+    server.reply_after(SIGNAL_DELAY, id, "thanks");
+    server.close_after(SIGNAL_DELAY * 2, id, 2);
+    listener.join();
+    assert_eq!(
+        event_rx.iter().collect::<Vec<_>>(),
+        [
+            Event::Replied("thanks".into()),
+            Event::Closed(CloseReason::Dismissed)
+        ]
+    );
+}
+
+#[test]
+fn waiting_only_subscribes_to_the_server() {
+    use notify_rust::ConnectionRef;
+
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("scoped").show().unwrap();
+    let own = match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => connection.unique_name(),
+    };
+    let notification_rules = move || {
+        common::match_rules_of(&own).map(|rules| {
+            rules
+                .into_iter()
+                .filter(|rule| rule.contains("interface='org.freedesktop.Notifications'"))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    // the impostor's signals never reach the waiter, so they can't wake it up
+    common::impersonate_later(handle.id(), "spoofed");
+    server.invoke_after(SIGNAL_DELAY * 2, handle.id(), "genuine");
+    let during = {
+        let notification_rules = notification_rules.clone();
+        thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY / 2);
+            notification_rules()
+        })
+    };
+    assert_eq!(
+        handle.wait().unwrap(),
+        Event::ActionInvoked("genuine".into())
+    );
+
+    if let Some(rules) = during.join().unwrap() {
+        assert_eq!(rules.len(), 1, "{:?}", rules);
+        assert!(rules[0].contains("sender=':"), "{}", rules[0]);
+        assert!(
+            rules[0].contains("path='/org/freedesktop/Notifications'"),
+            "{}",
+            rules[0]
+        );
+        // and they are gone once the wait is over
+        assert_eq!(notification_rules(), Some(Vec::new()));
+    }
+}
+
+#[test]
+fn sequential_waits_leave_no_rules_behind() {
+    use notify_rust::ConnectionRef;
+
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("twice").show().unwrap();
+    let own = match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => connection.unique_name(),
+    };
+
+    let mut during = Vec::new();
+    for action in ["first", "second"] {
+        server.invoke_later(handle.id(), action);
+        let own = own.clone();
+        let sampled = thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY / 2);
+            common::match_rules_of(&own)
+        });
+        assert_eq!(handle.wait().unwrap(), Event::ActionInvoked(action.into()));
+        during.push(sampled.join().unwrap());
+    }
+
+    if let [Some(first), Some(second)] = &during[..] {
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+        assert_eq!(common::match_rules_of(&own), Some(Vec::new()));
+    }
+}
+
+#[cfg(feature = "dbus")]
+#[test]
+fn dropped_handles_leave_their_connection_to_the_next_send() {
+    use notify_rust::ConnectionRef;
+
+    fn unique_name(handle: &notify_rust::NotificationHandle) -> Option<String> {
+        match handle.connection() {
+            ConnectionRef::Dbus(connection) => Some(connection.unique_name()),
+            // only dbus-rs keeps connections around
+            ConnectionRef::Zbus(_) => None,
+        }
+    }
+
+    let _server = TestServer::shared();
+    let first = Notification::new().summary("first").show().unwrap();
+    let own = match unique_name(&first) {
+        Some(own) => own,
+        None => return,
+    };
+    drop(first);
+    notify_rust::get_capabilities().unwrap();
+    let second = Notification::new().summary("second").show().unwrap();
+    assert_eq!(unique_name(&second).as_ref(), Some(&own));
+
+    // taken apart, the connection goes along with the parts
+    let (_, _connection, _) = second.into_parts();
+    let third = Notification::new().summary("third").show().unwrap();
+    assert_ne!(unique_name(&third), Some(own));
+}
+
+#[test]
+fn queries_reuse_the_handle_connection() {
+    use notify_rust::{get_capabilities_on, get_server_information_on, ConnectionRef};
+
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("connected").show().unwrap();
+    let connection = handle.connection();
+    assert_eq!(
+        get_capabilities_on(connection).unwrap(),
+        ["actions", "body"]
+    );
+    assert_eq!(
+        get_server_information_on(connection).unwrap().name,
+        "test-server"
+    );
+
+    let sender = match connection {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(connection) => connection.unique_name(),
+    };
+    assert_eq!(
+        server.methods_called_by(&sender),
+        ["Notify", "GetCapabilities", "GetServerInformation"]
+    );
+}
+
+#[test]
+fn subscription_sees_all_notifications() {
+    let server = TestServer::shared();
+    let (event_tx, event_rx) = mpsc::channel();
+    let subscription =
+        notify_rust::subscribe_all(move |id, event| event_tx.send((id, event)).unwrap()).unwrap();
+
+    let first = Notification::new().summary("first").show().unwrap();
+    let second = Notification::new().summary("second").show().unwrap();
+    server.invoke_later(first.id(), "open");
+    server.close_after(SIGNAL_DELAY * 2, second.id(), 1);
+
+    // other tests share the server, skip their notifications
+    let ours = [first.id(), second.id()];
+    let events = event_rx
+        .iter()
+        .filter(|(id, _)| ours.contains(id))
+        .take(2)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            (first.id(), Event::ActionInvoked("open".into())),
+            (second.id(), Event::Closed(CloseReason::Expired)),
+        ]
+    );
+
+    let stopping = Instant::now();
+    subscription.stop();
+    assert!(stopping.elapsed() < Duration::from_millis(50));
+}
+
+#[test]
+fn close_on_drop_closes_once() {
+    let server = TestServer::shared();
+
+    let mut dropped = Notification::new().summary("dropped").show().unwrap();
+    let dropped_id = dropped.id();
+    dropped.close_on_drop(true);
+    drop(dropped);
+    assert_eq!(server.close_requests(dropped_id), 1);
+
+    let mut closed = Notification::new().summary("closed").show().unwrap();
+    let closed_id = closed.id();
+    closed.close_on_drop(true);
+    closed.close().unwrap();
+    assert_eq!(server.close_requests(closed_id), 1);
+
+    let mut expired = Notification::new().summary("expired").show().unwrap();
+    let expired_id = expired.id();
+    expired.close_on_drop(true);
+    server.close_later(expired_id, 1);
+    expired.on_close(|| ());
+    assert_eq!(server.close_requests(expired_id), 0);
+
+    let unflagged = Notification::new().summary("unflagged").show().unwrap();
+    let unflagged_id = unflagged.id();
+    drop(unflagged);
+    assert_eq!(server.close_requests(unflagged_id), 0);
+}
+
+#[test]
+fn handle_from_parts_closes_the_original() {
+    use notify_rust::NotificationHandle;
+
+    let server = TestServer::shared();
+
+    let handle = Notification::new().summary("reused").show().unwrap();
+    let (id, connection, notification) = handle.into_parts();
+    assert_eq!(notification.summary, "reused");
+    let handle = NotificationHandle::from_parts(id, connection, notification);
+    assert_eq!(handle.id(), id);
+    handle.close().unwrap();
+    assert_eq!(server.close_requests(id), 1);
+
+    // as if the id was stored before a restart
+    let stored_id = Notification::new().summary("stored").show().unwrap().id();
+    let connection = zbus::block_on(zbus::Connection::session()).unwrap();
+    NotificationHandle::from_parts(stored_id, connection, Notification::new())
+        .close()
+        .unwrap();
+    assert_eq!(server.close_requests(stored_id), 1);
+}
+
+#[test]
+fn dispatched_handles_share_a_connection() {
+    use notify_rust::{HandleConnection, NotificationDispatcher, NotificationHandle};
+
+    let server = TestServer::shared();
+    let dispatcher = NotificationDispatcher::new().unwrap();
+
+    let mut handles = ["first", "second", "third"].map(|summary| {
+        let handle = dispatcher
+            .show(Notification::new().summary(summary))
+            .unwrap();
+        let (id, connection, notification) = handle.into_parts();
+        match connection {
+            HandleConnection::Zbus(ref connection) => {
+                assert_eq!(
+                    connection.unique_name(),
+                    dispatcher.connection().unique_name()
+                );
+            }
+            #[allow(unreachable_patterns)]
+            _ => panic!("dispatched over another connection"),
+        }
+        let mut handle = NotificationHandle::from_parts(id, connection, notification);
+        assert_eq!(handle.poll_event(), None); // subscribes
+        handle
+    });
+
+    // all signals arrive at once, in reverse order
+    for handle in handles.iter().rev() {
+        server.invoke_later(handle.id(), &handle.summary);
+    }
+    thread::sleep(SIGNAL_DELAY * 2);
+    for handle in &mut handles {
+        let summary = handle.summary.clone();
+        assert_eq!(handle.poll_event(), Some(Event::ActionInvoked(summary)));
+        assert_eq!(handle.poll_event(), None);
+    }
+}
+
+#[async_std::test]
+async fn proxy_talks_to_the_server_directly() {
+    use futures_lite::StreamExt;
+    use notify_rust::NotificationsProxy;
+
+    let server = TestServer::shared();
+    let connection = zbus::Connection::session().await.unwrap();
+    let proxy = NotificationsProxy::new(&connection).await.unwrap();
+    assert_eq!(proxy.get_capabilities().await.unwrap(), ["actions", "body"]);
+    assert_eq!(
+        proxy.get_server_information().await.unwrap().name,
+        "test-server"
+    );
+
+    let mut closed = proxy.receive_notification_closed().await.unwrap();
+    let id = proxy
+        .notify("app", 0, "", "raw", "", &[], Default::default(), -1)
+        .await
+        .unwrap();
+    assert_eq!(server.received(id)[0].summary, "raw");
+
+    proxy.close_notification(id).await.unwrap();
+    let args = closed.next().await.unwrap();
+    let args = args.args().unwrap();
+    assert_eq!((args.id, args.reason), (id, 3));
+}
+
+#[test]
+fn stopping_handle_action_before_the_signal_skips_the_handler() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("stopped early").show().unwrap();
+    server.invoke_after(SIGNAL_DELAY * 2, handle.id(), "too late");
+
+    let stop = notify_rust::StopHandle::new();
+    let stopper = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            thread::sleep(SIGNAL_DELAY);
+            stop.stop();
+            Instant::now()
+        })
+    };
+    notify_rust::handle_action_until(handle.id(), &stop, |_| panic!("stopped, but handled"))
+        .unwrap();
+    let stopped_at = stopper.join().unwrap();
+    assert!(stopped_at.elapsed() < Duration::from_millis(100));
+    assert!(stop.is_stopped());
+
+    // a stopped handle doesn't even start waiting, stopping again is fine
+    stop.stop();
+    let started = Instant::now();
+    notify_rust::handle_action_until(handle.id(), &stop, |_| panic!("stopped, but handled"))
+        .unwrap();
+    assert!(started.elapsed() < SIGNAL_DELAY);
+}
+
+#[test]
+fn stopping_handle_action_after_the_signal_keeps_the_response() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("handled").show().unwrap();
+    server.invoke_later(handle.id(), "open");
+
+    let stop = notify_rust::StopHandle::new();
+    let mut handled = None;
+    notify_rust::handle_action_until(handle.id(), &stop, |response| {
+        if let notify_rust::ActionResponse::Custom(action) = response {
+            handled = Some(action.to_string());
+        }
+    })
+    .unwrap();
+    assert_eq!(handled.as_deref(), Some("open"));
+
+    stop.stop();
+    stop.stop();
+    assert!(stop.is_stopped());
+}
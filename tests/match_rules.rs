@@ -0,0 +1,29 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+//! Counts the match rules of the whole bus, so this must not share a binary with other tests.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{bus_match_rules, TestServer};
+use notify_rust::Notification;
+
+#[async_std::test]
+async fn cancelled_wait_unsubscribes() {
+    let _server = TestServer::shared();
+    let handle = Notification::new()
+        .summary("never acted on")
+        .show_async()
+        .await
+        .unwrap();
+
+    let before = bus_match_rules();
+    let waited =
+        async_std::future::timeout(Duration::from_millis(100), handle.wait_for_action_async())
+            .await;
+    assert!(waited.is_err(), "nothing should have happened");
+
+    // the rule is removed in the background
+    async_std::task::sleep(Duration::from_millis(100)).await;
+    assert_eq!(bus_match_rules(), before);
+}
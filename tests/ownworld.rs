@@ -1,3 +1,4 @@
+#![allow(unexpected_cfgs)]
 #![cfg(feature = "server")]
 #![allow(unused_must_use)]
 #![cfg(all(unix, not(target_os = "macos")))]
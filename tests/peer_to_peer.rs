@@ -0,0 +1,34 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+//! Needs no bus at all, client and server talk over a socket pair.
+
+mod common;
+
+use std::{os::unix::net::UnixStream, thread};
+
+use common::TestServer;
+use notify_rust::{CloseReason, Event, Notification};
+
+#[test]
+fn roundtrip_over_a_socket_pair() {
+    let (server_socket, client_socket) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || TestServer::start_on_socket(server_socket));
+    let client = zbus::blocking::connection::Builder::async_io_unix_stream(client_socket)
+        .p2p()
+        .build()
+        .unwrap();
+    let server = server.join().unwrap();
+
+    let handle = Notification::new()
+        .summary("peer to peer")
+        .action("open", "Open")
+        .show_on(client.inner())
+        .unwrap();
+    assert_eq!(server.received(handle.id())[0].summary, "peer to peer");
+
+    server.invoke_later(handle.id(), "open");
+    assert_eq!(handle.wait().unwrap(), Event::ActionInvoked("open".into()));
+
+    let id = handle.id();
+    assert_eq!(handle.close_and_wait().unwrap(), CloseReason::CloseAction);
+    assert_eq!(server.close_requests(id), 1);
+}
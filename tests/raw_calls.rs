@@ -0,0 +1,49 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use common::TestServer;
+
+// one test, the server leaves the bus at the end
+#[test]
+fn raw_calls_reach_the_server() {
+    let _server = TestServer::start();
+
+    let information =
+        notify_rust::call_raw_zbus("org.freedesktop.Notifications", "GetServerInformation", &())
+            .unwrap();
+    let information = information
+        .into_iter()
+        .map(|value| String::try_from(value).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(information, ["test-server", "notify-rust", "0.1", "1.2"]);
+
+    #[cfg(feature = "dbus")]
+    {
+        use dbus::arg::messageitem::MessageItem;
+
+        let reply =
+            notify_rust::call_raw_dbus("org.freedesktop.Notifications", "GetCapabilities", &[])
+                .unwrap();
+        let capabilities = match reply.as_slice() {
+            [MessageItem::Array(capabilities)] => capabilities
+                .iter()
+                .map(|capability| capability.inner::<&str>().unwrap().to_owned())
+                .collect::<Vec<_>>(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert_eq!(capabilities, ["actions", "body"]);
+
+        let error = notify_rust::call_raw_dbus(
+            "org.freedesktop.Notifications",
+            "Unknown",
+            &[MessageItem::UInt32(1)],
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Unknown method"), "{}", error);
+    }
+
+    let reply = notify_rust::call_raw_zbus("org.freedesktop.Notifications", "Stop", &()).unwrap();
+    assert!(reply.is_empty());
+    assert!(notify_rust::get_capabilities().is_err());
+}
@@ -57,7 +57,8 @@ mod realworld {
             .body("you don't see me!")
             .show()
             .unwrap()
-            .close();
+            .close()
+            .unwrap();
     }
 
     #[test]
@@ -0,0 +1,90 @@
+#![cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+use common::TestServer;
+use notify_rust::{ConnectionRef, Notification, NotificationHandle};
+
+/// A session bus of its own at `socket`, killed once dropped.
+struct Daemon(Child);
+
+impl Daemon {
+    fn start(socket: &Path) -> Daemon {
+        let _ = std::fs::remove_file(socket);
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is not installed");
+        // listening once it printed its address
+        let mut address = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        Daemon(child)
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn unique_name(handle: &NotificationHandle) -> String {
+    match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(_) => unreachable!("show_async() always uses zbus"),
+    }
+}
+
+// one test, it swaps out the session bus of the whole test binary
+#[async_std::test]
+async fn show_async_survives_a_bus_restart() {
+    let socket = std::env::temp_dir().join(format!("notify-rust-bus-{}", std::process::id()));
+    let daemon = Daemon::start(&socket);
+    std::env::set_var(
+        "DBUS_SESSION_BUS_ADDRESS",
+        format!("unix:path={}", socket.display()),
+    );
+    let server = TestServer::start();
+    let _before = Notification::new()
+        .summary("before")
+        .show_async()
+        .await
+        .unwrap();
+
+    // takes the server and the shared connection down with it
+    drop((server, daemon));
+    let _daemon = Daemon::start(&socket);
+    let server = TestServer::start();
+
+    let after = Notification::new()
+        .summary("after")
+        .show_async()
+        .await
+        .unwrap();
+    assert_eq!(server.received(after.id())[0].summary, "after");
+
+    // the new connection is shared from now on
+    let again = Notification::new()
+        .summary("again")
+        .show_async()
+        .await
+        .unwrap();
+    assert_eq!(unique_name(&again), unique_name(&after));
+    assert_eq!(
+        server.methods_called_by(&unique_name(&after)),
+        ["Notify", "Notify"]
+    );
+}
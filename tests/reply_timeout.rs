@@ -0,0 +1,38 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+//! Slows the server down for everyone, so this must not share a binary with other tests.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::TestServer;
+use notify_rust::{error::ErrorKind, Notification};
+
+#[test]
+fn queries_give_up_on_a_slow_server() {
+    let server = TestServer::start();
+    let handle = Notification::new().summary("connected").show().unwrap();
+    server.delay_queries(Duration::from_secs(3));
+    let timeout = Duration::from_millis(500);
+
+    let started = Instant::now();
+    let error = notify_rust::get_capabilities_within(handle.connection(), timeout).unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::ReplyTimeout(t) if *t == timeout),
+        "{}",
+        error
+    );
+    let error =
+        notify_rust::get_server_information_within(handle.connection(), timeout).unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::ReplyTimeout(_)),
+        "{}",
+        error
+    );
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // patient enough
+    server.delay_queries(Duration::from_millis(100));
+    let capabilities = notify_rust::get_capabilities_within(handle.connection(), timeout);
+    assert_eq!(capabilities.unwrap(), ["actions", "body"]);
+}
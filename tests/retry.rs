@@ -0,0 +1,63 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{sync::mpsc, thread, time::Duration};
+
+use common::TestServer;
+use notify_rust::{error::ErrorKind, Notification};
+
+#[test]
+fn retries_until_server_is_up() {
+    let (server_tx, server_rx) = mpsc::channel();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        server_tx.send(TestServer::start()).unwrap();
+    });
+
+    let handle = Notification::new()
+        .summary("retry")
+        .show_with_retry(8, Duration::from_millis(50))
+        .unwrap();
+    let server = server_rx.recv().unwrap();
+    assert_eq!(handle.id(), 1);
+    assert_eq!(server.calls(), 1);
+
+    // the server is up, so a rejected notification is not retried
+    let error = Notification::new()
+        .summary("")
+        .show_with_retry(8, Duration::from_millis(50))
+        .unwrap_err();
+    assert!(error.to_string().contains("summary is empty"), "{}", error);
+    assert_eq!(server.calls(), 2);
+}
+
+#[test]
+#[allow(deprecated)]
+fn gives_up_after_all_attempts() {
+    let error = Notification::at_bus("retry_test")
+        .summary("nobody listens")
+        .show_with_retry(3, Duration::from_millis(10))
+        .unwrap_err();
+    assert!(
+        error.to_string().ends_with("(gave up after 3 attempts)"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn nobody_owning_the_bus_is_no_server() {
+    let error = Notification::at_bus("unowned_test")
+        .summary("nobody listens")
+        .show()
+        .unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::NoServer(_)), "{}", error);
+    let source = std::error::Error::source(&error).unwrap();
+    assert!(
+        error.to_string().ends_with(&source.to_string()),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,31 @@
+#![cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use common::TestServer;
+use notify_rust::Notification;
+
+#[async_std::test]
+async fn send_all_keeps_order_and_per_item_errors() {
+    let server = TestServer::shared();
+    let notifications = ["first", "", "third", "", "fifth"]
+        .iter()
+        .map(|summary| Notification::new().summary(summary).finalize())
+        .collect::<Vec<_>>();
+
+    let results = notify_rust::send_all(&notifications).await.unwrap();
+    assert_eq!(results.len(), 5);
+    for (notification, result) in notifications.iter().zip(&results) {
+        match result {
+            Ok(id) => assert_eq!(server.received(*id)[0].summary, notification.summary),
+            Err(error) => {
+                assert!(notification.summary.is_empty());
+                assert!(error.to_string().contains("summary is empty"), "{}", error);
+            }
+        }
+    }
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 3);
+
+    let ids = results.iter().flatten().collect::<Vec<_>>();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", ids);
+}
@@ -0,0 +1,61 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{TestServer, SIGNAL_DELAY};
+use notify_rust::{Event, Notification};
+
+/// Stops `server` after [`SIGNAL_DELAY`], the receiver gets the time it was gone.
+fn stop_later(server: TestServer) -> mpsc::Receiver<Instant> {
+    let (stopped_tx, stopped_rx) = mpsc::channel();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        server.stop();
+        stopped_tx.send(Instant::now()).unwrap();
+    });
+    stopped_rx
+}
+
+// both run in one test, only one server can own the name at a time
+#[test]
+fn waiting_ends_when_server_leaves() {
+    let server = TestServer::start();
+    let handle = Notification::new().summary("orphaned").show().unwrap();
+    let stopped = stop_later(server);
+    let events = handle.events().unwrap().collect::<Vec<_>>();
+    assert_eq!(events, [Event::ServerGone]);
+    assert!(stopped.recv().unwrap().elapsed() < Duration::from_secs(1));
+
+    let server = TestServer::start();
+    let handle = Notification::new().summary("orphaned").show().unwrap();
+    let stopped = stop_later(server);
+    let (action_tx, action_rx) = mpsc::channel::<String>();
+    handle.wait_for_action(move |action| action_tx.send(action.to_owned()).unwrap());
+    assert!(stopped.recv().unwrap().elapsed() < Duration::from_secs(1));
+    assert_eq!(action_rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+
+    // a restarted server takes the name over right away, without a gap
+    let server = TestServer::start();
+    let handle = Notification::new().summary("restarted").show().unwrap();
+    let (restarted_tx, restarted_rx) = mpsc::channel();
+    thread::spawn(move || {
+        thread::sleep(SIGNAL_DELAY);
+        restarted_tx
+            .send((TestServer::start(), Instant::now()))
+            .unwrap();
+    });
+    let events = handle.events().unwrap().collect::<Vec<_>>();
+    assert_eq!(events, [Event::ServerGone]);
+    let (successor, restarted) = restarted_rx.recv().unwrap();
+    assert!(restarted.elapsed() < Duration::from_secs(1));
+    drop(server);
+
+    let handle = Notification::new().summary("after").show().unwrap();
+    assert_eq!(successor.received(handle.id())[0].summary, "after");
+}
@@ -0,0 +1,15 @@
+#![cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use common::TestServer;
+
+#[async_std::test]
+async fn server_information_async_parses_the_spec_version() {
+    let _server = TestServer::shared();
+
+    let info = notify_rust::get_server_information_async().await.unwrap();
+    assert_eq!(info.name, "test-server");
+    assert_eq!(info.spec_version, "1.2");
+    assert_eq!(info.spec_version_parsed(), Some((1, 2)));
+}
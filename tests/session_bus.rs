@@ -0,0 +1,59 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{env, fs, os::unix::fs::symlink, path::PathBuf};
+
+use common::TestServer;
+use notify_rust::{error::ErrorKind, Notification};
+
+/// A directory of its own for a fake `XDG_RUNTIME_DIR`.
+fn runtime_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("notify-rust-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// one test, it changes the environment of the whole test binary
+#[test]
+fn finds_the_bus_in_the_runtime_dir() {
+    let server = TestServer::start();
+    let address = env::var("DBUS_SESSION_BUS_ADDRESS").unwrap();
+    let socket = match address
+        .strip_prefix("unix:path=")
+        .and_then(|rest| rest.split(',').next())
+    {
+        Some(socket) => PathBuf::from(socket),
+        // e.g. an abstract socket, which has no path to link to
+        None => return,
+    };
+    env::remove_var("DBUS_SESSION_BUS_ADDRESS");
+
+    let empty = runtime_dir("no-bus");
+    env::set_var("XDG_RUNTIME_DIR", &empty);
+    let error = Notification::new().summary("nowhere").show().unwrap_err();
+    let expected = format!("unix:path={}", empty.join("bus").display());
+    assert!(
+        matches!(error.kind(), ErrorKind::NoSessionBus { tried } if *tried == [expected.clone()]),
+        "{}",
+        error
+    );
+    assert!(error.to_string().contains(&expected), "{}", error);
+    let stop = notify_rust::StopHandle::new();
+    let error =
+        notify_rust::handle_action_until(1, &stop, |_| panic!("no bus, but handled")).unwrap_err();
+    assert!(
+        matches!(error.kind(), ErrorKind::NoSessionBus { .. }),
+        "{}",
+        error
+    );
+
+    let linked = runtime_dir("bus");
+    symlink(&socket, linked.join("bus")).unwrap();
+    env::set_var("XDG_RUNTIME_DIR", &linked);
+    let handle = Notification::new().summary("found").show().unwrap();
+    assert_eq!(server.received(handle.id())[0].summary, "found");
+
+    env::set_var("DBUS_SESSION_BUS_ADDRESS", address);
+}
@@ -0,0 +1,76 @@
+#![cfg(all(feature = "async", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use common::TestServer;
+use notify_rust::{CloseReason, ConnectionRef, Notification, NotificationHandle};
+
+fn unique_name(handle: &NotificationHandle) -> String {
+    match handle.connection() {
+        ConnectionRef::Zbus(connection) => connection.unique_name().unwrap().to_string(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(_) => unreachable!("show_async() always uses zbus"),
+    }
+}
+
+async fn show(summary: &str) -> NotificationHandle {
+    Notification::new()
+        .summary(summary)
+        .show_async()
+        .await
+        .unwrap()
+}
+
+// one test, the shared connection is global to the test binary
+#[async_std::test]
+async fn show_async_shares_one_connection() {
+    let server = TestServer::shared();
+
+    let first = show("first").await;
+    let second = show("second").await;
+    let shared = unique_name(&first);
+    assert_eq!(unique_name(&second), shared);
+    assert_eq!(server.methods_called_by(&shared), ["Notify", "Notify"]);
+
+    notify_rust::reset_connection();
+    let reset = show("after reset").await;
+    assert_ne!(unique_name(&reset), shared);
+
+    // a broken connection is replaced on the next send
+    let broken = match reset.connection() {
+        ConnectionRef::Zbus(connection) => connection.clone(),
+        #[cfg(feature = "dbus")]
+        ConnectionRef::Dbus(_) => unreachable!(),
+    };
+    broken.close().await.unwrap();
+    let reconnected = show("after disconnect").await;
+    assert_ne!(unique_name(&reconnected), unique_name(&reset));
+    assert_eq!(unique_name(&show("again").await), unique_name(&reconnected));
+}
+
+#[async_std::test]
+async fn show_async_on_uses_the_given_connection() {
+    let server = TestServer::shared();
+    let connection = zbus::Connection::session().await.unwrap();
+    let own = connection.unique_name().unwrap().to_string();
+
+    let mut handles = Vec::new();
+    for summary in ["one", "two", "three"] {
+        let notification = Notification::new().summary(summary).finalize();
+        handles.push(notification.show_async_on(&connection).await.unwrap());
+    }
+    for handle in &handles {
+        assert_eq!(unique_name(handle), own);
+    }
+    assert_eq!(
+        server.methods_called_by(&own),
+        ["Notify", "Notify", "Notify"]
+    );
+
+    // signals arrive on the same connection
+    server.close_later(handles[1].id(), 2);
+    assert_eq!(
+        handles[1].wait_for_closed_async().await.unwrap(),
+        CloseReason::Dismissed
+    );
+}
@@ -0,0 +1,83 @@
+#![cfg(all(feature = "dbus", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{sync::mpsc::Receiver, time::Duration};
+
+use common::{TestServer, SIGNAL_DELAY};
+use notify_rust::{CloseReason, Event, Notification, SignalDispatcher};
+
+const STEP: Duration = Duration::from_millis(50);
+
+/// Everything `events` receives until the dispatcher lets go of it.
+fn collect(events: Receiver<Event>) -> Vec<Event> {
+    let mut collected = Vec::new();
+    while let Ok(event) = events.recv_timeout(Duration::from_secs(5)) {
+        collected.push(event);
+    }
+    collected
+}
+
+#[test]
+fn interleaved_signals_reach_their_own_notification() {
+    let server = TestServer::shared();
+    let dispatcher = SignalDispatcher::new().unwrap();
+    let (a, a_events) = dispatcher.show(Notification::new().summary("a")).unwrap();
+    let (b, b_events) = dispatcher.show(Notification::new().summary("b")).unwrap();
+    let (c, c_events) = dispatcher.show(Notification::new().summary("c")).unwrap();
+
+    server.invoke_after(SIGNAL_DELAY, b, "open b");
+    server.invoke_after(SIGNAL_DELAY + STEP, a, "open a");
+    server.reply_after(SIGNAL_DELAY + STEP * 2, c, "hi c");
+    server.close_after(SIGNAL_DELAY + STEP * 3, b, 1);
+    server.invoke_after(SIGNAL_DELAY + STEP * 4, c, "open c");
+    server.close_after(SIGNAL_DELAY + STEP * 5, a, 2);
+    server.close_after(SIGNAL_DELAY + STEP * 6, c, 3);
+
+    assert_eq!(
+        collect(a_events),
+        [
+            Event::ActionInvoked("open a".into()),
+            Event::Closed(CloseReason::Dismissed)
+        ]
+    );
+    assert_eq!(
+        collect(b_events),
+        [
+            Event::ActionInvoked("open b".into()),
+            Event::Closed(CloseReason::Expired)
+        ]
+    );
+    assert_eq!(
+        collect(c_events),
+        [
+            Event::Replied("hi c".into()),
+            Event::ActionInvoked("open c".into()),
+            Event::Closed(CloseReason::CloseAction)
+        ]
+    );
+    // closed notifications are forgotten
+    assert!(!dispatcher.is_registered(a));
+    assert!(!dispatcher.is_registered(b));
+    assert!(!dispatcher.is_registered(c));
+    dispatcher.stop();
+}
+
+#[test]
+fn ids_register_while_the_dispatcher_runs() {
+    let server = TestServer::shared();
+    let dispatcher = SignalDispatcher::new().unwrap();
+    let handle = Notification::new().summary("late").show().unwrap();
+
+    let events = dispatcher.register(handle.id());
+    assert!(dispatcher.is_registered(handle.id()));
+    server.close_later(handle.id(), 2);
+    assert_eq!(collect(events), [Event::Closed(CloseReason::Dismissed)]);
+
+    let other = Notification::new().summary("unregistered").show().unwrap();
+    let events = dispatcher.register(other.id());
+    assert!(dispatcher.unregister(other.id()));
+    assert!(!dispatcher.unregister(other.id()));
+    server.close_later(other.id(), 2);
+    assert_eq!(collect(events), []);
+}
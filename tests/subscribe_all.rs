@@ -0,0 +1,43 @@
+#![cfg(all(feature = "zbus", feature = "async", unix, not(target_os = "macos")))]
+//! Counts the match rules of the whole bus, so this must not share a binary with other tests.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{bus_match_rules, TestServer, SIGNAL_DELAY};
+use futures_lite::StreamExt;
+use notify_rust::{CloseReason, Event, Notification};
+
+#[async_std::test]
+async fn stream_yields_every_notification_and_unsubscribes() {
+    let server = TestServer::shared();
+    let before = bus_match_rules();
+
+    let events = notify_rust::subscribe_all_async().await.unwrap();
+    let first = Notification::new()
+        .summary("first")
+        .show_async()
+        .await
+        .unwrap();
+    let second = Notification::new()
+        .summary("second")
+        .show_async()
+        .await
+        .unwrap();
+    assert_ne!(first.id(), second.id());
+    server.invoke_later(first.id(), "open");
+    server.close_after(SIGNAL_DELAY * 2, second.id(), 2);
+
+    assert_eq!(
+        events.take(2).collect::<Vec<_>>().await,
+        [
+            (first.id(), Event::ActionInvoked("open".into())),
+            (second.id(), Event::Closed(CloseReason::Dismissed)),
+        ]
+    );
+
+    // the rule is removed in the background
+    async_std::task::sleep(Duration::from_millis(100)).await;
+    assert_eq!(bus_match_rules(), before);
+}
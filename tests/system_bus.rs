@@ -0,0 +1,25 @@
+#![cfg(all(feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use common::TestServer;
+use notify_rust::Notification;
+
+/// Needs the busconfig of `examples/system_bus.rs` installed for the user running the test.
+#[test]
+#[ignore = "needs a system bus policy for de.hoodie.Notification.bridge"]
+fn sends_to_a_bridge_on_the_system_bus() {
+    let server = TestServer::start_on_system_bus_at("de.hoodie.Notification.bridge");
+
+    let handle = Notification::new()
+        .summary("from the system bus")
+        .on_system_bus(true)
+        .show_at_bus("bridge")
+        .unwrap();
+    let id = handle.id();
+    assert_eq!(server.received(id)[0].summary, "from the system bus");
+
+    // the handle stays on the system bus
+    handle.close().unwrap();
+    assert_eq!(server.close_requests(id), 1);
+}
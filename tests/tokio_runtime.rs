@@ -0,0 +1,62 @@
+#![cfg(all(feature = "tokio", unix, not(target_os = "macos")))]
+//! Run with `--no-default-features --features tokio`, and with `images` added.
+//!
+//! The test server runs on blocking zbus, async tests start it outside of the runtime.
+//! The shared connection lives on the runtime that opened it, so there is one async test only.
+
+mod common;
+
+use common::TestServer;
+use notify_rust::{CloseReason, Notification};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sends_and_waits_for_the_close_on_tokio() {
+    let server = tokio::task::block_in_place(TestServer::shared);
+    let handle = Notification::new()
+        .summary("from tokio")
+        .show_async()
+        .await
+        .unwrap();
+    server.close_later(handle.id(), 2);
+    assert_eq!(
+        handle.wait_for_closed_async().await.unwrap(),
+        CloseReason::Dismissed
+    );
+
+    // the key of an image depends on the spec version, asking for it must not block the runtime
+    #[cfg(feature = "images")]
+    {
+        let image = notify_rust::Image::from_rgb(2, 2, vec![0; 2 * 2 * 3]).unwrap();
+        let handle = Notification::new()
+            .summary("pictured on tokio")
+            .image_data(image)
+            .show_async()
+            .await
+            .unwrap();
+        let received = server.received(handle.id());
+        assert!(received[0].hints.contains_key("image-data"));
+    }
+}
+
+#[test]
+fn blocking_calls_bring_their_own_runtime() {
+    let server = TestServer::shared();
+    let handle = Notification::new().summary("blocking").show().unwrap();
+    server.close_later(handle.id(), 1);
+    assert_eq!(
+        handle.wait_for_closed(std::time::Duration::from_secs(5)),
+        Ok(CloseReason::Expired)
+    );
+
+    #[cfg(feature = "images")]
+    {
+        let image = notify_rust::Image::from_rgb(2, 2, vec![0; 2 * 2 * 3]).unwrap();
+        let handle = Notification::new()
+            .summary("pictured blocking")
+            .image_data(image)
+            .show()
+            .unwrap();
+        let received = server.received(handle.id());
+        assert!(received[0].hints.contains_key("image-data"));
+    }
+}
@@ -0,0 +1,140 @@
+#![cfg(all(feature = "tracing", feature = "zbus", unix, not(target_os = "macos")))]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use common::TestServer;
+use notify_rust::{CloseReason, Event, Notification};
+use tracing::{
+    field::{Field, Visit},
+    span, Metadata, Subscriber,
+};
+
+type Fields = HashMap<&'static str, String>;
+
+struct Recorder<'a>(&'a mut Fields);
+
+impl Visit for Recorder<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+/// Keeps the fields of every span and event.
+#[derive(Clone, Default)]
+struct Capture {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, (&'static str, Fields)>>>,
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl Capture {
+    fn spans_named(&self, name: &str) -> Vec<Fields> {
+        let spans = self.spans.lock().unwrap();
+        spans
+            .values()
+            .filter(|(span, _)| *span == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fields = Fields::new();
+        attributes.record(&mut Recorder(&mut fields));
+        let name = attributes.metadata().name();
+        self.spans.lock().unwrap().insert(id, (name, fields));
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+        if let Some((_, fields)) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(&mut Recorder(fields));
+        }
+    }
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = Fields::new();
+        event.record(&mut Recorder(&mut fields));
+        self.events.lock().unwrap().push(fields);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn sends_and_signals_are_traced() {
+    let server = TestServer::start();
+    let capture = Capture::default();
+
+    tracing::subscriber::with_default(capture.clone(), || {
+        let handle = Notification::new()
+            .appname("traced")
+            .summary("traced")
+            .timeout(1500)
+            .hint(notify_rust::Hint::Category("email".into()))
+            .show()
+            .unwrap();
+        server.invoke_then_close_later(handle.id(), "open", 2);
+        assert_eq!(
+            handle.events().unwrap().collect::<Vec<_>>(),
+            [
+                Event::ActionInvoked("open".into()),
+                Event::Closed(CloseReason::Dismissed)
+            ]
+        );
+
+        let spans = capture.spans_named("notify");
+        assert_eq!(spans.len(), 1, "{:?}", spans);
+        let span = &spans[0];
+        assert_eq!(span["appname"], "traced");
+        assert_eq!(span["replaces_id"], "0");
+        assert_eq!(span["id"], handle.id().to_string());
+        assert_eq!(span["timeout"], "1500");
+        assert_eq!(span["hints"], "1");
+        assert_eq!(span["image_bytes"], "0");
+    });
+
+    let events = capture.events.lock().unwrap();
+    let messages = events
+        .iter()
+        .filter_map(|fields| fields.get("message"))
+        .collect::<Vec<_>>();
+    assert!(
+        messages.iter().any(|m| *m == "ActionInvoked"),
+        "{:?}",
+        events
+    );
+    assert!(
+        messages.iter().any(|m| *m == "NotificationClosed"),
+        "{:?}",
+        events
+    );
+    let invoked = events
+        .iter()
+        .find(|fields| {
+            fields
+                .get("message")
+                .map_or(false, |m| m == "ActionInvoked")
+        })
+        .unwrap();
+    assert_eq!(invoked["action"], "open");
+}